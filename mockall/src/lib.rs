@@ -32,6 +32,7 @@
 //! * [`Call counts`](#call-counts)
 //! * [`Sequences`](#sequences)
 //! * [`Checkpoints`](#checkpoints)
+//! * [`Multithreaded tests`](#multithreaded-tests)
 //! * [`Reference arguments`](#reference-arguments)
 //! * [`Reference return values`](#reference-return-values)
 //! * [`impl Trait`](#impl-trait)
@@ -329,6 +330,14 @@
 //! # }
 //! ```
 //!
+//! When the calls come from different threads, their order may be a matter of
+//! chance.  A [`Sequence::blocking`](struct.Sequence.html#method.blocking)
+//! sequence fixes that.  A call that arrives too early will wait for its
+//! predecessors to be satisfied rather than panic, so the sequence drives the
+//! interleaving of the threads.  It only panics if the wait exceeds a timeout.
+//! Note that a call counts as satisfied as soon as it is admitted, so its
+//! successor may proceed while its `returning` closure is still running.
+//!
 //! ## Checkpoints
 //!
 //! Sometimes its useful to validate all expectations mid-test, throw them away,
@@ -373,6 +382,135 @@
 //! # }
 //! ```
 //!
+//! ## Multithreaded tests
+//!
+//! Mock objects are `Send` and `Sync`, so they can be shared with background
+//! threads.  When they are, the test thread often needs to know when the
+//! background thread has called the mock.  For that, an Expectation's `handle`
+//! method returns an [`ExpectationHandle`], whose
+//! [`wait_for_calls`](struct.ExpectationHandle.html#method.wait_for_calls)
+//! method blocks until the Expectation has been called a certain number of
+//! times.  Or, every mock object implements the [`WaitUntilSatisfied`] trait,
+//! whose `wait_until_satisfied` method blocks until all of its expectations
+//! have been called their minimum number of times.  Both take a timeout, and
+//! return [`Timeout`] if it elapses first.
+//!
+//! ```
+//! # use mockall::*;
+//! use mockall::WaitUntilSatisfied;
+//! # use std::{sync::Arc, thread, time::Duration};
+//! #[automock]
+//! trait Foo {
+//!     fn foo(&self, x: u32);
+//! }
+//!
+//! # fn main() {
+//! let mut mock = MockFoo::new();
+//! mock.expect_foo()
+//!     .times(2)
+//!     .return_const(());
+//! let mock = Arc::new(mock);
+//! let mock2 = mock.clone();
+//! thread::spawn(move || {
+//!     mock2.foo(1);
+//!     mock2.foo(2);
+//! });
+//! mock.wait_until_satisfied(Duration::from_secs(10)).unwrap();
+//! # }
+//! ```
+//!
+//! To reproduce a race deterministically, a mocked call can be made to pause
+//! until the test lets it continue.  Attach a [`Latch`] to the Expectation with
+//! `blocking_on`.  Every matching call will then block inside the mock until
+//! the test calls [`Latch::release`].  Meanwhile,
+//! [`Latch::wait_entered`](struct.Latch.html#method.wait_entered) tells the
+//! test when a caller is parked.
+//!
+//! ```
+//! # use mockall::*;
+//! # use std::{sync::Arc, thread, time::Duration};
+//! #[automock]
+//! trait Foo {
+//!     fn foo(&self) -> u32;
+//! }
+//!
+//! # fn main() {
+//! let latch = Latch::new();
+//! let mut mock = MockFoo::new();
+//! mock.expect_foo()
+//!     .blocking_on(&latch)
+//!     .return_const(42u32);
+//! let mock = Arc::new(mock);
+//! let mock2 = mock.clone();
+//! let t = thread::spawn(move || mock2.foo());
+//! latch.wait_entered(Duration::from_secs(10)).unwrap();
+//! // foo is now in progress on the other thread
+//! latch.release();
+//! assert_eq!(42, t.join().unwrap());
+//! # }
+//! ```
+//!
+//! When a background thread calls a mock object in a way that violates its
+//! expectations, the panic happens on that thread, where a thread pool might
+//! swallow it.  So Mockall records such failures, and raises them again on
+//! whichever thread next calls the mock object's `checkpoint` method or drops
+//! it.  Only Mockall's own failures get recorded: a call that matches no
+//! expectation, too many calls, or a sequence violation.  Panics from
+//! `returning` closures don't, nor do any panics on the thread that created
+//! the mock object, so `catch_unwind` works as usual there.  The expectations
+//! of static methods and mocked functions belong to their `Context` instead,
+//! so their failures are raised again by the `Context`'s `checkpoint` or when
+//! it drops, unless they happened on the thread that created the `Context`.
+//!
+//! ```should_panic(expected = "No matching expectation found")
+//! # use mockall::*;
+//! # use std::{sync::Arc, thread};
+//! #[automock]
+//! trait Foo {
+//!     fn foo(&self, x: u32);
+//! }
+//!
+//! # fn main() {
+//! let mut mock = MockFoo::new();
+//! mock.expect_foo()
+//!     .with(predicate::eq(1))
+//!     .return_const(());
+//! let mock = Arc::new(mock);
+//! let mock2 = mock.clone();
+//! // The background thread panics, but its result is ignored
+//! let _ = thread::spawn(move || mock2.foo(2)).join();
+//! drop(mock);     // Panics!
+//! # }
+//! ```
+//!
+//! Expectations can also assert which threads call them.  `on_thread` requires
+//! every call to come from the given thread, and `not_on_thread` forbids calls
+//! from the given thread.  `exclusive` reports any call that overlaps another
+//! call to the same method, such as from another thread.  Its scope is a
+//! single method of a single mock object: overlapping calls to two different
+//! methods, or to the same method of two different mock objects, are not
+//! reported.  For generic methods, each set of generic parameters counts as a
+//! separate method.
+//!
+//! ```should_panic(expected = "may only be called on thread")
+//! # use mockall::*;
+//! # use std::thread;
+//! #[automock]
+//! trait Foo {
+//!     fn foo(&self);
+//! }
+//!
+//! # fn main() {
+//! let mut mock = MockFoo::new();
+//! mock.expect_foo()
+//!     .on_thread(thread::current().id())
+//!     .return_const(());
+//! thread::scope(|s| {
+//!     s.spawn(|| mock.foo());    // Panics!
+//! });
+//! # }
+//! ```
+//!
 //! ## Reference arguments
 //!
 //! Mockall can mock methods with reference arguments, too.  There's one catch:
@@ -395,6 +533,28 @@
 //! assert_eq!(6, mock.foo(&5));
 //! ```
 //!
+//! Trait object arguments need not be `'static`.  If a trait object's lifetime
+//! is elided or non-`'static`, then the argument can only be matched with
+//! [`withf`](#matching-arguments), whose closure must accept any lifetime.
+//!
+//! ```
+//! # use mockall::*;
+//! pub trait Visitor<'a> {
+//!     fn name(&self) -> &'a str;
+//! }
+//! #[automock]
+//! trait Foo {
+//!     fn visit(&self, v: &mut dyn Visitor<'_>) -> usize;
+//! }
+//!
+//! # fn main() {
+//! let mut mock = MockFoo::new();
+//! mock.expect_visit()
+//!     .withf(|v| v.name() == "x")
+//!     .returning(|v| v.name().len());
+//! # }
+//! ```
+//!
 //! ## Reference return values
 //!
 //! Mockall can also use reference return values.  There is one restriction: the
@@ -480,6 +640,58 @@
 //! # }
 //! ```
 //!
+//! Both kinds of Expectation can also compute the returned reference from the
+//! method's arguments, with `returning_from`.  It takes some storage, which
+//! the Expectation will own, and a closure that returns a reference into that
+//! storage.
+//!
+//! ```
+//! # use mockall::*;
+//! # use std::collections::HashMap;
+//! struct Thing(u32);
+//!
+//! #[automock]
+//! trait Container {
+//!     fn get(&self, i: u32) -> &Thing;
+//! }
+//!
+//! # fn main() {
+//! let mut things = HashMap::new();
+//! things.insert(1, Thing(42));
+//! things.insert(2, Thing(43));
+//! let mut mock = MockContainer::new();
+//! mock.expect_get()
+//!     .returning_from(things, |m, i| &m[&i]);
+//!
+//! assert_eq!(43, mock.get(2).0);
+//! # }
+//! ```
+//!
+//! The same goes for methods whose return values borrow from `self` without
+//! being bare references, like `Option<&T>` or `std::slice::Iter<'_, T>`.
+//! Their Expectations can only use `returning_from`.  Generic methods of this
+//! kind are not yet supported.
+//!
+//! ```
+//! # use mockall::*;
+//! # use std::collections::HashMap;
+//! #[automock]
+//! trait Container {
+//!     fn get(&self, k: &str) -> Option<&u32>;
+//! }
+//!
+//! # fn main() {
+//! let mut map = HashMap::new();
+//! map.insert("one".to_owned(), 1);
+//! let mut mock = MockContainer::new();
+//! mock.expect_get()
+//!     .returning_from(map, |m, k| m.get(k));
+//!
+//! assert_eq!(Some(&1), mock.get("one"));
+//! assert_eq!(None, mock.get("two"));
+//! # }
+//! ```
+//!
 //! Unsized types that are common targets for
 //! [`Deref`](https://doc.rust-lang.org/stable/std/ops/trait.Deref.html)
 //! are special.  Mockall
@@ -572,8 +784,195 @@
 //! those would be to refactor them to return named (but possibly opaque) types
 //! instead.
 //!
+//! Methods that return iterators, either as `impl Iterator` or `Box<dyn
+//! Iterator>`, have a `return_iter` method too.  It takes anything that can be
+//! cloned and turned into an iterator, and returns a fresh iterator on every
+//! call.
+//!
+//! ```
+//! # use mockall::*;
+//! struct Foo {}
+//! #[automock]
+//! impl Foo {
+//!     fn foo(&self) -> impl Iterator<Item=u32> {
+//!         // ...
+//!         # 0..0
+//!     }
+//! }
+//!
+//! # fn main() {
+//! let mut mock = MockFoo::new();
+//! mock.expect_foo()
+//!     .return_iter(vec![1, 2, 3]);
+//! assert_eq!(vec![1, 2, 3], mock.foo().collect::<Vec<_>>());
+//! # }
+//! ```
+//!
+//! Trait methods may return `impl Trait` too, and the mock method keeps the
+//! trait's signature.  A trait method returning `impl Future` is a special
+//! case: because `dyn Future` isn't `Unpin`, its Expectation returns
+//! `Pin<Box<dyn Future>>` instead.  Inherent methods always return the plain
+//! `Box<dyn Trait>`.  `async fn` methods, on the other hand, are
+//! mocked as though they were synchronous: the Expectation returns the
+//! future's `Output` type directly, and the mock method wraps it in a ready
+//! future.  A trait's `async fn` calls its Expectation as soon as the mock
+//! method is called, so that `must_be_polled` can track the future.  Other
+//! `async fn` methods and functions call their Expectations when their futures
+//! are first polled.
+//!
+//! ```
+//! # use mockall::*;
+//! # use std::future::Future;
+//! #[automock]
+//! trait Foo {
+//!     fn foo(&self) -> impl Future<Output=u32> + Send;
+//!     async fn bar(&self, x: u32) -> u32;
+//! }
+//!
+//! # fn main() {
+//! let mut mock = MockFoo::new();
+//! mock.expect_foo()
+//!     .returning(|| Box::pin(async { 42 }));
+//! mock.expect_bar()
+//!     .returning(|x| x + 1);
+//! # }
+//! ```
+//!
+//! Expectations for methods that return futures also have a few extra
+//! methods.  `return_ready` returns a future that's immediately ready with a
+//! constant value, and `returning_async` takes a closure that returns a future,
+//! such as an `async` block.  `return_after_pending(n, v)` returns a future
+//! that yields `Poll::Pending` `n` times, waking its task each time, before
+//! resolving to `v`.  That's useful for testing code that polls futures
+//! itself.  None of them depend on any particular executor.
+//!
+//! ```
+//! # use mockall::*;
+//! # use std::future::Future;
+//! #[automock]
+//! trait Foo {
+//!     fn foo(&self, x: u32) -> impl Future<Output=u32> + Send;
+//! }
+//!
+//! # fn main() {
+//! let mut mock = MockFoo::new();
+//! mock.expect_foo()
+//!     .returning_async(|x| async move { x + 1 });
+//! # }
+//! ```
+//!
+//! A common bug in async code is to create a future and then drop it without
+//! ever `.await`ing it.  To catch that, set `must_be_polled` on the
+//! Expectation.  Then the Expectation will panic when it's checkpointed or
+//! dropped if any future that it returned was never polled to completion.
+//! That works for the `async fn` methods of traits too, including
+//! `#[async_trait]` traits mocked with `#[automock]`, and for methods that
+//! return `impl Future`.  It isn't available for other `async fn` methods,
+//! which don't call their Expectations until their futures are polled.
+//!
+//! ```should_panic
+//! # use mockall::*;
+//! # use std::future::Future;
+//! #[automock]
+//! trait Foo {
+//!     fn foo(&self) -> impl Future<Output=u32> + Send;
+//! }
+//!
+//! # fn main() {
+//! let mut mock = MockFoo::new();
+//! mock.expect_foo()
+//!     .must_be_polled()
+//!     .return_ready(42u32);
+//! let _ = mock.foo(); // Oops!  Forgot to .await it.
+//! mock.checkpoint();  // panics!
+//! # }
+//! ```
+//!
+//! Traits decorated with
+//! [`#[async_trait]`](https://docs.rs/async-trait) work the same way, whether
+//! `#[automock]` comes before or after `#[async_trait]`.  Their Expectations'
+//! `returning` closures return the plain output value, not a boxed future.
+//!
+//! ```
+//! # use mockall::*;
+//! # use async_trait::async_trait;
+//! #[automock]
+//! #[async_trait]
+//! trait Foo {
+//!     async fn foo(&self, x: u32) -> u32;
+//! }
+//!
+//! # fn main() {
+//! let mut mock = MockFoo::new();
+//! mock.expect_foo()
+//!     .returning(|x| x + 1);
+//! # }
+//! ```
+//!
 //! See Also [`impl-trait-for-returning-complex-types-with-ease.html`](https://rust-lang-nursery.github.io/edition-guide/rust-2018/trait-system/impl-trait-for-returning-complex-types-with-ease)
 //!
+//! Arguments of type `impl Trait` are converted to a concrete type for the
+//! Expectation, so they can be matched with ordinary predicates:
+//!
+//! * `impl Into<T>` becomes `T`.
+//! * `impl AsRef<T>` and `impl Borrow<T>` become `&T`.
+//! * `impl IntoIterator<Item=T>` and `impl Iterator<Item=T>` become `Vec<T>`.
+//! * Closures, like `impl Fn(u32) -> u32`, become `Box<dyn Fn(u32) -> u32>`.
+//! * Any other `impl Trait` becomes `&dyn Trait`.
+//!
+//! Marker traits among the bounds don't matter, so `impl Send + Into<u64>`
+//! becomes `u64` too.  Note that the mock method collects an iterator argument
+//! into a `Vec` as soon as it's called, so the iterator must be finite.
+//!
+//! ```
+//! # use mockall::*;
+//! # use std::path::PathBuf;
+//! #[automock]
+//! trait Foo {
+//!     fn open(&self, path: impl Into<PathBuf>, mode: impl AsRef<str>) -> u32;
+//! }
+//!
+//! # fn main() {
+//! let mut mock = MockFoo::new();
+//! mock.expect_open()
+//!     .with(predicate::eq(PathBuf::from("/tmp")), predicate::eq("rw"))
+//!     .return_const(3u32);
+//! assert_eq!(3, mock.open("/tmp", String::from("rw")));
+//! # }
+//! ```
+//!
+//! ## Deep stubs
+//!
+//! A method that returns a trait object, like `Box<dyn Session>` or `&dyn Db`,
+//! can return a nested mock object instead of using Expectations.  Mark it with
+//! [`#[deep]`](attr.deep.html), and configure the nested mock with the mock's
+//! `<method>_mock` accessor.  The nested mock is checkpointed along with its
+//! parent.  But a `Box<dyn Trait>` method hands the nested mock over to its
+//! caller.  After that, the parent can no longer reach it, so it's verified
+//! only when the caller drops it.
+//!
+//! ```
+//! # use mockall::*;
+//! #[automock]
+//! trait Session {
+//!     fn user(&self) -> u32;
+//! }
+//!
+//! #[automock]
+//! trait Client {
+//!     #[deep]
+//!     fn session(&self) -> Box<dyn Session>;
+//! }
+//!
+//! # fn main() {
+//! let mut mock = MockClient::new();
+//! mock.session_mock()
+//!     .expect_user()
+//!     .return_const(7u32);
+//! assert_eq!(7, mock.session().user());
+//! # }
+//! ```
+//!
 //! ## Mocking structs
 //!
 //! Mockall mocks structs as well as traits.  The problem here is a namespace
@@ -630,9 +1029,9 @@
 //! Generic methods can be mocked, too.  Effectively each generic method is an
 //! infinite set of regular methods, and each of those works just like any other
 //! regular method.  The expect_* method is generic, too, and usually must be
-//! called with a turbofish.  The only restrictions on mocking generic methods
-//! are that all generic parameters must be `'static`, and generic lifetime
-//! parameters are not allowed.
+//! called with a turbofish.  The only restriction on mocking generic methods
+//! is that all generic type parameters must be `'static`.  They may be
+//! `?Sized`, as long as they're only used behind references.
 //!
 //! ```
 //! # use mockall::*;
@@ -659,8 +1058,6 @@
 //! method, with a few additional restrictions.  One restriction is that you
 //! can't match calls with `with`, you must use `withf` instead.  Another is
 //! that the generic lifetime may not appear as part of the return type.
-//! Finally, no method may have both generic lifetime parameters *and* generic
-//! type parameters.
 //!
 //! ```
 //! # use mockall::*;
@@ -681,6 +1078,47 @@
 //! # }
 //! ```
 //!
+//! A method may have both generic lifetime parameters and generic type
+//! parameters.  The type parameters must still be `'static`, but they may be
+//! bounded by the lifetime parameters.  The Expectation ignores such bounds.
+//! A type parameter that isn't `'static`, and so might borrow through a
+//! lifetime parameter, can't be mocked.  Expectations can only be stored for
+//! `'static` types, and a returning closure can't produce a different type for
+//! every lifetime of its arguments.  So a method like
+//! `fn parse<'a, T: Decode<'a>>(&self, buf: &'a [u8]) -> T`, whose `T` may
+//! borrow from `buf`, is not supported.  It can only be mocked with a
+//! `+ 'static` bound on `T`:
+//!
+//! ```
+//! # use mockall::*;
+//! trait Decode<'a> {}
+//! impl<'a> Decode<'a> for u32 {}
+//!
+//! #[automock]
+//! trait Parser {
+//!     fn parse<'a, T: Decode<'a> + 'static>(&self, buf: &'a [u8]) -> T;
+//! }
+//!
+//! # fn main() {
+//! let mut mock = MockParser::new();
+//! mock.expect_parse::<u32>()
+//!     .returning(|buf| u32::from(buf[0]));
+//! assert_eq!(4u32, mock.parse(&[4u8][..]));
+//! # }
+//! ```
+//!
+//! Without the `'static` bound, Mockall reports an error:
+//!
+//! ```compile_fail
+//! # use mockall::*;
+//! trait Decode<'a> {}
+//!
+//! #[automock]
+//! trait Parser {
+//!     fn parse<'a, T: Decode<'a>>(&self, buf: &'a [u8]) -> T;
+//! }
+//! ```
+//!
 //! ## Generic traits and structs
 //!
 //! Mocking generic structs and generic traits is not a problem.  The mock
@@ -703,6 +1141,30 @@
 //! # }
 //! ```
 //!
+//! ## Const generics
+//!
+//! Traits, structs, and methods may have const generic parameters, too.  Just
+//! like type parameters, each value of a method's const parameters gets its own
+//! set of expectations.
+//!
+//! ```
+//! # use mockall::*;
+//! #[automock]
+//! trait Reader {
+//!     fn read<const N: usize>(&self) -> [u8; N];
+//! }
+//!
+//! # fn main() {
+//! let mut mock = MockReader::new();
+//! mock.expect_read::<2>()
+//!     .return_const([1u8, 2]);
+//! mock.expect_read::<3>()
+//!     .return_const([3u8, 4, 5]);
+//! assert_eq!([1, 2], mock.read::<2>());
+//! assert_eq!([3, 4, 5], mock.read::<3>());
+//! # }
+//! ```
+//!
 //! ## Associated types
 //!
 //! Traits with associated types can be mocked too.  Unlike generic traits, the
@@ -725,26 +1187,85 @@
 //! assert_eq!(4, mock.foo(4));
 //! ```
 //!
-//! ## Multiple and inherited traits
-//!
-//! Creating a mock struct that implements multiple traits, whether inherited or
-//! not, requires using the [`mock!`] macro.  But once created,
-//! using it is just the same as using any other mock object:
+//! Generic associated types are specified the same way.  The concrete type may
+//! use the associated type's generic parameters.
 //!
 //! ```
 //! # use mockall::*;
+//! #[automock(type Iter<'a> = std::slice::Iter<'a, u32>;)]
 //! pub trait A {
-//!     fn foo(&self);
+//!     type Iter<'a>: Iterator<Item=&'a u32> where Self: 'a;
+//!     fn iter<'a>(&'a self) -> Self::Iter<'a>;
 //! }
 //!
-//! pub trait B: A {
-//!     fn bar(&self);
+//! static VALUES: [u32; 3] = [1, 2, 3];
+//! let mut mock = MockA::new();
+//! mock.expect_iter()
+//!     .returning(|| VALUES.iter());
+//! assert_eq!(6, mock.iter().sum::<u32>());
+//! ```
+//!
+//! Alternatively, an associated type can be left without a value.  Then it
+//! becomes a generic parameter of the mock struct, and each test can choose
+//! the type when it creates the mock.  Any bounds on the associated type will
+//! be applied to the generic parameter, too.
+//!
+//! ```
+//! # use mockall::*;
+//! #[automock(type Key; type Value;)]
+//! pub trait A {
+//!     type Key;
+//!     type Value;
+//!     fn foo(&self, k: Self::Key) -> Self::Value;
 //! }
 //!
-//! mock! {
-//!     // Structure to mock
-//!     C {}
-//!     // First trait to implement on C
+//! let mut mock = MockA::<u16, i32>::new();
+//! mock.expect_foo()
+//!     .returning(|x: u16| i32::from(x));
+//! assert_eq!(4, mock.foo(4));
+//! ```
+//!
+//! ## Associated constants
+//!
+//! Associated constants work much like associated types.  Constants that the
+//! trait doesn't define must be given a value with a metaitem to the
+//! [`#[automock]`](attr.automock.html) attribute.  Constants that already have
+//! a default value in the trait will keep it.  When mocking a struct, any
+//! constants in its `impl` block will be copied onto the mock struct.
+//!
+//! ```
+//! # use mockall::*;
+//! #[automock(const MAX: usize = 16;)]
+//! pub trait A {
+//!     const MAX: usize;
+//!     const MIN: usize = 1;
+//!     fn foo(&self, x: usize) -> usize;
+//! }
+//!
+//! assert_eq!(16, <MockA as A>::MAX);
+//! assert_eq!(1, <MockA as A>::MIN);
+//! ```
+//!
+//! ## Multiple and inherited traits
+//!
+//! Creating a mock struct that implements multiple traits, whether inherited or
+//! not, requires using the [`mock!`] macro.  But once created,
+//! using it is just the same as using any other mock object:
+//!
+//! ```
+//! # use mockall::*;
+//! pub trait A {
+//!     fn foo(&self);
+//! }
+//!
+//! pub trait B: A {
+//!     fn bar(&self);
+//! }
+//!
+//! mock! {
+//!     // Structure to mock
+//!     C {}
+//!     // First trait to implement on C
 //!     trait A {
 //!         fn foo(&self);
 //!     }
@@ -786,6 +1307,33 @@
 //! # }
 //! ```
 //!
+//! ## Self receivers
+//!
+//! Besides `self`, `&self`, and `&mut self`, Mockall can mock methods with
+//! typed receivers, like `self: Box<Self>`, `self: Rc<Self>`,
+//! `self: Arc<Self>`, or `self: Pin<&mut Self>`.  Their Expectations work just
+//! like any other method's.  That makes it possible to mock poll-based traits:
+//!
+//! ```
+//! # use mockall::*;
+//! # use std::pin::Pin;
+//! # use std::task::{Context, Poll};
+//! #[automock]
+//! trait Source {
+//!     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>)
+//!         -> Poll<Option<u32>>;
+//! }
+//!
+//! # fn main() {
+//! let mut mock = MockSource::new();
+//! mock.expect_poll_next()
+//!     .return_const(Poll::Ready(Some(42)));
+//! let waker = futures::task::noop_waker();
+//! let mut cx = Context::from_waker(&waker);
+//! assert_eq!(Poll::Ready(Some(42)), Pin::new(&mut mock).poll_next(&mut cx));
+//! # }
+//! ```
+//!
 //! ## Static methods
 //!
 //! Mockall can also mock static methods.  But be careful!  The expectations are
@@ -903,6 +1451,32 @@
 //! # }
 //! ```
 //!
+//! ### Reentrancy
+//!
+//! A returning closure may call back into the same mock, including the same
+//! static method.  However, the inner call must match a different Expectation;
+//! an Expectation whose closure calls itself will panic.
+//!
+//! ```
+//! # use mockall::*;
+//! # use mockall::predicate::*;
+//! #[automock]
+//! pub trait A {
+//!     fn fact(x: u32) -> u32;
+//! }
+//!
+//! # fn main() {
+//! let ctx = MockA::fact_context();
+//! ctx.expect()
+//!     .with(eq(0))
+//!     .return_const(1u32);
+//! ctx.expect()
+//!     .with(gt(0))
+//!     .returning(|x| x * MockA::fact(x - 1));
+//! assert_eq!(1, MockA::fact(1));
+//! # }
+//! ```
+//!
 //! One more thing: Mockall normally creates a zero-argument `new` method for
 //! every mock struct.  But it *won't* do that when mocking a struct that
 //! already has a method named `new`.
@@ -954,6 +1528,83 @@
 //! # fn main() {}
 //! ```
 //!
+//! Foreign statics are mocked as `static mut` items.  A test can override one
+//! with its `<NAME>_context()` function, which returns a
+//! [`StaticContext`](struct.StaticContext.html).  The static keeps the value
+//! set through the context until the context drops.  Only one context for a
+//! given static can exist at a time, and reading the static without one would
+//! race with a test that sets it.  So once any test overrides a static, every
+//! test that reads it, even indirectly through the code under test, must hold
+//! its context.  Then the tests take turns.  An attribute like `static NAME: T = value;`
+//! gives a static its initial value.  Without one, the static is
+//! zero-initialized like an uninitialized C global, which only compiles for
+//! types that are valid when all zeros.  So types like function pointers and
+//! references need an initial value.
+//!
+//! ```
+//! # use mockall::*;
+//! extern "C" fn handler(_sig: i32) {}
+//!
+//! #[automock(mod mock_ffi; static HANDLER: extern "C" fn(i32) = handler;)]
+//! extern "C" {
+//!     pub static VERSION: i32;
+//!     pub static HANDLER: extern "C" fn(i32);
+//! }
+//!
+//! # fn main() {
+//! let mut ctx = mock_ffi::VERSION_context();
+//! assert_eq!(0, *ctx.get());
+//! ctx.set(3);
+//! assert_eq!(3, *ctx.get());
+//! # }
+//! ```
+//!
+//! Variadic foreign functions can be mocked too, with the **nightly** feature.
+//! The consuming crate must enable `feature(c_variadic)`.  The Expectation
+//! gets the fixed arguments as usual, followed by a [`VaArgs`] argument
+//! holding the rest.  Predicates can't inspect the variable arguments, but a
+//! `returning` closure can read them, if it knows their types.
+//!
+#![cfg_attr(feature = "nightly", doc = "```")]
+#![cfg_attr(not(feature = "nightly"), doc = "```ignore")]
+//! #![feature(c_variadic)]
+//! # use mockall::*;
+//! #[automock(mod mock_ffi;)]
+//! extern "C" {
+//!     pub fn ioctl(fd: std::os::raw::c_int, req: std::os::raw::c_ulong, ...)
+//!         -> std::os::raw::c_int;
+//! }
+//!
+//! # fn main() {
+//! let ctx = mock_ffi::ioctl_context();
+//! ctx.expect()
+//!     .with(predicate::eq(3), predicate::eq(0x5401), predicate::always())
+//!     .returning(|_fd, _req, va| unsafe { va.i32() });
+//! assert_eq!(7, unsafe { mock_ffi::ioctl(3, 0x5401, 7) });
+//! # }
+//! ```
+//!
+//! Native code, like a C library linked into the test binary, can't call the
+//! mock module's Rust functions.  But with an `export;` attribute, each mock
+//! function is also exported as a C symbol with the original function's name,
+//! so native callers will hit the mock instead of the real function.  Foreign
+//! statics are exported too.
+//! Alternatively, `export(wrap);` exports each mock function as
+//! `__wrap_<name>`, for use with the linker's `--wrap=<name>` option.  The
+//! exported functions use the foreign block's ABI in its unwinding form, like
+//! `extern "C-unwind"` for an `extern "C"` block, because failed expectations
+//! panic.  So native code that calls the mocks should be built with unwind
+//! tables.  `export` is only allowed on foreign blocks.
+//!
+//! ```no_run
+//! # use mockall::*;
+//! #[automock(mod mock_ffi; export;)]
+//! extern "C" {
+//!     pub fn foo(x: u32) -> i64;
+//! }
+//! # fn main() {}
+//! ```
+//!
 //! ## Modules
 //!
 //! In addition to mocking foreign functions, Mockall can also derive mocks for
@@ -1001,15 +1652,28 @@
 //! # fn main() {}
 //! ```
 //!
+//! A mocked module's constants and statics are copied to the mock module.  But
+//! their values may be overridden with attributes like
+//! `#[automock(const LIMIT: u32 = 7; static NAME: &str = "mock";)]`.  Such
+//! overrides happen at compile time, so they apply to every test alike.  A
+//! single test can override a `static mut` with its `<NAME>_context()`
+//! function, just like a foreign static, and then every test that reads it
+//! must hold its context too.  But constants and immutable statics
+//! can't change at runtime, so they can't be overridden per test.
+//! An attribute that names none of the module's constants or statics is an
+//! error.
+//!
 //! ## Crate features
 //!
-//! Mockall has a **nightly** feature.  Currently this feature has three
+//! Mockall has a **nightly** feature.  Currently this feature has four
 //! effects:
 //!
 //! * The compiler will produce better error messages.
 //!
 //! * Mocking modules will be enabled.
 //!
+//! * Mocking variadic foreign functions will be enabled.
+//!
 //! * Expectations for methods whose return type implements `Default` needn't
 //!   have their return values explicitly set.  Instead, they will automatically
 //!   return the default value.
@@ -1028,33 +1692,76 @@
 //! assert!(mock.foo().is_empty());
 //! ```
 //!
+//! Mockall also has a **stream** feature.  With it, Expectations for methods
+//! that return streams, as `impl Stream` or `Pin<Box<dyn Stream>>`, get a
+//! `return_stream` method.  Each call returns a stream that yields a clone of
+//! the given items.  `items_pulled` additionally checks how many items the
+//! consumer pulled from each stream, when the Expectation is checkpointed or
+//! dropped.
+#![cfg_attr(feature = "stream", doc = "```")]
+#![cfg_attr(not(feature = "stream"), doc = "```ignore")]
+//! # use mockall::*;
+//! # use futures::{executor::block_on, stream::{Stream, StreamExt}};
+//! #[automock]
+//! trait Foo {
+//!     fn foo(&self) -> impl Stream<Item=u32> + Send;
+//! }
+//!
+//! # fn main() {
+//! let mut mock = MockFoo::new();
+//! mock.expect_foo()
+//!     .return_stream(vec![1, 2, 3])
+//!     .items_pulled(3);
+//! assert_eq!(vec![1, 2, 3], block_on(mock.foo().collect::<Vec<_>>()));
+//! # }
+//! ```
+//!
 //! ## Examples
 //!
 //! For additional examples of Mockall in action, including detailed
 //! documentation on the autogenerated methods, see
 //! [`mockall_examples`](https://docs.rs/mockall_examples/latest/mockall_examples/).
 //!
+//! [`ExpectationHandle`]: ExpectationHandle
+//! [`Latch`]: Latch
+//! [`Latch::release`]: Latch::release
 //! [`Predicate`]: trait.Predicate.html
 //! [`Sequence`]: Sequence
 //! [`cfg-if`]: https://crates.io/crates/cfg-if
 //! [`function`]: predicate/fn.function.html
 //! [`mock!`]: macro.mock.html
 //! [`predicates`]: predicate/index.html
+//! [`Timeout`]: Timeout
+//! [`WaitUntilSatisfied`]: WaitUntilSatisfied
+//! [`VaArgs`]: struct.VaArgs.html
 
 #![cfg_attr(feature = "nightly", feature(specialization))]
 #![cfg_attr(feature = "nightly", feature(doc_cfg))]
+#![cfg_attr(feature = "nightly", feature(c_variadic))]
 #![deny(intra_doc_link_resolution_failure)]
 
 use downcast::*;
 use std::{
     any,
+    cell::Cell,
+    error,
+    fmt,
+    future::Future,
     marker::PhantomData,
-    ops::{Range, RangeFrom, RangeFull, RangeInclusive, RangeTo,
-          RangeToInclusive},
+    ops::{Deref, DerefMut, Range, RangeFrom, RangeFull, RangeInclusive,
+          RangeTo, RangeToInclusive},
+    panic::{self, AssertUnwindSafe},
+    pin::Pin,
     sync::{
         Arc,
+        Condvar,
+        Mutex,
+        MutexGuard,
         atomic::{AtomicUsize, Ordering}
     },
+    task::{Context, Poll},
+    thread::{self, ThreadId},
+    time::{Duration, Instant}
 };
 
 #[doc(hidden)]
@@ -1123,6 +1830,27 @@ pub use predicates_tree::CaseTreeExt;
 /// }
 /// ```
 ///
+/// Or, leave out the type to make it a generic parameter of the mock struct:
+/// ```
+/// # use mockall_derive::*;
+/// #[automock(type Item;)]
+/// trait Foo {
+///     type Item;
+///     fn foo(&self) -> Self::Item;
+/// }
+/// let mock = MockFoo::<u32>::new();
+/// ```
+///
+/// Associated constants work the same way:
+/// ```
+/// # use mockall_derive::*;
+/// #[automock(const MAX: usize = 16;)]
+/// trait Foo {
+///     const MAX: usize;
+///     fn foo(&self) -> usize;
+/// }
+/// ```
+///
 /// Finally, `#[automock]` can also mock foreign functions.  This requires
 /// another metaitem to specify the mock module name.
 ///
@@ -1148,6 +1876,47 @@ pub use predicates_tree::CaseTreeExt;
 ///   to choose your own name for the mock structure.
 pub use mockall_derive::automock;
 
+/// Make a mock method return a nested mock object, a "deep stub".
+///
+/// Mark a method that returns `Box<dyn Trait>`, `&dyn Trait`, or `&mut dyn
+/// Trait` with `#[deep]`, where `Trait` is also mocked as `MockTrait`.  The
+/// mock object will then store a `MockTrait` and return it from the method,
+/// instead of using Expectations.  Configure the nested mock with the
+/// `<method>_mock` accessor.  It's an error for the mock to have another method
+/// with that name.  The nested mock gets checkpointed along with its parent.
+///
+/// A method that returns `Box<dyn Trait>` hands out its nested mock and leaves
+/// a fresh one in its place, so each call may return a differently configured
+/// mock.  Once handed out, the nested mock no longer gets checkpointed with
+/// its parent.  It's only verified when it drops.
+///
+/// `#[deep]` has no effect except on methods mocked with [`automock`] or
+/// [`mock!`].
+///
+/// # Examples
+/// ```
+/// # use mockall::*;
+/// #[automock]
+/// trait Db {
+///     fn get(&self, key: u32) -> u32;
+/// }
+///
+/// #[automock]
+/// trait Service {
+///     #[deep]
+///     fn db(&self) -> &dyn Db;
+/// }
+///
+/// # fn main() {
+/// let mut mock = MockService::new();
+/// mock.db_mock()
+///     .expect_get()
+///     .return_const(42u32);
+/// assert_eq!(42, mock.db().get(0));
+/// # }
+/// ```
+pub use mockall_derive::deep;
+
 /// Manually mock a structure.
 ///
 /// Sometimes `automock` can't be used.  In those cases you can use `mock!`,
@@ -1206,9 +1975,8 @@ pub use mockall_derive::automock;
 /// # fn main() {}
 /// ```
 /// Associated types can easily be mocked by specifying a concrete type in the
-/// `mock!{}` invocation.  But be careful not to reference the associated type
-/// in the signatures of any of the trait's methods; repeat the concrete type
-/// instead.  For example, do:
+/// `mock!{}` invocation.  The trait's methods may refer to the associated type
+/// either by name or by the concrete type.
 /// ```
 /// # use mockall_derive::mock;
 /// mock!{
@@ -1216,20 +1984,43 @@ pub use mockall_derive::automock;
 ///     trait Iterator {
 ///         type Item=u32;
 ///
-///         fn next(&mut self) -> Option<u32>;
+///         fn next(&mut self) -> Option<<Self as Iterator>::Item>;
 ///     }
 /// }
 /// # fn main() {}
 /// ```
-/// *not*
-/// ```compile_fail
+/// Generic associated types work the same way.
+/// ```
 /// # use mockall_derive::mock;
+/// trait Storage {
+///     type Iter<'a>: Iterator<Item=&'a u32> where Self: 'a;
+///     fn iter<'a>(&'a self) -> Self::Iter<'a>;
+/// }
 /// mock!{
-///     MyIter {}
-///     trait Iterator {
-///         type Item=u32;
+///     MyStorage {}
+///     trait Storage {
+///         type Iter<'a> = std::slice::Iter<'a, u32> where Self: 'a;
+///         fn iter<'a>(&'a self) -> Self::Iter<'a>;
+///     }
+/// }
+/// # fn main() {}
+/// ```
 ///
-///         fn next(&mut self) -> Option<<Self as Iterator>::Item>;
+/// Associated constants must be given values too, either in the trait's
+/// definition or in the struct's inherent block.
+/// ```
+/// # use mockall_derive::mock;
+/// trait Buffer {
+///     const CAPACITY: usize;
+///     fn len(&self) -> usize;
+/// }
+/// mock!{
+///     MyBuffer {
+///         const ALIGN: usize = 8;
+///     }
+///     trait Buffer {
+///         const CAPACITY: usize = 4096;
+///         fn len(&self) -> usize;
 ///     }
 /// }
 /// # fn main() {}
@@ -1237,7 +2028,9 @@ pub use mockall_derive::automock;
 pub use mockall_derive::mock;
 
 #[doc(hidden)]
-pub trait AnyExpectations : Any + Send + Sync {}
+pub trait AnyExpectations : Any + Send + Sync {
+    fn wait_until_satisfied(&self, deadline: Instant) -> Result<(), Timeout>;
+}
 downcast!(dyn AnyExpectations);
 
 #[doc(hidden)]
@@ -1341,17 +2134,503 @@ impl From<RangeToInclusive<usize>> for TimesRange {
     }
 }
 
+/// Returned when waiting for calls to a mock object times out.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Timeout;
+
+impl fmt::Display for Timeout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Timed out waiting for calls to a mock object")
+    }
+}
+
+impl error::Error for Timeout {}
+
+/// Implemented by every mock struct, so multithreaded tests can wait for other
+/// threads to call it.
+///
+/// It's a trait rather than an inherent method so that it can't collide with
+/// any of the mocked struct's own methods.
+pub trait WaitUntilSatisfied {
+    /// Block until every current expectation of every non-static method has
+    /// been called its minimum number of times, or until `timeout` elapses.
+    fn wait_until_satisfied(&self, timeout: Duration) -> Result<(), Timeout>;
+}
+
+/// The variable arguments of a mock variadic foreign function.
+///
+/// The arguments carry no type information, so they can only be read in
+/// order, and only as a few primitive types.  Reading an argument as a
+/// different type than the caller passed, or reading more arguments than the
+/// caller passed, is undefined behavior.
+#[cfg(feature = "nightly")]
+#[doc(cfg(feature = "nightly"))]
+pub struct VaArgs<'a>(std::ffi::VaList<'a>);
+
+#[cfg(feature = "nightly")]
+impl<'a> VaArgs<'a> {
+    #[doc(hidden)]
+    pub fn new(va: std::ffi::VaList<'a>) -> Self {
+        VaArgs(va)
+    }
+
+    /// Read the next argument as a C `int`.
+    pub unsafe fn i32(&mut self) -> i32 {
+        self.0.next_arg::<i32>()
+    }
+
+    /// Read the next argument as a C `unsigned int`.
+    pub unsafe fn u32(&mut self) -> u32 {
+        self.0.next_arg::<u32>()
+    }
+
+    /// Read the next argument as a C `long long`.
+    pub unsafe fn i64(&mut self) -> i64 {
+        self.0.next_arg::<i64>()
+    }
+
+    /// Read the next argument as a C `unsigned long long`.
+    pub unsafe fn u64(&mut self) -> u64 {
+        self.0.next_arg::<u64>()
+    }
+
+    /// Read the next argument as a pointer-sized unsigned integer.
+    pub unsafe fn usize(&mut self) -> usize {
+        self.0.next_arg::<usize>()
+    }
+
+    /// Read the next argument as a C `double`.
+    pub unsafe fn f64(&mut self) -> f64 {
+        self.0.next_arg::<f64>()
+    }
+
+    /// Read the next argument as a raw pointer.
+    pub unsafe fn ptr<T>(&mut self) -> *const T {
+        self.0.next_arg::<*const T>()
+    }
+
+    /// Read the next argument as a raw mutable pointer.
+    pub unsafe fn mut_ptr<T>(&mut self) -> *mut T {
+        self.0.next_arg::<*mut T>()
+    }
+}
+
+/// How many times has an expectation been called?  Shared between the
+/// expectation and its [`ExpectationHandle`]s.
+#[derive(Debug, Default)]
+struct CallCount {
+    count: AtomicUsize,
+    mutex: Mutex<()>,
+    cvar: Condvar
+}
+
+impl CallCount {
+    fn get(&self) -> usize {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// Record a call, and wake up anybody waiting for it.
+    fn incr(&self) -> usize {
+        let count = self.count.fetch_add(1, Ordering::Relaxed) + 1;
+        // Take the lock so no waiter can miss the notification between
+        // checking the count and sleeping.
+        let _guard = self.mutex.lock().unwrap();
+        self.cvar.notify_all();
+        count
+    }
+
+    /// Block until the count reaches at least `n`
+    fn wait_until(&self, n: usize, deadline: Instant) -> Result<(), Timeout> {
+        let mut guard = self.mutex.lock().unwrap();
+        while self.get() < n {
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(Timeout);
+            }
+            guard = self.cvar.wait_timeout(guard, deadline - now).unwrap().0;
+        }
+        Ok(())
+    }
+}
+
+/// A handle to an Expectation that can be used while the mock object is in use
+/// by other threads.
+///
+/// Create one with an Expectation's `handle` method.
+///
+/// # Examples
+///
+/// ```
+/// # use mockall::*;
+/// # use std::{sync::Arc, thread, time::Duration};
+/// #[automock]
+/// trait Foo {
+///     fn foo(&self, x: u32);
+/// }
+///
+/// # fn main() {
+/// let mut mock = MockFoo::new();
+/// let handle = mock.expect_foo()
+///     .return_const(())
+///     .handle();
+/// let mock = Arc::new(mock);
+/// let mock2 = mock.clone();
+/// thread::spawn(move || mock2.foo(42));
+/// handle.wait_for_calls(1, Duration::from_secs(10)).unwrap();
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct ExpectationHandle {
+    calls: Arc<CallCount>
+}
+
+impl ExpectationHandle {
+    /// How many times has the Expectation been called so far?
+    pub fn calls(&self) -> usize {
+        self.calls.get()
+    }
+
+    /// Block until the Expectation has been called at least `n` times in
+    /// total, or until `timeout` elapses.
+    pub fn wait_for_calls(&self, n: usize, timeout: Duration)
+        -> Result<(), Timeout>
+    {
+        self.calls.wait_until(n, Instant::now() + timeout)
+    }
+}
+
+#[derive(Debug, Default)]
+struct LatchState {
+    /// How many callers have ever entered the latch?
+    entered: usize,
+    released: bool
+}
+
+/// A gate that can make mocked calls block until the test releases them.
+///
+/// Attach a `Latch` to an Expectation with its `blocking_on` method.  Every
+/// matching call will then wait inside the mock until the `Latch` is
+/// released.  This makes it possible to reproduce races deterministically.
+/// Clones of a `Latch` share the same state.
+///
+/// # Examples
+///
+/// ```
+/// # use mockall::*;
+/// # use std::{sync::Arc, thread, time::Duration};
+/// #[automock]
+/// trait Foo {
+///     fn foo(&self) -> u32;
+/// }
+///
+/// # fn main() {
+/// let latch = Latch::new();
+/// let mut mock = MockFoo::new();
+/// mock.expect_foo()
+///     .blocking_on(&latch)
+///     .return_const(42u32);
+/// let mock = Arc::new(mock);
+/// let mock2 = mock.clone();
+/// let t = thread::spawn(move || mock2.foo());
+/// // Wait until the other thread is parked inside of foo
+/// latch.wait_entered(Duration::from_secs(10)).unwrap();
+/// // ... do something while it's parked ...
+/// latch.release();
+/// assert_eq!(42, t.join().unwrap());
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Latch {
+    inner: Arc<(Mutex<LatchState>, Condvar)>
+}
+
+impl Latch {
+    /// Create a new, closed, `Latch`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How many callers have entered the latch so far, including any that
+    /// have since been released?
+    pub fn entered(&self) -> usize {
+        self.inner.0.lock().unwrap().entered
+    }
+
+    /// Has the latch been released?
+    pub fn is_released(&self) -> bool {
+        self.inner.0.lock().unwrap().released
+    }
+
+    /// Release every caller blocked on the latch.  Any future callers will
+    /// pass through without blocking.
+    pub fn release(&self) {
+        self.inner.0.lock().unwrap().released = true;
+        self.inner.1.notify_all();
+    }
+
+    /// Block the calling thread until the latch is released.  Mock methods
+    /// call this; tests usually don't need to.
+    pub fn wait(&self) {
+        let mut state = self.inner.0.lock().unwrap();
+        state.entered += 1;
+        self.inner.1.notify_all();
+        while !state.released {
+            state = self.inner.1.wait(state).unwrap();
+        }
+    }
+
+    /// Block until at least one caller has entered the latch, or until
+    /// `timeout` elapses.
+    pub fn wait_entered(&self, timeout: Duration) -> Result<(), Timeout> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.inner.0.lock().unwrap();
+        while state.entered == 0 {
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(Timeout);
+            }
+            state = self.inner.1.wait_timeout(state, deadline - now)
+                .unwrap().0;
+        }
+        Ok(())
+    }
+}
+
+thread_local! {
+    /// Set when the current thread panics because a mock object was called in
+    /// violation of its expectations, rather than for any other reason.
+    static VIOLATION: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Panic because a mock object was called in violation of its expectations.
+/// Not for public consumption, but it must be public so the generated code
+/// can call it.
+#[doc(hidden)]
+pub fn violation(msg: String) -> ! {
+    VIOLATION.with(|v| v.set(true));
+    panic!("{}", msg)
+}
+
+/// Failures that happened while another thread was calling a mock object.
+/// They get reported again by whichever thread next checkpoints or drops the
+/// mock object, or the `Context` of a static method, so that a failure can't
+/// be lost just because the thread that hit it swallowed its panic.
+///
+/// Only Mockall's own violations get recorded, like unmatched calls, excess
+/// calls, or sequence errors.  Other panics, like those from `returning`
+/// closures, are the caller's business.  So are any panics on the thread that
+/// created the mock object or `Context`, since that thread sees them already.
+#[derive(Clone, Debug)]
+#[doc(hidden)]
+pub struct Failures {
+    /// The thread that created the mock object
+    owner: ThreadId,
+    failures: Arc<Mutex<Vec<String>>>
+}
+
+impl Default for Failures {
+    fn default() -> Self {
+        Failures {
+            owner: thread::current().id(),
+            failures: Arc::default()
+        }
+    }
+}
+
+impl Failures {
+    /// Run `f`, recording the message of any violation on a foreign thread
+    /// before resuming its panic.
+    pub fn catch<F: FnOnce() -> R, R>(&self, f: F) -> R {
+        // Forget any violation that was caught elsewhere
+        VIOLATION.with(|v| v.set(false));
+        match panic::catch_unwind(AssertUnwindSafe(f)) {
+            Ok(r) => r,
+            Err(payload) => {
+                let violation = VIOLATION.with(|v| v.replace(false));
+                if violation && thread::current().id() != self.owner {
+                    let msg = if let Some(s) = payload.downcast_ref::<&str>() {
+                        s.to_string()
+                    } else if let Some(s) = payload.downcast_ref::<String>() {
+                        s.clone()
+                    } else {
+                        "Box<dyn Any>".to_string()
+                    };
+                    self.failures.lock().unwrap().push(msg);
+                }
+                panic::resume_unwind(payload)
+            }
+        }
+    }
+
+    /// Panic with any failures recorded so far, and forget them.  Does nothing
+    /// if the current thread is already panicking.
+    pub fn check(&self) {
+        if std::thread::panicking() {
+            return;
+        }
+        let failures = std::mem::take(&mut *self.failures.lock().unwrap());
+        if !failures.is_empty() {
+            panic!("{}", failures.join("\n"));
+        }
+    }
+}
+
+/// Counts the calls to one method that are currently in progress
+#[derive(Debug, Default)]
+#[doc(hidden)]
+pub struct InFlight {
+    /// How many calls are in progress right now?
+    current: AtomicUsize,
+    /// How many calls have ever begun?
+    started: AtomicUsize
+}
+
+/// Thread affinity and exclusivity rules for an Expectation
+#[derive(Debug, Default)]
+#[doc(hidden)]
+pub struct Concurrency {
+    exclusive: bool,
+    in_flight: Arc<InFlight>,
+    not_on_threads: Vec<ThreadId>,
+    on_thread: Option<ThreadId>
+}
+
+impl Concurrency {
+    /// Begin a call.  The call lasts until the returned guard is dropped.
+    pub fn enter(&self) -> Result<InFlightGuard<'_>, String> {
+        let id = thread::current().id();
+        if let Some(expected) = self.on_thread {
+            if id != expected {
+                return Err(format!(
+                    "called on thread {:?}, but it may only be called on thread {:?}",
+                    id, expected));
+            }
+        }
+        if self.not_on_threads.contains(&id) {
+            return Err(format!("called on forbidden thread {:?}", id));
+        }
+        let prior = self.in_flight.current.fetch_add(1, Ordering::SeqCst);
+        let guard = InFlightGuard {
+            concurrency: self,
+            started: self.in_flight.started.fetch_add(1, Ordering::SeqCst) + 1
+        };
+        if self.exclusive && prior > 0 {
+            Err("called while another call to the same method was in progress"
+                .to_string())
+        } else {
+            Ok(guard)
+        }
+    }
+
+    pub fn exclusive(&mut self) {
+        self.exclusive = true;
+    }
+
+    pub fn not_on_thread(&mut self, id: ThreadId) {
+        self.not_on_threads.push(id);
+    }
+
+    pub fn on_thread(&mut self, id: ThreadId) {
+        self.on_thread = Some(id);
+    }
+
+    /// Count calls in the given `InFlight`, which should be shared by all of
+    /// the method's Expectations.
+    pub fn share(&mut self, in_flight: &Arc<InFlight>) {
+        self.in_flight = in_flight.clone();
+    }
+}
+
+/// A call that is in progress
+#[doc(hidden)]
+pub struct InFlightGuard<'a> {
+    concurrency: &'a Concurrency,
+    /// The value of `InFlight::started` just after this call began
+    started: usize
+}
+
+impl<'a> InFlightGuard<'a> {
+    /// Finish the call, checking that no other call began in the meantime if
+    /// it was supposed to be exclusive.
+    pub fn exit(self) -> Result<(), String> {
+        let started = self.concurrency.in_flight.started.load(Ordering::SeqCst);
+        if self.concurrency.exclusive && started != self.started {
+            Err("overlapped with another call to the same method".to_string())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<'a> Drop for InFlightGuard<'a> {
+    fn drop(&mut self) {
+        self.concurrency.in_flight.current.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Records which thread is running an Expectation's return function, so that a
+/// returning closure that calls back into its own Expectation fails instead of
+/// deadlocking.
+#[derive(Debug, Default)]
+#[doc(hidden)]
+pub struct Reentrancy(Mutex<Option<ThreadId>>);
+
+impl Reentrancy {
+    /// Lock `rfunc` on behalf of the current thread.  Other threads will block,
+    /// but the current thread will fail if it already holds the lock.
+    pub fn lock<'a, T>(&'a self, rfunc: &'a Mutex<T>)
+        -> Result<ReentrancyGuard<'a, T>, &'static str>
+    {
+        let id = thread::current().id();
+        if *self.0.lock().unwrap() == Some(id) {
+            return Err(
+                "called from within its own returning closure.  Add another \
+                 Expectation to handle the inner call");
+        }
+        let guard = rfunc.lock().unwrap();
+        *self.0.lock().unwrap() = Some(id);
+        Ok(ReentrancyGuard {guard, reentrancy: self})
+    }
+}
+
+/// A locked return function
+#[doc(hidden)]
+pub struct ReentrancyGuard<'a, T> {
+    guard: MutexGuard<'a, T>,
+    reentrancy: &'a Reentrancy
+}
+
+impl<'a, T> Deref for ReentrancyGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<'a, T> DerefMut for ReentrancyGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
+
+impl<'a, T> Drop for ReentrancyGuard<'a, T> {
+    fn drop(&mut self) {
+        *self.reentrancy.0.lock().unwrap() = None;
+    }
+}
+
 #[derive(Debug, Default)]
 #[doc(hidden)]
 pub struct Times{
     /// How many times has the expectation already been called?
-    count: AtomicUsize,
+    calls: Arc<CallCount>,
     range: TimesRange
 }
 
 impl Times {
     pub fn call(&self) -> Result<(), String> {
-        let count = self.count.fetch_add(1, Ordering::Relaxed) + 1;
+        let count = self.calls.incr();
         if count >= self.range.0.end {
             if self.range.0.end == 1 {
                 Err("should not have been called".to_owned())
@@ -1370,7 +2649,7 @@ impl Times {
     /// Has this expectation already been called the maximum allowed number of
     /// times?
     pub fn is_done(&self) -> bool {
-        self.count.load(Ordering::Relaxed) >= self.range.0.end - 1
+        self.calls.get() >= self.range.0.end - 1
     }
 
     /// Is it required that this expectation be called an exact number of times,
@@ -1382,7 +2661,7 @@ impl Times {
     /// Has this expectation already been called the minimum required number of
     /// times?
     pub fn is_satisfied(&self) -> bool {
-        self.count.load(Ordering::Relaxed) >= self.range.0.start
+        self.calls.get() >= self.range.0.start
     }
 
     /// The minimum number of times that this expectation must be called
@@ -1408,6 +2687,16 @@ impl Times {
     pub fn times<T: Into<TimesRange>>(&mut self, t: T) {
         self.range = t.into();
     }
+
+    pub fn handle(&self) -> ExpectationHandle {
+        ExpectationHandle{calls: self.calls.clone()}
+    }
+
+    /// Block until this expectation has been called the minimum required
+    /// number of times.
+    pub fn wait_satisfied(&self, deadline: Instant) -> Result<(), Timeout> {
+        self.calls.wait_until(self.range.0.start, deadline)
+    }
 }
 
 /// Non-generic keys to `GenericExpectation` internal storage
@@ -1421,6 +2710,197 @@ impl Key {
     }
 }
 
+/// A future that returns `Poll::Pending` a fixed number of times, waking its
+/// task each time, before resolving.  Used by the generated `return_ready` and
+/// `return_after_pending` methods.
+#[doc(hidden)]
+pub struct PendingThenReady<V, O> {
+    pending: usize,
+    value: Option<V>,
+    _output: PhantomData<fn() -> O>
+}
+
+impl<V, O> PendingThenReady<V, O> {
+    pub fn new(pending: usize, value: V) -> Self {
+        PendingThenReady{pending, value: Some(value), _output: PhantomData}
+    }
+}
+
+// The value is never pinned, so the future is Unpin no matter what V is.
+impl<V, O> Unpin for PendingThenReady<V, O> {}
+
+impl<V: Into<O>, O> Future for PendingThenReady<V, O> {
+    type Output = O;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<O> {
+        if self.pending > 0 {
+            self.pending -= 1;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        } else {
+            let v = self.value.take()
+                .expect("PendingThenReady polled after completion");
+            Poll::Ready(v.into())
+        }
+    }
+}
+
+/// Counts the futures returned by an Expectation that haven't yet been polled
+/// to completion.  Used by the generated `must_be_polled` method.
+#[derive(Default)]
+#[doc(hidden)]
+pub struct PollTracker {
+    enabled: bool,
+    unpolled: Arc<AtomicUsize>
+}
+
+impl PollTracker {
+    pub fn enable(&mut self) {
+        self.enabled = true;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Wrap a future so the tracker will know when it completes
+    pub fn track<F: Future + Unpin>(&self, fut: F) -> TrackedFuture<F> {
+        self.unpolled.fetch_add(1, Ordering::Relaxed);
+        TrackedFuture{fut, unpolled: Some(self.unpolled.clone())}
+    }
+
+    /// How many tracked futures have not yet been polled to completion?
+    pub fn unpolled(&self) -> usize {
+        self.unpolled.load(Ordering::Relaxed)
+    }
+
+    /// Wrap the output of an `async fn` method's Expectation in a future that's
+    /// immediately ready.  The future is tracked only if the tracker is
+    /// enabled.
+    pub fn ready<T>(&self, value: T) -> TrackedFuture<std::future::Ready<T>> {
+        let fut = std::future::ready(value);
+        if self.enabled {
+            self.track(fut)
+        } else {
+            TrackedFuture{fut, unpolled: None}
+        }
+    }
+}
+
+/// A future tracked by a [`PollTracker`]
+#[doc(hidden)]
+pub struct TrackedFuture<F> {
+    fut: F,
+    unpolled: Option<Arc<AtomicUsize>>
+}
+
+impl<F: Future + Unpin> Future for TrackedFuture<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output>
+    {
+        let r = Pin::new(&mut self.fut).poll(cx);
+        if r.is_ready() {
+            if let Some(unpolled) = self.unpolled.take() {
+                unpolled.fetch_sub(1, Ordering::Relaxed);
+            }
+        }
+        r
+    }
+}
+
+/// Counts the items pulled from each stream returned by an Expectation, and
+/// checks them against the expected range.  Used by the generated
+/// `return_stream` and `items_pulled` methods.
+#[cfg(feature = "stream")]
+#[derive(Default)]
+#[doc(hidden)]
+pub struct PullTracker {
+    range: Option<TimesRange>,
+    counts: PullCounts
+}
+
+#[cfg(feature = "stream")]
+impl PullTracker {
+    pub fn counts(&self) -> PullCounts {
+        self.counts.clone()
+    }
+
+    pub fn expect<T: Into<TimesRange>>(&mut self, range: T) {
+        self.range = Some(range.into());
+    }
+
+    /// Check every stream's pull count against the expected range
+    pub fn verify(&self) -> Result<(), String> {
+        let range = match &self.range {
+            Some(r) => &r.0,
+            None => return Ok(())
+        };
+        for count in self.counts.0.lock().unwrap().iter() {
+            let n = count.load(Ordering::Relaxed);
+            if !range.contains(&n) {
+                let expected = if range.end - range.start == 1 {
+                    format!("{}", range.start)
+                } else if range.end == usize::max_value() {
+                    format!("at least {}", range.start)
+                } else {
+                    format!("between {} and {}", range.start, range.end - 1)
+                };
+                return Err(format!(
+                    "returned a stream from which {} item(s) were pulled instead of {}",
+                    n, expected));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Shared handle to the pull counts of a [`PullTracker`]
+#[cfg(feature = "stream")]
+#[derive(Clone, Default)]
+#[doc(hidden)]
+pub struct PullCounts(Arc<Mutex<Vec<Arc<AtomicUsize>>>>);
+
+#[cfg(feature = "stream")]
+impl PullCounts {
+    /// Create a stream that yields `items` and counts how many are pulled
+    pub fn stream<I: IntoIterator>(&self, items: I)
+        -> ScriptedStream<I::IntoIter>
+    {
+        let pulled = Arc::new(AtomicUsize::new(0));
+        self.0.lock().unwrap().push(pulled.clone());
+        ScriptedStream{iter: items.into_iter(), pulled}
+    }
+}
+
+/// A stream that yields the items of an iterator, counting them as they're
+/// pulled
+#[cfg(feature = "stream")]
+#[doc(hidden)]
+pub struct ScriptedStream<I> {
+    iter: I,
+    pulled: Arc<AtomicUsize>
+}
+
+// The iterator is never pinned, so the stream is Unpin no matter what I is.
+#[cfg(feature = "stream")]
+impl<I> Unpin for ScriptedStream<I> {}
+
+#[cfg(feature = "stream")]
+impl<I: Iterator> futures_core::Stream for ScriptedStream<I> {
+    type Item = I::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, _cx: &mut Context<'_>)
+        -> Poll<Option<I::Item>>
+    {
+        let item = self.iter.next();
+        if item.is_some() {
+            self.pulled.fetch_add(1, Ordering::Relaxed);
+        }
+        Poll::Ready(item)
+    }
+}
+
 #[doc(hidden)]
 pub struct SeqHandle {
     inner: Arc<SeqInner>,
@@ -1437,24 +2917,60 @@ impl SeqHandle {
     pub fn verify(&self) {
         self.inner.verify(self.seq);
     }
+
+    /// For blocking Sequences, wait until this handle's predecessors are
+    /// satisfied.  For other Sequences, do nothing.
+    pub fn wait(&self) {
+        self.inner.wait(self.seq);
+    }
 }
 
 #[derive(Default)]
 struct SeqInner {
     satisfaction_level: AtomicUsize,
+    /// Only set for blocking sequences
+    timeout: Option<Duration>,
+    mutex: Mutex<()>,
+    condvar: Condvar
 }
 
 impl SeqInner {
     /// Record the call identified by `seq` as fully satisfied.
     fn satisfy(&self, seq: usize) {
+        let guard = self.mutex.lock().unwrap();
         let old_sl = self.satisfaction_level.fetch_add(1, Ordering::Relaxed);
-        assert_eq!(old_sl, seq, "Method sequence violation.  Was an already-satisfied method called another time?");
+        drop(guard);
+        self.condvar.notify_all();
+        if old_sl != seq {
+            violation("Method sequence violation.  Was an already-satisfied method called another time?".to_owned());
+        }
     }
 
     /// Verify that the call identified by `seq` was called in the correct order
     fn verify(&self, seq: usize) {
-        assert_eq!(seq, self.satisfaction_level.load(Ordering::Relaxed),
-            "Method sequence violation")
+        if seq != self.satisfaction_level.load(Ordering::Relaxed) {
+            violation("Method sequence violation".to_owned());
+        }
+    }
+
+    /// Block until every call preceding `seq` is satisfied, if this is a
+    /// blocking sequence.
+    fn wait(&self, seq: usize) {
+        let timeout = match self.timeout {
+            Some(timeout) => timeout,
+            None => return
+        };
+        let deadline = Instant::now() + timeout;
+        let mut guard = self.mutex.lock().unwrap();
+        while self.satisfaction_level.load(Ordering::Relaxed) < seq {
+            let now = Instant::now();
+            if now >= deadline {
+                drop(guard);
+                violation("Method sequence violation.  Timed out waiting for the preceding calls".to_owned());
+            }
+            guard = self.condvar.wait_timeout(guard, deadline - now)
+                .unwrap().0;
+        }
     }
 }
 
@@ -1516,6 +3032,52 @@ impl Sequence {
         Self::default()
     }
 
+    /// Create a `Sequence` that orders calls from multiple threads.
+    ///
+    /// Rather than panicking, a call that arrives before its predecessors in
+    /// the sequence are satisfied will block until they are.  If that takes
+    /// longer than `timeout`, it panics with a method sequence violation.
+    ///
+    /// # Examples
+    /// ```
+    /// # use mockall::*;
+    /// # use std::{sync::Arc, thread, time::Duration};
+    /// #[automock]
+    /// trait Foo {
+    ///     fn foo(&self);
+    ///     fn bar(&self);
+    /// }
+    ///
+    /// # fn main() {
+    /// let mut seq = Sequence::blocking(Duration::from_secs(10));
+    /// let mut mock = MockFoo::new();
+    /// mock.expect_foo()
+    ///     .times(1)
+    ///     .return_const(())
+    ///     .in_sequence(&mut seq);
+    /// mock.expect_bar()
+    ///     .times(1)
+    ///     .return_const(())
+    ///     .in_sequence(&mut seq);
+    /// let mock = Arc::new(mock);
+    /// let mock2 = mock.clone();
+    /// // bar will wait for foo, even though it may be called first.
+    /// let t = thread::spawn(move || mock2.bar());
+    /// mock.foo();
+    /// t.join().unwrap();
+    /// # }
+    /// ```
+    pub fn blocking(timeout: Duration) -> Self {
+        let inner = SeqInner {
+            timeout: Some(timeout),
+            .. SeqInner::default()
+        };
+        Sequence {
+            inner: Arc::new(inner),
+            next_seq: 0
+        }
+    }
+
     /// Not for public consumption, but it must be public so the generated code
     /// can call it.
     #[doc(hidden)]
@@ -1525,3 +3087,76 @@ impl Sequence {
         handle
     }
 }
+
+/// Overrides the value of a mocked `static mut` until it drops.
+///
+/// Every `static mut` of a mocked module, and every static of a mocked foreign
+/// block, gets a `<NAME>_context()` function that returns one of these.
+/// Setting a value through the `StaticContext` saves the static's previous
+/// value, and dropping it restores that.  Only one `StaticContext` for a given
+/// static can exist at a time, so tests that hold one take turns rather than
+/// racing.  But reading the static without a `StaticContext` would race with
+/// another test that sets it.  So once any test overrides a static, every test
+/// that reads it, even indirectly, must hold its `StaticContext`.
+///
+/// # Examples
+/// ```
+/// # use mockall::*;
+/// #[automock(mod mock_ffi;)]
+/// extern "C" {
+///     pub static LEVEL: i32;
+/// }
+///
+/// # fn main() {
+/// {
+///     let mut ctx = mock_ffi::LEVEL_context();
+///     ctx.set(3);
+///     assert_eq!(3, *ctx.get());
+/// }
+/// assert_eq!(0, *mock_ffi::LEVEL_context().get());
+/// # }
+/// ```
+#[must_use = "The static's value is restored when the StaticContext drops"]
+pub struct StaticContext<T: 'static> {
+    ptr: *mut T,
+    saved: Option<T>,
+    _guard: MutexGuard<'static, ()>
+}
+
+impl<T: 'static> StaticContext<T> {
+    /// Not for public consumption, but it must be public so the generated code
+    /// can call it.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to a static that is only ever written while `mutex` is
+    /// held.
+    #[doc(hidden)]
+    pub unsafe fn new(ptr: *mut T, mutex: &'static Mutex<()>) -> Self {
+        // A test that panicked while holding the lock has already restored the
+        // static, so the poison is harmless.
+        let guard = mutex.lock().unwrap_or_else(|e| e.into_inner());
+        StaticContext{ptr, saved: None, _guard: guard}
+    }
+
+    /// Get the static's current value.
+    pub fn get(&self) -> &T {
+        unsafe { &*self.ptr }
+    }
+
+    /// Set the static to `value` until this `StaticContext` drops.
+    pub fn set(&mut self, value: T) {
+        let old = unsafe { std::ptr::replace(self.ptr, value) };
+        if self.saved.is_none() {
+            self.saved = Some(old);
+        }
+    }
+}
+
+impl<T: 'static> Drop for StaticContext<T> {
+    fn drop(&mut self) {
+        if let Some(value) = self.saved.take() {
+            unsafe { *self.ptr = value };
+        }
+    }
+}
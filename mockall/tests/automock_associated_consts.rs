@@ -0,0 +1,62 @@
+// vim: tw=80
+//! automatic-style mocking with associated constants
+
+use mockall::*;
+
+#[automock(const MAX: usize = 16;)]
+trait A {
+    const MAX: usize;
+    const MIN: usize = 1;
+    fn foo(&self, x: usize) -> usize;
+}
+
+pub struct Foo {}
+#[automock]
+impl Foo {
+    pub const CAPACITY: u32 = 42;
+    pub fn foo(&self) -> u32 {
+        unimplemented!()
+    }
+}
+
+pub trait B {
+    const NAME: &'static str;
+    fn bar(&self) -> u32;
+}
+#[allow(unused)]
+pub struct Bar {}
+#[automock]
+impl B for Bar {
+    const NAME: &'static str = "foo";
+    fn bar(&self) -> u32 {
+        unimplemented!()
+    }
+}
+
+#[test]
+fn from_attribute() {
+    assert_eq!(16, <MockA as A>::MAX);
+}
+
+#[test]
+fn from_impl() {
+    assert_eq!(42, MockFoo::CAPACITY);
+}
+
+#[test]
+fn from_trait_impl() {
+    assert_eq!("foo", <MockBar as B>::NAME);
+}
+
+#[test]
+fn trait_default() {
+    assert_eq!(1, <MockA as A>::MIN);
+}
+
+#[test]
+fn returning() {
+    let mut mock = MockA::new();
+    mock.expect_foo()
+        .returning(|x| x.min(MockA::MAX));
+    assert_eq!(16, mock.foo(20));
+}
@@ -0,0 +1,68 @@
+// vim: tw=80
+//! automatic-style mocking with associated types that become generic
+//! parameters of the mock struct
+
+use mockall::*;
+
+#[automock(type Key; type Value;)]
+trait A {
+    type Key: Clone;
+    type Value;
+    fn foo(&self, k: Self::Key) -> Self::Value;
+}
+
+#[automock(type Item: Copy;)]
+trait B<T: 'static> {
+    type Item;
+    fn bar(&self, t: T) -> Option<Self::Item>;
+}
+
+#[automock(type Item;)]
+trait MyIterator {
+    type Item;
+    fn next(&mut self) -> Option<Self::Item>;
+}
+
+#[test]
+fn returning() {
+    let mut mock = MockA::<u16, i32>::new();
+    mock.expect_foo()
+        .returning(|x| i32::from(x));
+    assert_eq!(4, mock.foo(4));
+}
+
+#[test]
+fn different_types() {
+    let mut mock0 = MockA::<u8, String>::new();
+    mock0.expect_foo()
+        .returning(|x| format!("{}", x));
+    let mut mock1 = MockA::<String, usize>::new();
+    mock1.expect_foo()
+        .returning(|x| x.len());
+    assert_eq!("5", mock0.foo(5));
+    assert_eq!(5, mock1.foo("hello".to_owned()));
+}
+
+#[test]
+fn with_trait_generics() {
+    let mut mock = MockB::<u32, char>::new();
+    mock.expect_bar()
+        .returning(|x| std::char::from_digit(x, 10));
+    assert_eq!(Some('7'), mock.bar(7));
+}
+
+#[test]
+fn iterator() {
+    let mut mock = MockMyIterator::<u32>::new();
+    let mut i = 0;
+    mock.expect_next()
+        .returning(move || {
+            i += 1;
+            if i <= 3 { Some(i) } else { None }
+        });
+    let mut values = Vec::new();
+    while let Some(v) = mock.next() {
+        values.push(v);
+    }
+    assert_eq!(vec![1, 2, 3], values);
+}
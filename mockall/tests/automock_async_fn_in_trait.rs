@@ -0,0 +1,42 @@
+// vim: tw=80
+//! A trait with `async fn` methods
+
+#![allow(async_fn_in_trait)]
+
+use futures::executor::block_on;
+use mockall::*;
+use mockall::predicate::*;
+
+#[automock]
+pub trait Foo {
+    async fn foo(&self, x: u32) -> u32;
+    async fn bar(&mut self);
+    async fn baz(&self, x: &str) -> String;
+}
+
+#[test]
+fn returning() {
+    let mut mock = MockFoo::new();
+    mock.expect_foo()
+        .with(eq(4))
+        .returning(|x| x + 1);
+    assert_eq!(5, block_on(mock.foo(4)));
+}
+
+#[test]
+fn return_unit() {
+    let mut mock = MockFoo::new();
+    mock.expect_bar()
+        .times(1)
+        .return_const(());
+    block_on(mock.bar());
+}
+
+#[test]
+fn reference_argument() {
+    let mut mock = MockFoo::new();
+    mock.expect_baz()
+        .withf(|x| x == "abc")
+        .returning(|x| x.to_uppercase());
+    assert_eq!("ABC", block_on(mock.baz("abc")));
+}
@@ -0,0 +1,113 @@
+// vim: tw=80
+//! A trait decorated with #[async_trait], in either order relative to
+//! #[automock]
+
+use async_trait::async_trait;
+use futures::executor::block_on;
+use mockall::*;
+use mockall::predicate::*;
+
+#[automock]
+#[async_trait]
+pub trait Foo {
+    async fn foo(&self, x: u32) -> u32;
+    async fn bar(&mut self, x: &str) -> String;
+    async fn baz();
+}
+
+#[async_trait]
+#[automock]
+pub trait Bar {
+    async fn foo(&self, x: u32) -> u32;
+    async fn bar(&mut self, x: &str) -> String;
+    async fn baz();
+}
+
+#[automock]
+#[async_trait(?Send)]
+pub trait NotSend {
+    async fn foo(&self, x: u32) -> u32;
+}
+
+#[async_trait(?Send)]
+#[automock]
+pub trait NotSend2 {
+    async fn foo(&self, x: u32) -> u32;
+}
+
+#[automock]
+#[async_trait]
+pub trait Lifetimes {
+    async fn foo<'a>(&self, x: &'a u32, y: &u32) -> u32;
+    async fn bar<T>(&self, t: &T) -> String
+        where T: std::fmt::Display + Send + Sync + 'static;
+}
+
+#[test]
+fn automock_first() {
+    let mut mock = MockFoo::new();
+    mock.expect_foo()
+        .with(eq(4))
+        .returning(|x| x + 1);
+    mock.expect_bar()
+        .returning(|x| x.to_uppercase());
+    assert_eq!(5, block_on(mock.foo(4)));
+    assert_eq!("ABC", block_on(mock.bar("abc")));
+}
+
+#[test]
+fn async_trait_first() {
+    let mut mock = MockBar::new();
+    mock.expect_foo()
+        .with(eq(4))
+        .returning(|x| x + 1);
+    mock.expect_bar()
+        .returning(|x| x.to_uppercase());
+    assert_eq!(5, block_on(mock.foo(4)));
+    assert_eq!("ABC", block_on(mock.bar("abc")));
+}
+
+#[test]
+fn not_send() {
+    let mut mock = MockNotSend::new();
+    mock.expect_foo()
+        .returning(|x| x + 1);
+    assert_eq!(5, block_on(mock.foo(4)));
+    let mut mock = MockNotSend2::new();
+    mock.expect_foo()
+        .returning(|x| x + 2);
+    assert_eq!(6, block_on(mock.foo(4)));
+}
+
+#[test]
+fn static_method() {
+    let ctx = MockFoo::baz_context();
+    ctx.expect().return_const(());
+    block_on(MockFoo::baz());
+    let ctx = MockBar::baz_context();
+    ctx.expect().return_const(());
+    block_on(MockBar::baz());
+}
+
+/// The mock's futures must be Send, unless the trait was declared ?Send
+#[test]
+fn send() {
+    fn is_send<T: Send>(_t: &T) {}
+    let mut mock = MockBar::new();
+    mock.expect_foo()
+        .returning(|x| x);
+    let fut = mock.foo(4);
+    is_send(&fut);
+    assert_eq!(4, block_on(fut));
+}
+
+#[test]
+fn lifetimes() {
+    let mut mock = MockLifetimes::new();
+    mock.expect_foo()
+        .returning(|x, y| x + y);
+    mock.expect_bar::<u32>()
+        .returning(|t| format!("{t}"));
+    assert_eq!(5, block_on(mock.foo(&2, &3)));
+    assert_eq!("42", block_on(mock.bar(&42u32)));
+}
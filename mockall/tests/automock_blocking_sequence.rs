@@ -0,0 +1,132 @@
+// vim: tw=80
+//! Sequences that order calls from multiple threads
+
+use mockall::*;
+use std::{
+    sync::Arc,
+    thread,
+    time::Duration
+};
+
+#[automock]
+pub trait Foo {
+    fn foo(&self, x: u32);
+    fn bar(&self);
+}
+
+/// Calls that arrive early should wait for their predecessors.  Had they been
+/// admitted out of order, the worker threads would've panicked.
+#[test]
+fn orders_threads() {
+    let mut seq = Sequence::blocking(Duration::from_secs(60));
+    let mut mock = MockFoo::new();
+    let latches = [Latch::new(), Latch::new(), Latch::new()];
+    for i in 0..3 {
+        mock.expect_foo()
+            .with(predicate::eq(i))
+            .times(1)
+            .blocking_on(&latches[i as usize])
+            .return_const(())
+            .in_sequence(&mut seq);
+    }
+    let mock = Arc::new(mock);
+    // Start the calls in reverse order, making sure that each one is inside
+    // the mock before the next one starts.
+    let threads = (1..3).rev().map(|i| {
+        let mock2 = mock.clone();
+        let t = thread::spawn(move || mock2.foo(i));
+        latches[i as usize].wait_entered(Duration::from_secs(60)).unwrap();
+        t
+    }).collect::<Vec<_>>();
+    for latch in latches.iter() {
+        latch.release();
+    }
+    mock.foo(0);
+    for t in threads {
+        t.join().unwrap();
+    }
+}
+
+#[test]
+fn in_order() {
+    let mut seq = Sequence::blocking(Duration::from_secs(60));
+    let mut mock = MockFoo::new();
+    mock.expect_bar()
+        .times(1)
+        .return_const(())
+        .in_sequence(&mut seq);
+    mock.expect_foo()
+        .times(2)
+        .return_const(())
+        .in_sequence(&mut seq);
+    mock.bar();
+    mock.foo(1);
+    mock.foo(2);
+}
+
+#[test]
+#[should_panic(expected = "Method sequence violation")]
+fn timeout() {
+    let mut seq = Sequence::blocking(Duration::from_millis(10));
+    let mut mock = MockFoo::new();
+    mock.expect_bar()
+        .times(1)
+        .return_const(())
+        .in_sequence(&mut seq);
+    mock.expect_foo()
+        .times(1)
+        .return_const(())
+        .in_sequence(&mut seq);
+    mock.foo(0);
+}
+
+/// A thread that calls ahead of another thread's call should wait for it, even
+/// if it has already made an earlier call in the same sequence.
+#[test]
+fn interleaved() {
+    let mut seq = Sequence::blocking(Duration::from_secs(60));
+    let mut mock = MockFoo::new();
+    let latch = Latch::new();
+    for i in 0..3 {
+        let e = mock.expect_foo();
+        e.with(predicate::eq(i))
+            .times(1)
+            .return_const(())
+            .in_sequence(&mut seq);
+        if i == 2 {
+            e.blocking_on(&latch);
+        }
+    }
+    let mock = Arc::new(mock);
+    let mock2 = mock.clone();
+    let latch2 = latch.clone();
+    let t = thread::spawn(move || {
+        // Wait until the third call is inside the mock
+        latch2.wait_entered(Duration::from_secs(60)).unwrap();
+        latch2.release();
+        mock2.foo(1);
+    });
+    mock.foo(0);
+    mock.foo(2);
+    t.join().unwrap();
+}
+
+/// A blocking sequence should not wait for a call that already happened
+#[test]
+#[should_panic(expected = "called more than 1 times")]
+fn called_again() {
+    let mut seq = Sequence::blocking(Duration::from_secs(60));
+    let mut mock0 = MockFoo::new();
+    let mut mock1 = MockFoo::new();
+    mock0.expect_bar()
+        .times(1)
+        .return_const(())
+        .in_sequence(&mut seq);
+    mock1.expect_bar()
+        .times(1)
+        .return_const(())
+        .in_sequence(&mut seq);
+    mock0.bar();
+    mock1.bar();
+    mock0.bar();
+}
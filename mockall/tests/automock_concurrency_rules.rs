@@ -0,0 +1,211 @@
+// vim: tw=80
+//! Thread affinity and exclusivity rules for expectations
+
+use futures::executor::block_on;
+use mockall::*;
+use std::{
+    future::Future,
+    sync::Arc,
+    thread,
+    time::Duration
+};
+
+#[automock]
+pub trait Foo {
+    fn foo(&self, x: u32) -> u32;
+    fn bar<T: 'static>(&self, t: T);
+    fn baz();
+    fn fut(&self, x: u32) -> impl Future<Output=u32> + Send;
+}
+
+mod on_thread {
+    use super::*;
+
+    #[test]
+    fn ok() {
+        let mut mock = MockFoo::new();
+        mock.expect_foo()
+            .on_thread(thread::current().id())
+            .return_const(0u32);
+        mock.foo(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "but it may only be called on thread")]
+    fn fail() {
+        let mut mock = MockFoo::new();
+        mock.expect_foo()
+            .on_thread(thread::current().id())
+            .return_const(0u32);
+        let mock = Arc::new(mock);
+        let mock2 = mock.clone();
+        let _ = thread::spawn(move || mock2.foo(0)).join();
+    }
+
+    #[test]
+    #[should_panic(expected = "but it may only be called on thread")]
+    fn generic_method() {
+        let mut mock = MockFoo::new();
+        mock.expect_bar::<u32>()
+            .on_thread(thread::current().id())
+            .return_const(());
+        let mock = Arc::new(mock);
+        let mock2 = mock.clone();
+        let _ = thread::spawn(move || mock2.bar(0u32)).join();
+    }
+
+    #[test]
+    fn static_method() {
+        let ctx = MockFoo::baz_context();
+        ctx.expect()
+            .on_thread(thread::current().id())
+            .return_const(());
+        MockFoo::baz();
+    }
+}
+
+mod not_on_thread {
+    use super::*;
+
+    #[test]
+    fn ok() {
+        let mut mock = MockFoo::new();
+        mock.expect_foo()
+            .not_on_thread(thread::current().id())
+            .return_const(0u32);
+        let mock = Arc::new(mock);
+        let mock2 = mock.clone();
+        thread::spawn(move || mock2.foo(0)).join().unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "called on forbidden thread")]
+    fn fail() {
+        let mut mock = MockFoo::new();
+        mock.expect_foo()
+            .not_on_thread(thread::current().id())
+            .return_const(0u32);
+        mock.foo(0);
+    }
+}
+
+mod exclusive {
+    use super::*;
+
+    #[test]
+    fn ok() {
+        let mut mock = MockFoo::new();
+        mock.expect_foo()
+            .exclusive()
+            .returning(|x| x);
+        let mock = Arc::new(mock);
+        let mock2 = mock.clone();
+        thread::spawn(move || mock2.foo(1)).join().unwrap();
+        mock.foo(2);
+    }
+
+    /// A second call while the first is still running
+    #[test]
+    #[should_panic(expected = "call to the same method")]
+    fn overlap() {
+        let latch = Latch::new();
+        let latch2 = latch.clone();
+        let mut mock = MockFoo::new();
+        mock.expect_foo()
+            .with(predicate::eq(1))
+            .exclusive()
+            .returning(move |x| {
+                latch2.wait();
+                x
+            });
+        mock.expect_foo()
+            .with(predicate::eq(2))
+            .return_const(2u32);
+        let mock = Arc::new(mock);
+        let mock2 = mock.clone();
+        let t = thread::spawn(move || mock2.foo(1));
+        latch.wait_entered(Duration::from_secs(60)).unwrap();
+        mock.foo(2);
+        latch.release();
+        // The first call detects the overlap when it finishes
+        let _ = t.join();
+    }
+
+    /// Like `overlap`, but for a method whose futures must be polled
+    #[test]
+    #[should_panic(expected = "call to the same method")]
+    fn overlap_must_be_polled() {
+        let latch = Latch::new();
+        let latch2 = latch.clone();
+        let mut mock = MockFoo::new();
+        mock.expect_fut()
+            .with(predicate::eq(1))
+            .exclusive()
+            .must_be_polled()
+            .returning_async(move |x| {
+                latch2.wait();
+                async move { x }
+            });
+        mock.expect_fut()
+            .with(predicate::eq(2))
+            .return_ready(2u32);
+        let mock = Arc::new(mock);
+        let mock2 = mock.clone();
+        let t = thread::spawn(move || block_on(mock2.fut(1)));
+        latch.wait_entered(Duration::from_secs(60)).unwrap();
+        block_on(mock.fut(2));
+        latch.release();
+        // The first call detects the overlap when it finishes
+        let _ = t.join();
+    }
+
+    /// An exclusive call that begins while another is still running
+    #[test]
+    #[should_panic(expected = "called while another call to the same method")]
+    fn overlap_at_entry() {
+        let latch = Latch::new();
+        let latch2 = latch.clone();
+        let mut mock = MockFoo::new();
+        mock.expect_foo()
+            .with(predicate::eq(1))
+            .exclusive()
+            .return_const(1u32);
+        mock.expect_foo()
+            .with(predicate::eq(2))
+            .returning(move |x| {
+                latch2.wait();
+                x
+            });
+        let mock = Arc::new(mock);
+        let mock2 = mock.clone();
+        let t = thread::spawn(move || mock2.foo(2));
+        latch.wait_entered(Duration::from_secs(60)).unwrap();
+        let mock3 = mock.clone();
+        let _ = thread::spawn(move || mock3.foo(1)).join();
+        latch.release();
+        t.join().unwrap();
+    }
+
+    /// Exclusivity is per-method, so a call to another method may overlap
+    #[test]
+    fn other_method() {
+        let latch = Latch::new();
+        let latch2 = latch.clone();
+        let mut mock = MockFoo::new();
+        mock.expect_foo()
+            .exclusive()
+            .returning(move |x| {
+                latch2.wait();
+                x
+            });
+        mock.expect_bar::<u32>()
+            .return_const(());
+        let mock = Arc::new(mock);
+        let mock2 = mock.clone();
+        let t = thread::spawn(move || mock2.foo(1));
+        latch.wait_entered(Duration::from_secs(60)).unwrap();
+        mock.bar(0u32);
+        latch.release();
+        assert_eq!(1, t.join().unwrap());
+    }
+}
@@ -0,0 +1,87 @@
+// vim: tw=80
+//! Const generic parameters on traits and methods
+
+use mockall::*;
+
+#[automock]
+pub trait Codec<const N: usize> {
+    fn encode(&self, x: u32) -> [u8; N];
+    fn decode(&self, buf: [u8; N]) -> u32;
+}
+
+#[automock]
+pub trait Reader {
+    fn read<const N: usize>(&self) -> [u8; N];
+    fn fill<const N: usize>(&self, buf: [u8; N]) -> usize;
+    fn sized<T: 'static, const N: usize>(&self, t: T) -> [T; N];
+}
+
+#[test]
+fn trait_param() {
+    let mut mock = MockCodec::<4>::new();
+    mock.expect_encode()
+        .returning(|x| x.to_le_bytes());
+    mock.expect_decode()
+        .with(predicate::eq([1, 0, 0, 0]))
+        .returning(u32::from_le_bytes);
+    assert_eq!([5, 0, 0, 0], mock.encode(5));
+    assert_eq!(1, mock.decode([1, 0, 0, 0]));
+}
+
+#[test]
+fn method_param() {
+    let mut mock = MockReader::new();
+    mock.expect_read::<2>()
+        .return_const([1u8, 2]);
+    mock.expect_read::<3>()
+        .return_const([3u8, 4, 5]);
+    assert_eq!([1, 2], mock.read::<2>());
+    assert_eq!([3, 4, 5], mock.read::<3>());
+}
+
+#[test]
+fn method_param_argument() {
+    let mut mock = MockReader::new();
+    mock.expect_fill::<2>()
+        .with(predicate::eq([1u8, 2]))
+        .return_const(2usize);
+    mock.expect_fill::<3>()
+        .return_const(3usize);
+    assert_eq!(2, mock.fill([1, 2]));
+    assert_eq!(3, mock.fill([0, 0, 0]));
+}
+
+#[test]
+fn method_type_and_const_params() {
+    let mut mock = MockReader::new();
+    mock.expect_sized::<u32, 2>()
+        .returning(|t| [t; 2]);
+    mock.expect_sized::<u32, 3>()
+        .returning(|t| [t + 1; 3]);
+    assert_eq!([7, 7], mock.sized::<u32, 2>(7));
+    assert_eq!([8, 8, 8], mock.sized::<u32, 3>(7));
+}
+
+#[test]
+#[should_panic(expected = "No matching expectation found")]
+fn method_param_mismatch() {
+    let mut mock = MockReader::new();
+    mock.expect_read::<2>()
+        .return_const([1u8, 2]);
+    mock.read::<3>();
+}
+
+#[automock]
+pub mod m {
+    pub fn zeroes<const N: usize>() -> [u8; N] {
+        [0; N]
+    }
+}
+
+#[test]
+fn module_function() {
+    let ctx = mock_m::zeroes_context();
+    ctx.expect::<2>()
+        .returning(|| [1, 1]);
+    assert_eq!([1, 1], mock_m::zeroes::<2>());
+}
@@ -0,0 +1,148 @@
+// vim: tw=80
+//! Methods that return trait objects can return nested mock objects
+
+use mockall::*;
+
+#[automock]
+pub trait Session {
+    fn user(&self) -> u32;
+}
+
+#[automock]
+pub trait Db {
+    fn get(&self, key: u32) -> u32;
+    fn put(&mut self, key: u32, value: u32);
+}
+
+#[automock]
+pub trait Client {
+    #[deep]
+    fn session(&self) -> Box<dyn Session + Send>;
+    #[deep]
+    fn db(&self) -> &dyn Db;
+    #[deep]
+    fn db_mut(&mut self) -> &mut dyn Db;
+    fn foo(&self) -> u32;
+}
+
+mod boxed {
+    use super::*;
+
+    #[test]
+    fn returning() {
+        let mut mock = MockClient::new();
+        mock.session_mock()
+            .expect_user()
+            .return_const(7u32);
+        assert_eq!(7, mock.session().user());
+    }
+
+    /// Each call hands out the currently configured nested mock
+    #[test]
+    fn sequential() {
+        let mut mock = MockClient::new();
+        mock.session_mock()
+            .expect_user()
+            .return_const(1u32);
+        let s1 = mock.session();
+        mock.session_mock()
+            .expect_user()
+            .return_const(2u32);
+        let s2 = mock.session();
+        assert_eq!(1, s1.user());
+        assert_eq!(2, s2.user());
+    }
+
+    #[test]
+    #[should_panic(expected =
+        "MockSession::user: Expectation(<anything>) called fewer than 1 times")]
+    fn checkpoint() {
+        let mut mock = MockClient::new();
+        mock.session_mock()
+            .expect_user()
+            .times(1)
+            .return_const(7u32);
+        mock.checkpoint();
+    }
+
+    /// Once handed out, the nested mock is verified when it drops
+    #[test]
+    #[should_panic(expected =
+        "MockSession::user: Expectation(<anything>) called fewer than 1 times")]
+    fn handed_out() {
+        let mut mock = MockClient::new();
+        mock.session_mock()
+            .expect_user()
+            .times(1)
+            .return_const(7u32);
+        let session = mock.session();
+        mock.checkpoint();
+        drop(session);
+    }
+}
+
+mod reference {
+    use super::*;
+
+    #[test]
+    fn returning() {
+        let mut mock = MockClient::new();
+        mock.db_mock()
+            .expect_get()
+            .with(predicate::eq(1))
+            .return_const(42u32);
+        assert_eq!(42, mock.db().get(1));
+    }
+
+    #[test]
+    #[should_panic(expected =
+        "MockDb::get: Expectation(<anything>) called fewer than 1 times")]
+    fn checkpoint() {
+        let mut mock = MockClient::new();
+        mock.db_mock()
+            .expect_get()
+            .times(1)
+            .return_const(42u32);
+        mock.checkpoint();
+    }
+
+    /// The nested mock is verified when its parent drops
+    #[test]
+    #[should_panic(expected =
+        "MockDb::get: Expectation(<anything>) called fewer than 1 times")]
+    fn drop() {
+        let mut mock = MockClient::new();
+        mock.db_mock()
+            .expect_get()
+            .times(1)
+            .return_const(42u32);
+    }
+
+    /// Ordinary methods are unaffected
+    #[test]
+    fn other_methods() {
+        let mut mock = MockClient::new();
+        mock.expect_foo()
+            .return_const(5u32);
+        mock.db_mock()
+            .expect_get()
+            .return_const(42u32);
+        assert_eq!(5, mock.foo());
+        assert_eq!(42, mock.db().get(0));
+    }
+}
+
+mod mutable_reference {
+    use super::*;
+
+    #[test]
+    fn returning() {
+        let mut mock = MockClient::new();
+        mock.db_mut_mock()
+            .expect_put()
+            .with(predicate::eq(1), predicate::eq(2))
+            .times(1)
+            .return_const(());
+        mock.db_mut().put(1, 2);
+    }
+}
@@ -0,0 +1,102 @@
+// vim: tw=80
+//! Mock foreign functions can be exported to native code
+
+use mockall::*;
+
+mod no_mangle {
+    use super::*;
+
+    // Declared "C-unwind" so a failed expectation may unwind to the test
+    #[automock(mod mock_ffi; export;)]
+    extern "C-unwind" {
+        pub fn mockall_export_foo(x: u32) -> i64;
+        // Every should_panic method needs to operate on a separate method so
+        // it doesn't poison other tests
+        pub fn mockall_export_foo1(x: u32) -> i64;
+        pub static mut MOCKALL_EXPORT_LEVEL: i32;
+    }
+
+    /// Calling the foreign declaration, like native code would, hits the mock
+    #[test]
+    fn returning() {
+        let ctx = mock_ffi::mockall_export_foo_context();
+        ctx.expect()
+            .returning(|x| i64::from(x) + 1);
+        assert_eq!(5, unsafe { mockall_export_foo(4) });
+    }
+
+    /// The mock can still be called directly from Rust
+    #[test]
+    fn rust_caller() {
+        let ctx = mock_ffi::mockall_export_foo_context();
+        ctx.expect()
+            .returning(|x| i64::from(x) + 1);
+        assert_eq!(5, unsafe { mock_ffi::mockall_export_foo(4) });
+    }
+
+    #[test]
+    #[should_panic(expected =
+        "mock_ffi::mockall_export_foo1: No matching expectation found")]
+    fn with_no_matches() {
+        let ctx = mock_ffi::mockall_export_foo1_context();
+        ctx.expect()
+            .with(predicate::eq(4))
+            .returning(i64::from);
+        unsafe { mockall_export_foo1(5) };
+    }
+
+    /// Native code sees the mock's statics, too
+    #[test]
+    fn static_() {
+        unsafe { mock_ffi::MOCKALL_EXPORT_LEVEL = 7 };
+        assert_eq!(7, unsafe { MOCKALL_EXPORT_LEVEL });
+    }
+}
+
+mod wrap {
+    use super::*;
+
+    #[automock(mod mock_ffi; export(wrap);)]
+    extern "C-unwind" {
+        #[allow(unused)]
+        pub fn mockall_wrap_bar(x: u32) -> i64;
+    }
+
+    // With `-C link-arg=-Wl,--wrap=mockall_wrap_bar`, native calls to
+    // mockall_wrap_bar would resolve to this symbol.
+    extern "C-unwind" {
+        fn __wrap_mockall_wrap_bar(x: u32) -> i64;
+    }
+
+    #[test]
+    fn returning() {
+        let ctx = mock_ffi::mockall_wrap_bar_context();
+        ctx.expect()
+            .returning(|x| i64::from(x) * 2);
+        assert_eq!(8, unsafe { __wrap_mockall_wrap_bar(4) });
+    }
+}
+
+mod system_abi {
+    use super::*;
+
+    #[automock(mod mock_ffi; export;)]
+    extern "system" {
+        pub fn mockall_export_baz(x: u32) -> i64;
+    }
+
+    /// The exported mock keeps the foreign block's ABI, but may unwind
+    #[test]
+    fn abi() {
+        let _f: unsafe extern "system-unwind" fn(u32) -> i64 =
+            mock_ffi::mockall_export_baz;
+    }
+
+    #[test]
+    fn returning() {
+        let ctx = mock_ffi::mockall_export_baz_context();
+        ctx.expect()
+            .returning(|x| i64::from(x) - 1);
+        assert_eq!(3, unsafe { mockall_export_baz(4) });
+    }
+}
@@ -0,0 +1,64 @@
+// vim: tw=80
+//! Mocking foreign statics
+
+use mockall::*;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(C)]
+pub struct Config {
+    flags: u32,
+    level: i32
+}
+
+extern "C" fn double(x: u32) -> u32 {
+    x * 2
+}
+
+#[automock(mod mock_ffi; static CALLBACK: extern "C" fn(u32) -> u32 = double;)]
+extern "C" {
+    pub static VERSION: i32;
+    pub static CALLBACK: extern "C" fn(u32) -> u32;
+    pub static mut COUNTER: u64;
+    pub static CONFIG: Config;
+    #[allow(unused)]
+    pub fn foo(x: u32) -> i64;
+}
+
+/// Mock statics are zero-initialized, like uninitialized C globals
+#[test]
+fn zeroed() {
+    assert_eq!(Config{flags: 0, level: 0}, unsafe { mock_ffi::CONFIG });
+}
+
+/// Types that can't be zero, like function pointers, need an initial value
+#[test]
+fn initialized() {
+    assert_eq!(6, unsafe { mock_ffi::CALLBACK }(3));
+}
+
+/// A test can override a static until its context drops
+#[test]
+fn set() {
+    {
+        let mut ctx = mock_ffi::VERSION_context();
+        ctx.set(3);
+        assert_eq!(3, *ctx.get());
+    }
+    assert_eq!(0, *mock_ffi::VERSION_context().get());
+}
+
+/// A mock function can use a mock static
+#[test]
+fn with_function() {
+    let ctx = mock_ffi::foo_context();
+    ctx.expect()
+        .returning(|x| {
+            unsafe { mock_ffi::COUNTER += 1 };
+            i64::from(x)
+        });
+    unsafe {
+        mock_ffi::foo(1);
+        mock_ffi::foo(2);
+    }
+    assert_eq!(2, unsafe { mock_ffi::COUNTER });
+}
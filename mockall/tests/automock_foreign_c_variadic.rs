@@ -0,0 +1,90 @@
+// vim: tw=80
+//! Mocking variadic foreign functions
+
+// Defining variadic functions requires the c_variadic feature in the
+// _consumer_ code
+#![cfg_attr(feature = "nightly", feature(c_variadic))]
+
+use cfg_if::cfg_if;
+
+cfg_if! {
+    if #[cfg(feature = "nightly")] {
+        mod m {
+            use mockall::*;
+            use std::os::raw::{c_char, c_int};
+
+            #[automock(mod mock_ffi;)]
+            extern "C" {
+                #[allow(unused)]
+                fn ioctl(fd: std::os::raw::c_int, req: std::os::raw::c_ulong,
+                    ...) -> std::os::raw::c_int;
+                #[allow(unused)]
+                fn log(fmt: *const std::os::raw::c_char, ...);
+                // Every should_panic method needs to operate on a separate
+                // method so it doesn't poison other tests
+                #[allow(unused)]
+                fn ioctl1(fd: std::os::raw::c_int, req: std::os::raw::c_ulong,
+                    ...) -> std::os::raw::c_int;
+            }
+
+            #[test]
+            fn returning() {
+                let ctx = mock_ffi::ioctl_context();
+                ctx.expect()
+                    .with(predicate::eq(3), predicate::eq(42),
+                          predicate::always())
+                    .returning(|_fd, _req, va| unsafe { va.i32() } + 1);
+                assert_eq!(8, unsafe { mock_ffi::ioctl(3, 42, 7 as c_int) });
+            }
+
+            #[test]
+            fn pointer_arguments() {
+                let ctx = mock_ffi::log_context();
+                ctx.expect()
+                    .withf(|fmt, _va| !fmt.is_null())
+                    .returning(|_fmt, va| unsafe {
+                        assert_eq!(5, *va.ptr::<u32>());
+                        assert_eq!(1.5, va.f64());
+                        assert_eq!(usize::MAX, va.usize());
+                    });
+                let x = 5u32;
+                unsafe {
+                    mock_ffi::log(b"%p %f %zu\0".as_ptr() as *const c_char,
+                        &x as *const u32, 1.5f64, usize::MAX);
+                }
+            }
+
+            #[test]
+            #[should_panic(expected =
+                "mock_ffi::ioctl1: No matching expectation found")]
+            fn with_no_matches() {
+                let ctx = mock_ffi::ioctl1_context();
+                ctx.expect()
+                    .with(predicate::eq(3), predicate::eq(42),
+                          predicate::always())
+                    .return_const(0);
+                unsafe { mock_ffi::ioctl1(4, 42) };
+            }
+        }
+
+        mod export {
+            use mockall::*;
+
+            #[automock(mod mock_ffi; export;)]
+            extern "C-unwind" {
+                fn mockall_export_sum(n: std::os::raw::c_int, ...)
+                    -> std::os::raw::c_int;
+            }
+
+            /// Native callers of an exported variadic function hit the mock
+            #[test]
+            fn returning() {
+                let ctx = mock_ffi::mockall_export_sum_context();
+                ctx.expect()
+                    .returning(|n, va| (0..n).map(|_| unsafe { va.i32() })
+                        .sum());
+                assert_eq!(6, unsafe { mockall_export_sum(3, 1, 2, 3) });
+            }
+        }
+    }
+}
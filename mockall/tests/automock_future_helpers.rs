@@ -0,0 +1,86 @@
+// vim: tw=80
+//! return_ready, returning_async, and return_after_pending for methods that
+//! return futures
+
+use futures::{
+    executor::block_on,
+    task::{ArcWake, waker}
+};
+use mockall::*;
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering}
+    },
+    task::{Context, Poll}
+};
+
+#[automock]
+pub trait Foo {
+    fn foo(&self, x: u32) -> impl Future<Output=u32> + Send;
+    fn bar() -> impl Future<Output=String>;
+    fn baz<T: Send + 'static>(t: T) -> impl Future<Output=u32> + Send;
+}
+
+#[derive(Default)]
+struct CountingWaker(AtomicUsize);
+impl ArcWake for CountingWaker {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        arc_self.0.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[test]
+fn return_after_pending() {
+    let mut mock = MockFoo::new();
+    mock.expect_foo()
+        .return_after_pending(3, 42u32);
+    let cw = Arc::new(CountingWaker::default());
+    let w = waker(cw.clone());
+    let mut cx = Context::from_waker(&w);
+    let mut fut = mock.foo(0);
+    for i in 0..3 {
+        assert_eq!(Poll::Pending, Pin::new(&mut fut).poll(&mut cx));
+        assert_eq!(i + 1, cw.0.load(Ordering::Relaxed));
+    }
+    assert_eq!(Poll::Ready(42), Pin::new(&mut fut).poll(&mut cx));
+    assert_eq!(3, cw.0.load(Ordering::Relaxed));
+}
+
+#[test]
+fn return_ready() {
+    let mut mock = MockFoo::new();
+    mock.expect_foo()
+        .return_ready(42u32);
+    assert_eq!(42, block_on(mock.foo(0)));
+    assert_eq!(42, block_on(mock.foo(1)));
+}
+
+#[test]
+fn returning_async() {
+    let mut mock = MockFoo::new();
+    mock.expect_foo()
+        .returning_async(|x| async move { x + 1 });
+    assert_eq!(5, block_on(mock.foo(4)));
+}
+
+#[test]
+fn static_method() {
+    let ctx = MockFoo::bar_context();
+    ctx.expect()
+        .return_after_pending(1, "abc");
+    assert_eq!("abc", block_on(MockFoo::bar()));
+}
+
+#[test]
+fn generic_static_method() {
+    let ctx = MockFoo::baz_context();
+    ctx.expect::<i16>()
+        .returning_async(|t| async move { t as u32 });
+    ctx.expect::<u8>()
+        .return_ready(7u32);
+    assert_eq!(5, block_on(MockFoo::baz(5i16)));
+    assert_eq!(7, block_on(MockFoo::baz(0u8)));
+}
@@ -0,0 +1,57 @@
+// vim: tw=80
+//! automatic-style mocking with generic associated types
+
+use mockall::*;
+
+pub struct Row(u32);
+
+#[automock(type Iter<'a> = std::slice::Iter<'a, u32>;
+           type Wrapped<T> = Vec<T>;)]
+trait Storage {
+    type Iter<'a>: Iterator<Item=&'a u32> where Self: 'a;
+    type Wrapped<T>;
+    fn iter<'a>(&'a self) -> Self::Iter<'a>;
+    fn wrap(&self, x: u32) -> Self::Wrapped<u32>;
+    fn count<'a>(&self, it: Self::Iter<'a>) -> usize;
+}
+
+#[automock(type Rows<'a> = std::slice::Iter<'a, Row>;)]
+trait Table {
+    type Rows<'a>: Iterator<Item=&'a Row> where Self: 'a;
+    fn rows<'a>(&'a self) -> <Self as Table>::Rows<'a>;
+}
+
+#[test]
+fn argument() {
+    let mut mock = MockStorage::new();
+    mock.expect_count()
+        .returning(|it| it.count());
+    let v = vec![1, 2, 3];
+    assert_eq!(3, mock.count(v.iter()));
+}
+
+#[test]
+fn lifetime_param() {
+    static VALUES: [u32; 3] = [1, 2, 3];
+    let mut mock = MockStorage::new();
+    mock.expect_iter()
+        .returning(|| VALUES.iter());
+    assert_eq!(6, mock.iter().sum::<u32>());
+}
+
+#[test]
+fn qself() {
+    static ROWS: [Row; 2] = [Row(4), Row(5)];
+    let mut mock = MockTable::new();
+    mock.expect_rows()
+        .returning(|| ROWS.iter());
+    assert_eq!(9, mock.rows().map(|r| r.0).sum::<u32>());
+}
+
+#[test]
+fn type_param() {
+    let mut mock = MockStorage::new();
+    mock.expect_wrap()
+        .returning(|x| vec![x; 2]);
+    assert_eq!(vec![7, 7], mock.wrap(7));
+}
@@ -0,0 +1,55 @@
+// vim: tw=80
+//! Generic methods whose type parameters are ?Sized
+
+use mockall::*;
+use std::fmt::Debug;
+
+#[automock]
+pub trait Foo {
+    fn write<T: ?Sized + Debug + 'static>(&self, v: &T) -> String;
+    fn write_mut<T>(&self, v: &mut T) -> usize
+        where T: ?Sized + AsMut<[u8]> + 'static;
+    fn bar<T: ?Sized + Debug + 'static>(v: &T) -> String;
+}
+
+#[test]
+fn unsized_type() {
+    let mut mock = MockFoo::new();
+    mock.expect_write::<str>()
+        .withf(|v| v == "abc")
+        .returning(|v| v.to_uppercase());
+    mock.expect_write::<[u32]>()
+        .returning(|v| format!("{}", v.len()));
+    assert_eq!("ABC", mock.write("abc"));
+    assert_eq!("3", mock.write(&[1u32, 2, 3][..]));
+}
+
+#[test]
+fn sized() {
+    let mut mock = MockFoo::new();
+    mock.expect_write::<u32>()
+        .with(predicate::eq(5))
+        .returning(|v| format!("{}", v + 1));
+    assert_eq!("6", mock.write(&5u32));
+}
+
+#[test]
+fn mutable() {
+    let mut mock = MockFoo::new();
+    mock.expect_write_mut::<Vec<u8>>()
+        .returning(|v| {
+            v[0] = 9;
+            v.len()
+        });
+    let mut v = vec![1u8, 2];
+    assert_eq!(2, mock.write_mut(&mut v));
+    assert_eq!(vec![9u8, 2], v);
+}
+
+#[test]
+fn static_method() {
+    let ctx = MockFoo::bar_context();
+    ctx.expect::<str>()
+        .returning(|v| v.to_owned());
+    assert_eq!("abc", MockFoo::bar("abc"));
+}
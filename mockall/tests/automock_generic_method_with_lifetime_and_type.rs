@@ -0,0 +1,94 @@
+// vim: tw=80
+//! A method with both generic lifetime parameters and generic type parameters
+
+use mockall::*;
+
+pub trait Decode<'a>: Sized {
+    fn decode(buf: &'a [u8]) -> Self;
+}
+
+impl<'a> Decode<'a> for u32 {
+    fn decode(buf: &'a [u8]) -> Self {
+        u32::from(buf[0])
+    }
+}
+
+impl<'a> Decode<'a> for u64 {
+    fn decode(buf: &'a [u8]) -> Self {
+        u64::from(buf[0])
+    }
+}
+
+#[automock]
+pub trait Parser {
+    fn parse<'a, T: Decode<'a> + 'static>(&self, buf: &'a [u8]) -> T;
+    fn foo<'a, T: 'static>(&self, x: &'a u32, t: T) -> u32;
+    fn bar<'a, T>(&self, x: &'a u32, t: T) -> &u32
+        where T: Decode<'a> + 'static;
+    fn baz<'a, T: Decode<'a> + 'static>(&mut self, buf: &'a [u8], t: T)
+        -> &mut u32;
+    fn bean<'a, T: Decode<'a> + 'static>(buf: &'a [u8]) -> T;
+}
+
+#[test]
+fn returning() {
+    let mut mock = MockParser::new();
+    mock.expect_parse::<u32>()
+        .returning(|buf| u32::from(buf[0]) + 1);
+    assert_eq!(5u32, mock.parse::<u32>(&[4u8][..]));
+}
+
+/// Expectations for different type parameters are independent, even when the
+/// type parameter only appears in the return type.
+#[test]
+fn return_type_only() {
+    let mut mock = MockParser::new();
+    mock.expect_parse::<u32>()
+        .returning(|buf| u32::from(buf[0]) + 1);
+    mock.expect_parse::<u64>()
+        .returning(|buf| u64::from(buf[0]) + 2);
+    assert_eq!(6u64, mock.parse::<u64>(&[4u8][..]));
+    assert_eq!(5u32, mock.parse::<u32>(&[4u8][..]));
+}
+
+#[test]
+fn withf() {
+    let mut mock = MockParser::new();
+    mock.expect_foo::<i16>()
+        .withf(|x, t| *x == 1 && *t == 2)
+        .returning(|x, t| *x + t as u32);
+    mock.expect_foo::<i16>()
+        .returning(|_, _| 0);
+    let x = 1u32;
+    assert_eq!(3, mock.foo(&x, 2i16));
+    assert_eq!(0, mock.foo(&x, 3i16));
+}
+
+#[test]
+fn return_reference() {
+    let mut mock = MockParser::new();
+    mock.expect_bar::<u64>()
+        .return_const(42u32);
+    let x = 1u32;
+    assert_eq!(42u32, *mock.bar(&x, 5u64));
+}
+
+#[test]
+fn return_mutable_reference() {
+    let mut mock = MockParser::new();
+    mock.expect_baz::<u64>()
+        .return_var(7u32);
+    *mock.baz(&[0u8][..], 0u64) += 1;
+    assert_eq!(8u32, *mock.baz(&[0u8][..], 0u64));
+}
+
+#[test]
+fn static_method() {
+    let ctx = MockParser::bean_context();
+    ctx.expect::<u32>()
+        .returning(|buf| u32::from(buf[0]) * 2);
+    ctx.expect::<u64>()
+        .returning(|buf| u64::from(buf[0]) * 3);
+    assert_eq!(8u32, MockParser::bean::<u32>(&[4u8][..]));
+    assert_eq!(12u64, MockParser::bean::<u64>(&[4u8][..]));
+}
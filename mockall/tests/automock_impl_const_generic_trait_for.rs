@@ -0,0 +1,71 @@
+// vim: tw=80
+//! Structs with const generic parameters
+
+use mockall::*;
+
+pub trait Codec<const N: usize> {
+    fn encode(&self, x: u8) -> [u8; N];
+}
+
+mod inherent {
+    use super::*;
+
+    #[allow(unused)]
+    pub struct Buffer<const N: usize> {
+        buf: [u8; N]
+    }
+
+    #[automock]
+    impl<const N: usize> Buffer<N> {
+        pub fn len(&self) -> usize {
+            N
+        }
+        pub fn new_len(_x: u32) -> usize {
+            N
+        }
+    }
+
+    #[test]
+    fn returning() {
+        let mut mock = MockBuffer::<4>::new();
+        mock.expect_len()
+            .return_const(4usize);
+        assert_eq!(4, mock.len());
+    }
+
+    #[test]
+    fn static_method() {
+        let ctx2 = MockBuffer::<2>::new_len_context();
+        ctx2.expect()
+            .returning(|_| 2);
+        let ctx3 = MockBuffer::<3>::new_len_context();
+        ctx3.expect()
+            .returning(|_| 3);
+        assert_eq!(2, MockBuffer::<2>::new_len(0));
+        assert_eq!(3, MockBuffer::<3>::new_len(0));
+    }
+}
+
+mod trait_impl {
+    use super::*;
+
+    #[allow(unused)]
+    pub struct Buffer<const N: usize> {
+        buf: [u8; N]
+    }
+
+    #[automock]
+    impl<const N: usize> Codec<N> for Buffer<N> {
+        fn encode(&self, x: u8) -> [u8; N] {
+            [x; N]
+        }
+    }
+
+    #[test]
+    fn returning() {
+        let mut mock = MockBuffer::<2>::new();
+        mock.expect_encode()
+            .returning(|x| [x, x]);
+        assert_eq!([7, 7], <MockBuffer<2> as Codec<2>>::encode(&mock, 7));
+    }
+}
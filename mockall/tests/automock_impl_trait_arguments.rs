@@ -0,0 +1,114 @@
+// vim: tw=80
+//! Arguments of type `impl Trait`
+
+use mockall::*;
+use std::fmt::Debug;
+use std::path::{Path, PathBuf};
+
+#[automock]
+pub trait Foo {
+    fn name(&self, x: impl AsRef<str>) -> u32;
+    fn path(&self, x: impl Into<PathBuf>) -> u32;
+    fn bytes(&self, x: impl IntoIterator<Item=u8>) -> u32;
+    fn iter_bytes(&self, x: impl Iterator<Item=u8>) -> u32;
+    fn borrowed(&self, x: impl std::borrow::Borrow<u32>) -> u32;
+    fn closure(&self, f: impl Fn(u32) -> u32 + 'static) -> u32;
+    fn debug(&self, x: impl Debug + 'static) -> u32;
+    fn bar(x: impl AsRef<Path>) -> u32;
+    fn send_into(&self, x: impl Send + Into<u64>) -> u32;
+    fn send_closure(&self, f: impl Send + Fn(u32) -> u32 + 'static) -> u32;
+}
+
+#[test]
+fn as_ref() {
+    let mut mock = MockFoo::new();
+    mock.expect_name()
+        .with(predicate::eq("abc"))
+        .returning(|x| x.len() as u32);
+    assert_eq!(3, mock.name("abc"));
+    assert_eq!(3, mock.name(String::from("abc")));
+}
+
+#[test]
+fn into() {
+    let mut mock = MockFoo::new();
+    mock.expect_path()
+        .with(predicate::eq(PathBuf::from("/tmp")))
+        .return_const(1u32);
+    assert_eq!(1, mock.path("/tmp"));
+    assert_eq!(1, mock.path(PathBuf::from("/tmp")));
+}
+
+#[test]
+fn into_iter() {
+    let mut mock = MockFoo::new();
+    mock.expect_bytes()
+        .with(predicate::eq(vec![1u8, 2, 3]))
+        .returning(|v| v.iter().map(|&x| u32::from(x)).sum());
+    assert_eq!(6, mock.bytes(vec![1, 2, 3]));
+    assert_eq!(6, mock.bytes([1u8, 2, 3].iter().cloned()));
+}
+
+#[test]
+fn iter() {
+    let mut mock = MockFoo::new();
+    mock.expect_iter_bytes()
+        .withf(|v| v.len() == 2)
+        .return_const(2u32);
+    assert_eq!(2, mock.iter_bytes(1..3));
+}
+
+#[test]
+fn borrow() {
+    let mut mock = MockFoo::new();
+    mock.expect_borrowed()
+        .with(predicate::eq(5))
+        .returning(|x| x + 1);
+    assert_eq!(6, mock.borrowed(5u32));
+    assert_eq!(6, mock.borrowed(Box::new(5u32)));
+}
+
+#[test]
+fn closure() {
+    let mut mock = MockFoo::new();
+    mock.expect_closure()
+        .returning(|f| f(42));
+    assert_eq!(43, mock.closure(|x| x + 1));
+}
+
+#[test]
+fn trait_object() {
+    let mut mock = MockFoo::new();
+    mock.expect_debug()
+        .withf(|x| format!("{:?}", x) == "\"abc\"")
+        .return_const(7u32);
+    assert_eq!(7, mock.debug("abc"));
+}
+
+#[test]
+fn static_method() {
+    let ctx = MockFoo::bar_context();
+    ctx.expect()
+        .with(predicate::eq(Path::new("/tmp")))
+        .return_const(4u32);
+    assert_eq!(4, MockFoo::bar("/tmp"));
+    assert_eq!(4, MockFoo::bar(PathBuf::from("/tmp")));
+}
+
+/// Marker traits before the convertible one don't matter
+#[test]
+fn send_into() {
+    let mut mock = MockFoo::new();
+    mock.expect_send_into()
+        .with(predicate::eq(5u64))
+        .return_const(1u32);
+    assert_eq!(1, mock.send_into(5u8));
+}
+
+#[test]
+fn send_closure() {
+    let mut mock = MockFoo::new();
+    mock.expect_send_closure()
+        .returning(|f| f(4));
+    assert_eq!(5, mock.send_closure(|x| x + 1));
+}
@@ -0,0 +1,99 @@
+// vim: tw=80
+//! Making mocked calls block until the test releases them
+
+use mockall::*;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc
+    },
+    thread,
+    time::Duration
+};
+
+#[automock]
+pub trait Foo {
+    fn foo(&self, x: u32) -> u32;
+    fn bar<T: 'static>(&self, t: T) -> u32;
+}
+
+#[test]
+fn blocking_on() {
+    let latch = Latch::new();
+    let done = Arc::new(AtomicBool::new(false));
+    let mut mock = MockFoo::new();
+    mock.expect_foo()
+        .blocking_on(&latch)
+        .returning(|x| x + 1);
+    let mock = Arc::new(mock);
+    let mock2 = mock.clone();
+    let done2 = done.clone();
+    let t = thread::spawn(move || {
+        let r = mock2.foo(4);
+        done2.store(true, Ordering::SeqCst);
+        r
+    });
+    latch.wait_entered(Duration::from_secs(60)).unwrap();
+    assert_eq!(1, latch.entered());
+    thread::sleep(Duration::from_millis(10));
+    assert!(!done.load(Ordering::SeqCst));
+    latch.release();
+    assert_eq!(5, t.join().unwrap());
+    assert!(done.load(Ordering::SeqCst));
+}
+
+/// Once released, the latch no longer blocks anybody
+#[test]
+fn already_released() {
+    let latch = Latch::new();
+    latch.release();
+    assert!(latch.is_released());
+    let mut mock = MockFoo::new();
+    mock.expect_foo()
+        .blocking_on(&latch)
+        .returning(|x| x + 1);
+    assert_eq!(5, mock.foo(4));
+    assert_eq!(1, latch.entered());
+}
+
+/// Only calls that match the blocking expectation should block
+#[test]
+fn only_matching_calls() {
+    let latch = Latch::new();
+    let mut mock = MockFoo::new();
+    mock.expect_foo()
+        .with(predicate::eq(1))
+        .blocking_on(&latch)
+        .return_const(1u32);
+    mock.expect_foo()
+        .with(predicate::eq(2))
+        .return_const(2u32);
+    let mock = Arc::new(mock);
+    let mock2 = mock.clone();
+    let t = thread::spawn(move || mock2.foo(1));
+    latch.wait_entered(Duration::from_secs(60)).unwrap();
+    assert_eq!(2, mock.foo(2));
+    latch.release();
+    assert_eq!(1, t.join().unwrap());
+}
+
+#[test]
+fn generic_method() {
+    let latch = Latch::new();
+    let mut mock = MockFoo::new();
+    mock.expect_bar::<u16>()
+        .blocking_on(&latch)
+        .return_const(7u32);
+    let mock = Arc::new(mock);
+    let mock2 = mock.clone();
+    let t = thread::spawn(move || mock2.bar(0u16));
+    latch.wait_entered(Duration::from_secs(60)).unwrap();
+    latch.release();
+    assert_eq!(7, t.join().unwrap());
+}
+
+#[test]
+fn wait_entered_timeout() {
+    let latch = Latch::new();
+    assert_eq!(Err(Timeout), latch.wait_entered(Duration::from_millis(10)));
+}
@@ -0,0 +1,79 @@
+// vim: tw=80
+//! A mocked module's constants and statics
+
+// mocking modules requires the proc_macro_hygiene feature in the _consumer_
+// code
+#![cfg_attr(feature = "nightly", feature(proc_macro_hygiene))]
+
+use cfg_if::cfg_if;
+
+cfg_if! {
+    if #[cfg(feature = "nightly")] {
+        mod m {
+            use mockall::*;
+            use std::{sync::mpsc, thread, time::Duration};
+
+            #[automock(const LIMIT: u32 = 7; static NAME: &str = "mock";)]
+            #[allow(unused)]
+            mod foo {
+                pub const LIMIT: u32 = 5;
+                pub const DEPTH: u32 = 2;
+                pub static NAME: &str = "real";
+                pub static mut LEVEL: i32 = 1;
+                pub fn bar(_x: u32) -> u32 {unimplemented!()}
+            }
+
+            /// Constants and statics can be overridden by the attribute
+            #[test]
+            fn overridden() {
+                assert_eq!(7, mock_foo::LIMIT);
+                assert_eq!("mock", mock_foo::NAME);
+            }
+
+            /// Others keep their original values
+            #[test]
+            fn verbatim() {
+                assert_eq!(2, mock_foo::DEPTH);
+            }
+
+            /// Mutable statics can be overridden by a single test
+            #[test]
+            fn static_mut() {
+                let mut ctx = mock_foo::LEVEL_context();
+                ctx.set(3);
+                assert_eq!(3, *ctx.get());
+            }
+
+            /// Tests that override the same static take turns
+            #[test]
+            fn static_mut_takes_turns() {
+                let mut ctx = mock_foo::LEVEL_context();
+                ctx.set(4);
+                let (started_tx, started_rx) = mpsc::channel();
+                let (tx, rx) = mpsc::channel();
+                let t = thread::spawn(move || {
+                    started_tx.send(()).unwrap();
+                    let ctx2 = mock_foo::LEVEL_context();
+                    tx.send(*ctx2.get()).unwrap();
+                });
+                started_rx.recv().unwrap();
+                // The second context can't be created until the first drops
+                assert_eq!(Err(mpsc::RecvTimeoutError::Timeout),
+                    rx.recv_timeout(Duration::from_millis(100)));
+                drop(ctx);
+                // And by then, the original value has been restored
+                assert_eq!(Ok(1), rx.recv());
+                t.join().unwrap();
+            }
+
+            /// A mock function can use the overridden values
+            #[test]
+            fn with_function() {
+                let ctx = mock_foo::bar_context();
+                ctx.expect()
+                    .returning(|x| x.min(mock_foo::LIMIT));
+                assert_eq!(7, mock_foo::bar(10));
+            }
+        }
+    }
+}
@@ -0,0 +1,179 @@
+// vim: tw=80
+//! must_be_polled detects futures that are never polled to completion
+
+#![allow(async_fn_in_trait)]
+
+use async_trait::async_trait;
+use futures::{
+    executor::block_on,
+    task::noop_waker
+};
+use mockall::*;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll}
+};
+
+#[automock]
+pub trait Foo {
+    fn foo(&self) -> impl Future<Output=u32> + Send;
+    fn bar() -> impl Future<Output=u32> + Send;
+}
+
+#[automock]
+pub trait AsyncFn {
+    async fn foo(&self) -> u32;
+}
+
+#[automock]
+#[async_trait]
+pub trait AsyncTrait {
+    async fn foo(&self) -> u32;
+}
+
+#[async_trait]
+#[automock]
+pub trait AsyncTraitFirst {
+    async fn foo(&self) -> u32;
+}
+
+pub struct Inherent {}
+#[automock]
+impl Inherent {
+    pub async fn foo(&self) -> u32 {
+        42
+    }
+}
+
+#[test]
+fn awaited() {
+    let mut mock = MockFoo::new();
+    mock.expect_foo()
+        .must_be_polled()
+        .return_ready(42u32);
+    assert_eq!(42, block_on(mock.foo()));
+    mock.checkpoint();
+}
+
+#[test]
+#[should_panic(expected =
+    "MockFoo::foo: Expectation(<anything>) returned 1 future(s) that were never polled to completion")]
+fn checkpoint() {
+    let mut mock = MockFoo::new();
+    mock.expect_foo()
+        .must_be_polled()
+        .return_ready(42u32);
+    let _ = mock.foo();
+    mock.checkpoint();
+}
+
+#[test]
+#[should_panic(expected = "returned 1 future(s) that were never polled")]
+fn drop_mock() {
+    let mut mock = MockFoo::new();
+    mock.expect_foo()
+        .must_be_polled()
+        .return_ready(42u32);
+    assert_eq!(42, block_on(mock.foo()));
+    let _ = mock.foo();
+}
+
+#[test]
+#[should_panic(expected = "returned 1 future(s) that were never polled")]
+fn partially_polled() {
+    let mut mock = MockFoo::new();
+    mock.expect_foo()
+        .must_be_polled()
+        .return_after_pending(2, 42u32);
+    let w = noop_waker();
+    let mut cx = Context::from_waker(&w);
+    let mut fut = mock.foo();
+    assert_eq!(Poll::Pending, Pin::new(&mut fut).poll(&mut cx));
+}
+
+#[test]
+fn not_enabled() {
+    let mut mock = MockFoo::new();
+    mock.expect_foo()
+        .return_ready(42u32);
+    let _ = mock.foo();
+    mock.checkpoint();
+}
+
+#[test]
+#[should_panic(expected = "returned 1 future(s) that were never polled")]
+fn static_method() {
+    let ctx = MockFoo::bar_context();
+    ctx.expect()
+        .must_be_polled()
+        .return_ready(42u32);
+    let _ = MockFoo::bar();
+    ctx.checkpoint();
+}
+
+#[test]
+fn async_fn_awaited() {
+    let mut mock = MockAsyncFn::new();
+    mock.expect_foo()
+        .must_be_polled()
+        .return_const(42u32);
+    assert_eq!(42, block_on(mock.foo()));
+    mock.checkpoint();
+}
+
+#[test]
+#[should_panic(expected =
+    "MockAsyncFn::foo: Expectation(<anything>) returned 1 future(s) that were never polled to completion")]
+fn async_fn() {
+    let mut mock = MockAsyncFn::new();
+    mock.expect_foo()
+        .must_be_polled()
+        .return_const(42u32);
+    let _ = mock.foo();
+    mock.checkpoint();
+}
+
+#[test]
+fn async_trait_awaited() {
+    let mut mock = MockAsyncTrait::new();
+    mock.expect_foo()
+        .must_be_polled()
+        .return_const(42u32);
+    assert_eq!(42, block_on(mock.foo()));
+    mock.checkpoint();
+}
+
+#[test]
+#[should_panic(expected =
+    "MockAsyncTrait::foo: Expectation(<anything>) returned 1 future(s) that were never polled to completion")]
+fn async_trait() {
+    let mut mock = MockAsyncTrait::new();
+    mock.expect_foo()
+        .must_be_polled()
+        .return_const(42u32);
+    let _ = mock.foo();
+    mock.checkpoint();
+}
+
+#[test]
+#[should_panic(expected = "returned 1 future(s) that were never polled")]
+fn async_trait_first() {
+    let mut mock = MockAsyncTraitFirst::new();
+    mock.expect_foo()
+        .must_be_polled()
+        .return_const(42u32);
+    let _ = mock.foo();
+    mock.checkpoint();
+}
+
+/// An inherent async fn doesn't call its Expectation until its future is polled
+#[test]
+fn inherent_async_fn_is_lazy() {
+    let mut mock = MockInherent::new();
+    mock.expect_foo()
+        .never();
+    let fut = mock.foo();
+    drop(fut);
+    mock.checkpoint();
+}
@@ -0,0 +1,80 @@
+// vim: tw=80
+//! Arguments that are trait objects with elided or non-'static lifetimes
+
+use mockall::*;
+use std::fmt::Debug;
+
+pub trait Visitor<'a> {
+    fn visit(&mut self, s: &'a str);
+}
+
+struct Collect<'a>(Vec<&'a str>);
+impl<'a> Visitor<'a> for Collect<'a> {
+    fn visit(&mut self, s: &'a str) {
+        self.0.push(s)
+    }
+}
+
+#[automock]
+pub trait Foo {
+    fn debug(&self, x: &dyn Debug) -> u32;
+    fn visit(&self, v: &mut dyn Visitor<'_>);
+    fn both(&self, x: &(dyn Debug + Send), y: u32) -> u32;
+    fn static_debug(x: &dyn Debug) -> u32;
+    fn imp(&self, x: impl Debug) -> u32;
+}
+
+#[test]
+fn debug() {
+    let mut mock = MockFoo::new();
+    mock.expect_debug()
+        .withf(|x| format!("{:?}", x) == "5")
+        .return_const(1u32);
+    mock.expect_debug()
+        .return_const(2u32);
+    let five = 5;
+    let six = 6;
+    assert_eq!(1, mock.debug(&five));
+    assert_eq!(2, mock.debug(&six));
+}
+
+#[test]
+fn visitor() {
+    let mut mock = MockFoo::new();
+    mock.expect_visit()
+        .withf(|_| true)
+        .returning(|v| v.visit("hello"));
+    let mut c = Collect(Vec::new());
+    mock.visit(&mut c);
+    assert_eq!(vec!["hello"], c.0);
+}
+
+#[test]
+fn multiple_args() {
+    let mut mock = MockFoo::new();
+    mock.expect_both()
+        .withf(|x, y| format!("{:?}", x) == "\"a\"" && *y == 1)
+        .returning(|_, y| y + 1);
+    let s = String::from("a");
+    assert_eq!(2, mock.both(&s.as_str(), 1));
+}
+
+#[test]
+fn static_method() {
+    let ctx = MockFoo::static_debug_context();
+    ctx.expect()
+        .withf(|x| format!("{:?}", x) == "[1, 2]")
+        .return_const(3u32);
+    let v = vec![1, 2];
+    assert_eq!(3, MockFoo::static_debug(&v));
+}
+
+#[test]
+fn impl_trait() {
+    let mut mock = MockFoo::new();
+    mock.expect_imp()
+        .withf(|x| format!("{:?}", x) == "\"abc\"")
+        .return_const(4u32);
+    let s = String::from("abc");
+    assert_eq!(4, mock.imp(&s));
+}
@@ -0,0 +1,132 @@
+// vim: tw=80
+//! A returning closure may call back into the same mock
+
+use mockall::*;
+use std::sync::{Arc, Mutex, Weak};
+
+#[automock]
+pub trait Foo {
+    fn foo(&self, x: u32) -> u32;
+    fn bar(&self, x: u32) -> u32;
+    fn fact(x: u32) -> u32;
+    fn baz(x: u32) -> u32;
+    fn qux(x: u32) -> u32;
+    fn quux(x: u32) -> u32;
+    fn recurse(x: u32) -> u32;
+    fn generic<T: 'static>(t: T) -> u32;
+}
+
+/// A non-static method may call another method of the same mock
+#[test]
+fn other_method() {
+    let this = Arc::new(Mutex::new(Weak::<MockFoo>::new()));
+    let this2 = this.clone();
+    let mut mock = MockFoo::new();
+    mock.expect_foo()
+        .returning(move |x| this2.lock().unwrap().upgrade().unwrap().bar(x));
+    mock.expect_bar()
+        .returning(|x| x + 1);
+    let mock = Arc::new(mock);
+    *this.lock().unwrap() = Arc::downgrade(&mock);
+    assert_eq!(6, mock.foo(5));
+}
+
+/// A non-static method may call itself, if the inner call matches a different
+/// expectation.
+#[test]
+fn same_method() {
+    let this = Arc::new(Mutex::new(Weak::<MockFoo>::new()));
+    let this2 = this.clone();
+    let mut mock = MockFoo::new();
+    mock.expect_foo()
+        .with(predicate::eq(0))
+        .return_const(1u32);
+    mock.expect_foo()
+        .with(predicate::eq(1))
+        .returning(move |x| this2.lock().unwrap().upgrade().unwrap().foo(x - 1));
+    let mock = Arc::new(mock);
+    *this.lock().unwrap() = Arc::downgrade(&mock);
+    assert_eq!(1, mock.foo(1));
+}
+
+/// Re-entering the same expectation fails instead of deadlocking
+#[test]
+#[should_panic(expected = "called from within its own returning closure")]
+fn same_expectation() {
+    let this = Arc::new(Mutex::new(Weak::<MockFoo>::new()));
+    let this2 = this.clone();
+    let mut mock = MockFoo::new();
+    mock.expect_foo()
+        .returning(move |x| this2.lock().unwrap().upgrade().unwrap().foo(x));
+    let mock = Arc::new(mock);
+    *this.lock().unwrap() = Arc::downgrade(&mock);
+    mock.foo(1);
+}
+
+/// A static method may recurse, as long as the inner call matches a different
+/// expectation.
+#[test]
+fn static_recursion() {
+    let ctx = MockFoo::fact_context();
+    ctx.expect()
+        .with(predicate::eq(0))
+        .return_const(1u32);
+    ctx.expect()
+        .with(predicate::eq(1))
+        .returning(|x| x * MockFoo::fact(x - 1));
+    ctx.expect()
+        .with(predicate::eq(2))
+        .returning(|x| x * MockFoo::fact(x - 1));
+    ctx.expect()
+        .with(predicate::eq(3))
+        .returning(|x| x * MockFoo::fact(x - 1));
+    assert_eq!(6, MockFoo::fact(3));
+}
+
+/// A static method's closure may add expectations to itself
+#[test]
+fn static_expect() {
+    let ctx = MockFoo::baz_context();
+    ctx.expect()
+        .with(predicate::eq(1))
+        .returning(|x| {
+            let ctx = MockFoo::baz_context();
+            ctx.expect()
+                .with(predicate::eq(2))
+                .return_const(2u32);
+            MockFoo::baz(x + 1)
+        });
+    assert_eq!(2, MockFoo::baz(1));
+}
+
+/// A static method may call another static method
+#[test]
+fn static_other_method() {
+    let ctx = MockFoo::qux_context();
+    ctx.expect()
+        .returning(|x| MockFoo::quux(x) + 1);
+    let ctx2 = MockFoo::quux_context();
+    ctx2.expect()
+        .with(predicate::eq(100))
+        .return_const(5u32);
+    assert_eq!(6, MockFoo::qux(100));
+}
+
+#[test]
+#[should_panic(expected = "called from within its own returning closure")]
+fn static_same_expectation() {
+    let ctx = MockFoo::recurse_context();
+    ctx.expect()
+        .returning(MockFoo::recurse);
+    MockFoo::recurse(1);
+}
+
+#[test]
+fn generic_static_recursion() {
+    let ctx = MockFoo::generic_context();
+    ctx.expect::<u32>()
+        .returning(|x| MockFoo::generic(u64::from(x)) + 1);
+    ctx.expect::<u64>()
+        .returning(|_| 41);
+    assert_eq!(42, MockFoo::generic(0u32));
+}
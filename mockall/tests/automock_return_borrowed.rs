@@ -0,0 +1,100 @@
+// vim: tw=80
+//! Methods whose return values borrow from self, without being bare references
+
+use mockall::*;
+use std::collections::HashMap;
+
+#[automock]
+pub trait Foo {
+    fn get(&self, k: &str) -> Option<&u32>;
+    fn bytes(&self, x: u32) -> Result<&[u8], u32>;
+    fn iter(&self) -> std::slice::Iter<'_, u32>;
+    fn get_mut(&mut self, k: u32) -> Option<&mut String>;
+    fn pair(&self) -> (&u32, &str);
+}
+
+#[test]
+fn option() {
+    let mut mock = MockFoo::new();
+    let map: HashMap<String, u32> = [("a".to_owned(), 1), ("b".to_owned(), 2)]
+        .iter().cloned().collect();
+    mock.expect_get()
+        .returning_from(map, |m, k| m.get(k));
+    assert_eq!(Some(&1), mock.get("a"));
+    assert_eq!(Some(&2), mock.get("b"));
+    assert_eq!(None, mock.get("c"));
+}
+
+#[test]
+fn result() {
+    let mut mock = MockFoo::new();
+    mock.expect_bytes()
+        .returning_from(vec![1u8, 2, 3], |v, x| {
+            if x == 0 {
+                Err(x)
+            } else {
+                Ok(&v[..x as usize])
+            }
+        });
+    assert_eq!(Ok(&[1u8, 2][..]), mock.bytes(2));
+    assert_eq!(Err(0), mock.bytes(0));
+}
+
+#[test]
+fn iter() {
+    let mut mock = MockFoo::new();
+    mock.expect_iter()
+        .returning_from(vec![4u32, 5, 6], |v| v.iter());
+    assert_eq!(vec![4, 5, 6], mock.iter().cloned().collect::<Vec<_>>());
+}
+
+#[test]
+fn mutable() {
+    let mut mock = MockFoo::new();
+    mock.expect_get_mut()
+        .returning_from(vec![String::from("x")], |v, k| v.get_mut(k as usize));
+    mock.get_mut(0).unwrap().push('y');
+    assert_eq!("xy", mock.get_mut(0).unwrap());
+    assert!(mock.get_mut(1).is_none());
+}
+
+#[test]
+fn tuple() {
+    let mut mock = MockFoo::new();
+    mock.expect_pair()
+        .returning_from((7u32, String::from("seven")),
+                        |s| (&s.0, s.1.as_str()));
+    assert_eq!((&7, "seven"), mock.pair());
+}
+
+#[test]
+fn with() {
+    let mut mock = MockFoo::new();
+    mock.expect_bytes()
+        .with(predicate::eq(1))
+        .returning_from(vec![1u8], |v, _| Ok(&v[..]));
+    mock.expect_bytes()
+        .with(predicate::eq(2))
+        .returning_from(vec![2u8], |v, _| Ok(&v[..]));
+    assert_eq!(Ok(&[2u8][..]), mock.bytes(2));
+    assert_eq!(Ok(&[1u8][..]), mock.bytes(1));
+}
+
+#[test]
+#[should_panic(expected =
+    "MockFoo::get: Expectation(<anything>) No return value was set")]
+fn no_return_value() {
+    let mut mock = MockFoo::new();
+    mock.expect_get();
+    mock.get("a");
+}
+
+#[test]
+#[should_panic(expected =
+    "MockFoo::iter: Expectation(<anything>) called fewer than 1 times")]
+fn times() {
+    let mut mock = MockFoo::new();
+    mock.expect_iter()
+        .times(1)
+        .returning_from(Vec::new(), |v| v.iter());
+}
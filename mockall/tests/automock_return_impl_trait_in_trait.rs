@@ -0,0 +1,49 @@
+// vim: tw=80
+//! A trait whose methods return `impl Trait`
+
+use futures::executor::block_on;
+use mockall::*;
+use std::{
+    fmt::Debug,
+    future::Future
+};
+
+#[automock]
+pub trait Foo {
+    fn foo(&self) -> impl Debug;
+    fn bar(&self, x: u32) -> impl Iterator<Item=u32>;
+    fn baz(&self) -> impl Future<Output=u32> + Send;
+    fn bean() -> impl Debug;
+}
+
+#[test]
+fn debug() {
+    let mut mock = MockFoo::new();
+    mock.expect_foo()
+        .returning(|| Box::new(42u32));
+    assert_eq!("42", format!("{:?}", mock.foo()));
+}
+
+#[test]
+fn future() {
+    let mut mock = MockFoo::new();
+    mock.expect_baz()
+        .returning(|| Box::pin(async { 42 }));
+    assert_eq!(42, block_on(mock.baz()));
+}
+
+#[test]
+fn iterator() {
+    let mut mock = MockFoo::new();
+    mock.expect_bar()
+        .returning(|x| Box::new(0..x));
+    assert_eq!(vec![0, 1, 2], mock.bar(3).collect::<Vec<_>>());
+}
+
+#[test]
+fn static_method() {
+    let ctx = MockFoo::bean_context();
+    ctx.expect()
+        .returning(|| Box::new("hello"));
+    assert_eq!("\"hello\"", format!("{:?}", MockFoo::bean()));
+}
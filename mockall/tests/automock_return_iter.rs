@@ -0,0 +1,45 @@
+// vim: tw=80
+//! return_iter for methods that return iterators
+
+use mockall::*;
+
+#[automock]
+pub trait Foo {
+    fn foo(&self) -> impl Iterator<Item=u32>;
+    fn bar(&self, x: u32) -> Box<dyn Iterator<Item=String> + Send>;
+    fn baz() -> impl Iterator<Item=u8>;
+}
+
+#[test]
+fn impl_iterator() {
+    let mut mock = MockFoo::new();
+    mock.expect_foo()
+        .return_iter(vec![1, 2, 3]);
+    assert_eq!(vec![1, 2, 3], mock.foo().collect::<Vec<_>>());
+    // Each call gets a fresh iterator
+    assert_eq!(vec![1, 2, 3], mock.foo().collect::<Vec<_>>());
+}
+
+#[test]
+fn boxed_iterator() {
+    let mut mock = MockFoo::new();
+    mock.expect_bar()
+        .return_iter(vec![String::from("a"), String::from("b")]);
+    assert_eq!(vec!["a", "b"], mock.bar(0).collect::<Vec<_>>());
+}
+
+#[test]
+fn range() {
+    let mut mock = MockFoo::new();
+    mock.expect_foo()
+        .return_iter(0..4);
+    assert_eq!(6, mock.foo().sum::<u32>());
+}
+
+#[test]
+fn static_method() {
+    let ctx = MockFoo::baz_context();
+    ctx.expect()
+        .return_iter(vec![4, 5]);
+    assert_eq!(vec![4, 5], MockFoo::baz().collect::<Vec<_>>());
+}
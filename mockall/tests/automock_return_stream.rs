@@ -0,0 +1,78 @@
+// vim: tw=80
+//! return_stream and items_pulled for methods that return streams
+#![cfg(feature = "stream")]
+
+use futures::{
+    executor::block_on,
+    stream::{Stream, StreamExt}
+};
+use mockall::*;
+use std::pin::Pin;
+
+#[automock]
+pub trait Foo {
+    fn foo(&self) -> impl Stream<Item=u32> + Send;
+    fn bar(&self, x: u32) -> Pin<Box<dyn Stream<Item=String>>>;
+    fn baz() -> impl Stream<Item=u8> + Send;
+}
+
+#[test]
+fn impl_stream() {
+    let mut mock = MockFoo::new();
+    mock.expect_foo()
+        .return_stream(vec![1, 2, 3]);
+    assert_eq!(vec![1, 2, 3], block_on(mock.foo().collect::<Vec<_>>()));
+}
+
+#[test]
+fn boxed_stream() {
+    let mut mock = MockFoo::new();
+    mock.expect_bar()
+        .return_stream(vec![String::from("a")]);
+    assert_eq!(vec!["a"], block_on(mock.bar(0).collect::<Vec<_>>()));
+}
+
+#[test]
+fn items_pulled_ok() {
+    let mut mock = MockFoo::new();
+    mock.expect_foo()
+        .return_stream(vec![1, 2, 3])
+        .items_pulled(1..3);
+    let mut stream = mock.foo();
+    assert_eq!(Some(1), block_on(stream.next()));
+    assert_eq!(Some(2), block_on(stream.next()));
+    drop(stream);
+    mock.checkpoint();
+}
+
+#[test]
+#[should_panic(expected = "MockFoo::foo: Expectation(<anything>) returned a stream from which 1 item(s) were pulled instead of 3")]
+fn items_pulled_too_few() {
+    let mut mock = MockFoo::new();
+    mock.expect_foo()
+        .return_stream(vec![1, 2, 3])
+        .items_pulled(3);
+    let mut stream = mock.foo();
+    assert_eq!(Some(1), block_on(stream.next()));
+    drop(stream);
+    mock.checkpoint();
+}
+
+#[test]
+#[should_panic(expected = "returned a stream from which 0 item(s) were pulled instead of at least 1")]
+fn items_pulled_dropped() {
+    let mut mock = MockFoo::new();
+    mock.expect_foo()
+        .return_stream(vec![1, 2, 3])
+        .items_pulled(1..);
+    let _ = mock.foo();
+}
+
+#[test]
+fn static_method() {
+    let ctx = MockFoo::baz_context();
+    ctx.expect()
+        .return_stream(vec![4, 5])
+        .items_pulled(2);
+    assert_eq!(vec![4, 5], block_on(MockFoo::baz().collect::<Vec<_>>()));
+}
@@ -0,0 +1,116 @@
+// vim: tw=80
+//! Methods with typed self receivers, like `self: Box<Self>`
+
+use mockall::*;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+#[automock]
+pub trait Foo {
+    fn boxed(self: Box<Self>, x: u32) -> u32;
+    fn rc(self: Rc<Self>, x: u32) -> u32;
+    fn arc(self: Arc<Self>, x: u32) -> u32;
+    fn pinned(self: Pin<&mut Self>, x: u32) -> u32;
+    fn by_ref(self: &Self) -> u32;
+    fn by_mut(self: &mut Self) -> &mut u32;
+}
+
+/// A custom poll-based trait
+#[automock]
+pub trait Source {
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>)
+        -> Poll<Option<u32>>;
+}
+
+#[test]
+fn boxed() {
+    let mut mock = MockFoo::new();
+    mock.expect_boxed()
+        .with(predicate::eq(4))
+        .returning(|x| x + 1);
+    assert_eq!(5, Box::new(mock).boxed(4));
+}
+
+/// The mock is verified when the boxed receiver is consumed
+#[test]
+#[should_panic(expected =
+    "MockFoo::by_ref: Expectation(<anything>) called fewer than 1 times")]
+fn boxed_drop() {
+    let mut mock = MockFoo::new();
+    mock.expect_boxed()
+        .return_const(0u32);
+    mock.expect_by_ref()
+        .times(1)
+        .return_const(0u32);
+    Box::new(mock).boxed(4);
+}
+
+#[test]
+fn rc() {
+    let mut mock = MockFoo::new();
+    mock.expect_rc()
+        .returning(|x| x + 2);
+    let mock = Rc::new(mock);
+    assert_eq!(6, mock.clone().rc(4));
+    assert_eq!(7, mock.rc(5));
+}
+
+#[test]
+fn arc() {
+    let mut mock = MockFoo::new();
+    mock.expect_arc()
+        .returning(|x| x + 3);
+    assert_eq!(7, Arc::new(mock).arc(4));
+}
+
+#[test]
+fn pinned() {
+    let mut mock = MockFoo::new();
+    mock.expect_pinned()
+        .with(predicate::eq(4))
+        .return_const(8u32);
+    assert_eq!(8, Pin::new(&mut mock).pinned(4));
+}
+
+#[test]
+fn by_ref() {
+    let mut mock = MockFoo::new();
+    mock.expect_by_ref()
+        .return_const(9u32);
+    assert_eq!(9, mock.by_ref());
+}
+
+#[test]
+fn by_mut() {
+    let mut mock = MockFoo::new();
+    mock.expect_by_mut()
+        .return_var(10u32);
+    *mock.by_mut() += 1;
+    assert_eq!(11, *mock.by_mut());
+}
+
+#[test]
+fn poll() {
+    let waker = futures::task::noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    let mut mock = MockSource::new();
+    let mut seq = Sequence::new();
+    mock.expect_poll_next()
+        .times(1)
+        .in_sequence(&mut seq)
+        .return_const(Poll::Pending);
+    mock.expect_poll_next()
+        .times(1)
+        .in_sequence(&mut seq)
+        .return_const(Poll::Ready(Some(5)));
+    mock.expect_poll_next()
+        .times(1)
+        .in_sequence(&mut seq)
+        .return_const(Poll::Ready(None));
+    let mut mock = Box::pin(mock);
+    assert_eq!(Poll::Pending, mock.as_mut().poll_next(&mut cx));
+    assert_eq!(Poll::Ready(Some(5)), mock.as_mut().poll_next(&mut cx));
+    assert_eq!(Poll::Ready(None), mock.as_mut().poll_next(&mut cx));
+}
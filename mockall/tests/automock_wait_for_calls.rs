@@ -0,0 +1,89 @@
+// vim: tw=80
+//! Waiting for other threads to call a mock object
+
+use mockall::*;
+use std::{
+    sync::Arc,
+    thread,
+    time::Duration
+};
+
+#[automock]
+pub trait Foo {
+    fn foo(&self, x: u32) -> u32;
+    fn bar<T: 'static>(&self, t: T);
+    fn baz();
+}
+
+#[test]
+fn wait_for_calls() {
+    let mut mock = MockFoo::new();
+    let handle = mock.expect_foo()
+        .returning(|x| x)
+        .handle();
+    let mock = Arc::new(mock);
+    let mock2 = mock.clone();
+    let t = thread::spawn(move || {
+        for i in 0..3 {
+            thread::sleep(Duration::from_millis(10));
+            mock2.foo(i);
+        }
+    });
+    handle.wait_for_calls(3, Duration::from_secs(60)).unwrap();
+    assert_eq!(3, handle.calls());
+    t.join().unwrap();
+}
+
+#[test]
+fn wait_for_calls_timeout() {
+    let mut mock = MockFoo::new();
+    let handle = mock.expect_foo()
+        .returning(|x| x)
+        .handle();
+    mock.foo(0);
+    assert_eq!(Err(Timeout),
+               handle.wait_for_calls(2, Duration::from_millis(10)));
+}
+
+#[test]
+fn wait_until_satisfied() {
+    let mut mock = MockFoo::new();
+    mock.expect_foo()
+        .times(2)
+        .returning(|x| x);
+    mock.expect_bar::<u8>()
+        .times(1)
+        .return_const(());
+    let mock = Arc::new(mock);
+    let mock2 = mock.clone();
+    let t = thread::spawn(move || {
+        mock2.foo(0);
+        mock2.bar(0u8);
+        mock2.foo(1);
+    });
+    mock.wait_until_satisfied(Duration::from_secs(60)).unwrap();
+    t.join().unwrap();
+}
+
+#[test]
+fn wait_until_satisfied_timeout() {
+    let mut mock = MockFoo::new();
+    mock.expect_foo()
+        .times(2)
+        .returning(|x| x);
+    mock.foo(0);
+    assert_eq!(Err(Timeout),
+               mock.wait_until_satisfied(Duration::from_millis(10)));
+    mock.foo(1);
+}
+
+#[test]
+fn static_method() {
+    let ctx = MockFoo::baz_context();
+    let handle = ctx.expect()
+        .return_const(())
+        .handle();
+    let t = thread::spawn(MockFoo::baz);
+    handle.wait_for_calls(1, Duration::from_secs(60)).unwrap();
+    t.join().unwrap();
+}
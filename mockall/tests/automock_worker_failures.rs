@@ -0,0 +1,167 @@
+// vim: tw=80
+//! Failures on worker threads should be reported again by the thread that owns
+//! the mock object, even if the worker's panic is swallowed.
+
+use mockall::*;
+use std::{
+    panic,
+    sync::Arc,
+    thread
+};
+
+#[automock]
+pub trait Foo {
+    fn foo(&self, x: u32) -> u32;
+    fn bar<T: 'static>(&self, t: T);
+    fn baz(x: u32) -> u32;
+    fn bean(x: u32) -> u32;
+    fn cob<T: 'static>(t: T);
+}
+
+#[automock]
+pub mod m {
+    pub fn qux(x: u32) -> u32 {
+        x
+    }
+}
+
+/// Call `f` with the mock on another thread, and ignore any panic there
+fn on_worker<F>(mock: &Arc<MockFoo>, f: F)
+    where F: FnOnce(&MockFoo) + Send + 'static
+{
+    let mock2 = mock.clone();
+    let _ = thread::spawn(move || f(&mock2)).join();
+}
+
+#[test]
+#[should_panic(expected = "MockFoo::foo: No matching expectation found")]
+fn checkpoint() {
+    let mut mock = MockFoo::new();
+    mock.expect_foo()
+        .with(predicate::eq(1))
+        .return_const(1u32);
+    let mut mock = Arc::new(mock);
+    on_worker(&mock, |m| {m.foo(2);});
+    Arc::get_mut(&mut mock).unwrap().checkpoint();
+}
+
+#[test]
+#[should_panic(expected = "called more than 1 times")]
+fn drop() {
+    let mut mock = MockFoo::new();
+    mock.expect_foo()
+        .times(1)
+        .return_const(1u32);
+    let mock = Arc::new(mock);
+    on_worker(&mock, |m| {
+        m.foo(1);
+        m.foo(1);
+    });
+}
+
+#[test]
+#[should_panic(expected = "MockFoo::bar: No matching expectation found")]
+fn generic_method() {
+    let mut mock = MockFoo::new();
+    mock.expect_bar::<u16>()
+        .return_const(());
+    let mock = Arc::new(mock);
+    on_worker(&mock, |m| m.bar(0u32));
+}
+
+/// Panics from within returning closures are the caller's business
+#[test]
+fn returning() {
+    let mut mock = MockFoo::new();
+    mock.expect_foo()
+        .returning(|x| {
+            assert!(x < 5, "Unexpected argument {}", x);
+            x
+        });
+    let mock = Arc::new(mock);
+    on_worker(&mock, |m| {m.foo(7);});
+}
+
+/// A failure that the owning thread already caught shouldn't be raised again
+#[test]
+fn same_thread() {
+    let mut mock = MockFoo::new();
+    mock.expect_foo()
+        .with(predicate::eq(1))
+        .return_const(1u32);
+    let r = panic::catch_unwind(panic::AssertUnwindSafe(|| mock.foo(2)));
+    assert!(r.is_err());
+    assert_eq!(1, mock.foo(1));
+}
+
+/// Once reported, a failure shouldn't be reported again
+#[test]
+fn reported_once() {
+    let mut mock = MockFoo::new();
+    mock.expect_foo()
+        .with(predicate::eq(1))
+        .return_const(1u32);
+    let mut mock = Arc::new(mock);
+    on_worker(&mock, |m| {m.foo(2);});
+    let r = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        Arc::get_mut(&mut mock).unwrap().checkpoint();
+    }));
+    assert!(r.is_err());
+    Arc::get_mut(&mut mock).unwrap().checkpoint();
+}
+
+#[test]
+fn no_failures() {
+    let mut mock = MockFoo::new();
+    mock.expect_foo()
+        .times(2)
+        .returning(|x| x + 1);
+    let mock = Arc::new(mock);
+    on_worker(&mock, |m| {
+        assert_eq!(2, m.foo(1));
+        assert_eq!(3, m.foo(2));
+    });
+}
+
+#[test]
+#[should_panic(expected = "MockFoo::baz: No matching expectation found")]
+fn static_method_checkpoint() {
+    let ctx = MockFoo::baz_context();
+    ctx.expect()
+        .with(predicate::eq(1))
+        .return_const(1u32);
+    let _ = thread::spawn(|| MockFoo::baz(2)).join();
+    ctx.checkpoint();
+}
+
+#[test]
+#[should_panic(expected = "called more than 1 times")]
+fn static_method_drop() {
+    let ctx = MockFoo::bean_context();
+    ctx.expect()
+        .times(1)
+        .return_const(1u32);
+    let _ = thread::spawn(|| {
+        MockFoo::bean(1);
+        MockFoo::bean(1);
+    }).join();
+}
+
+#[test]
+#[should_panic(expected = "MockFoo::cob: No matching expectation found")]
+fn generic_static_method() {
+    let ctx = MockFoo::cob_context();
+    ctx.expect::<u16>()
+        .returning(|_| ());
+    let _ = thread::spawn(|| MockFoo::cob(0u32)).join();
+}
+
+#[test]
+#[should_panic(expected = "mock_m::qux: No matching expectation found")]
+fn free_function() {
+    let ctx = mock_m::qux_context();
+    ctx.expect()
+        .with(predicate::eq(1))
+        .return_const(1u32);
+    let _ = thread::spawn(|| mock_m::qux(2)).join();
+}
@@ -0,0 +1,41 @@
+// vim: tw=80
+//! mock!-style mocking with associated constants
+
+use mockall::*;
+
+trait A {
+    const MAX: usize;
+    const MIN: usize = 1;
+    fn foo(&self) -> usize;
+}
+
+mock! {
+    pub Foo {
+        const LIMIT: u32 = 7;
+        fn bar(&self) -> u32;
+    }
+    trait A {
+        const MAX: usize = 16;
+        const MIN: usize = 1;
+        fn foo(&self) -> usize;
+    }
+}
+
+#[test]
+fn inherent() {
+    assert_eq!(7, MockFoo::LIMIT);
+}
+
+#[test]
+fn trait_const() {
+    assert_eq!(16, <MockFoo as A>::MAX);
+    assert_eq!(1, <MockFoo as A>::MIN);
+}
+
+#[test]
+fn returning() {
+    let mut mock = MockFoo::new();
+    mock.expect_foo()
+        .returning(|| <MockFoo as A>::MAX);
+    assert_eq!(16, mock.foo());
+}
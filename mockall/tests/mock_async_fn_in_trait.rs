@@ -0,0 +1,37 @@
+// vim: tw=80
+//! mock!-style mocking of traits with `async fn` and `-> impl Future` methods
+
+#![allow(async_fn_in_trait)]
+
+use futures::executor::block_on;
+use mockall::*;
+use std::future::Future;
+
+pub trait Foo {
+    async fn foo(&self, x: u32) -> u32;
+    fn bar(&self) -> impl Future<Output=i32> + Send;
+}
+
+mock! {
+    pub Bar {}
+    trait Foo {
+        async fn foo(&self, x: u32) -> u32;
+        fn bar(&self) -> impl Future<Output=i32> + Send;
+    }
+}
+
+#[test]
+fn async_fn() {
+    let mut mock = MockBar::new();
+    mock.expect_foo()
+        .returning(|x| x * 2);
+    assert_eq!(8, block_on(mock.foo(4)));
+}
+
+#[test]
+fn impl_future() {
+    let mut mock = MockBar::new();
+    mock.expect_bar()
+        .returning(|| Box::pin(async { -1 }));
+    assert_eq!(-1, block_on(mock.bar()));
+}
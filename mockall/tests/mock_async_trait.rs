@@ -0,0 +1,32 @@
+// vim: tw=80
+//! mock!ing a trait decorated with #[async_trait]
+
+use async_trait::async_trait;
+use futures::executor::block_on;
+use mockall::*;
+
+#[async_trait]
+pub trait Foo {
+    async fn foo(&self, x: u32) -> u32;
+    async fn bar(&self) -> Vec<u8>;
+}
+
+mock! {
+    pub Bar {}
+    #[async_trait]
+    trait Foo {
+        async fn foo(&self, x: u32) -> u32;
+        async fn bar(&self) -> Vec<u8>;
+    }
+}
+
+#[test]
+fn returning() {
+    let mut mock = MockBar::new();
+    mock.expect_foo()
+        .returning(|x| x * 2);
+    mock.expect_bar()
+        .returning(|| vec![1, 2, 3]);
+    assert_eq!(8, block_on(mock.foo(4)));
+    assert_eq!(vec![1, 2, 3], block_on(mock.bar()));
+}
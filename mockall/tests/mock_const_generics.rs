@@ -0,0 +1,48 @@
+// vim: tw=80
+//! Const generic parameters with mock!
+
+use mockall::*;
+
+trait Codec<const N: usize> {
+    fn encode(&self, x: u8) -> [u8; N];
+}
+
+mock! {
+    Buffer<const N: usize> {
+        fn flag<const B: bool>(&self) -> bool;
+        fn make<const M: usize>(x: u8) -> [u8; M];
+    }
+    trait Codec<const N: usize> {
+        fn encode(&self, x: u8) -> [u8; N];
+    }
+}
+
+#[test]
+fn bool_param() {
+    let mut mock = MockBuffer::<1>::new();
+    mock.expect_flag::<true>()
+        .return_const(false);
+    mock.expect_flag::<false>()
+        .return_const(true);
+    assert!(!mock.flag::<true>());
+    assert!(mock.flag::<false>());
+}
+
+#[test]
+fn static_method() {
+    let ctx = MockBuffer::<1>::make_context();
+    ctx.expect::<2>()
+        .returning(|x| [x; 2]);
+    ctx.expect::<3>()
+        .returning(|x| [x + 1; 3]);
+    assert_eq!([4, 4], MockBuffer::<1>::make::<2>(4));
+    assert_eq!([5, 5, 5], MockBuffer::<1>::make::<3>(4));
+}
+
+#[test]
+fn trait_method() {
+    let mut mock = MockBuffer::<3>::new();
+    mock.expect_encode()
+        .returning(|x| [x; 3]);
+    assert_eq!([9, 9, 9], mock.encode(9));
+}
@@ -0,0 +1,48 @@
+// vim: tw=80
+//! mock! can create deep stubs, too
+
+use mockall::*;
+
+pub trait Session {
+    fn user(&self) -> u32;
+}
+
+pub trait Client {
+    fn session(&self) -> Box<dyn Session>;
+}
+
+mock! {
+    pub Session {}
+    trait Session {
+        fn user(&self) -> u32;
+    }
+}
+
+mock! {
+    pub Client {
+        #[deep]
+        fn current(&self) -> &dyn Session;
+    }
+    trait Client {
+        #[deep]
+        fn session(&self) -> Box<dyn Session>;
+    }
+}
+
+#[test]
+fn inherent_method() {
+    let mut mock = MockClient::new();
+    mock.current_mock()
+        .expect_user()
+        .return_const(3u32);
+    assert_eq!(3, mock.current().user());
+}
+
+#[test]
+fn trait_method() {
+    let mut mock = MockClient::new();
+    mock.session_mock()
+        .expect_user()
+        .return_const(4u32);
+    assert_eq!(4, mock.session().user());
+}
@@ -0,0 +1,38 @@
+// vim: tw=80
+//! mock!-style mocking with generic associated types
+
+use mockall::*;
+
+trait Storage {
+    type Iter<'a>: Iterator<Item=&'a u32> where Self: 'a;
+    type Wrapped<T>;
+    fn iter<'a>(&'a self) -> Self::Iter<'a>;
+    fn wrap(&self, x: u32) -> Self::Wrapped<u32>;
+}
+
+mock! {
+    Foo {}
+    trait Storage {
+        type Iter<'a> = std::slice::Iter<'a, u32> where Self: 'a;
+        type Wrapped<T> = Vec<T>;
+        fn iter<'a>(&'a self) -> Self::Iter<'a>;
+        fn wrap(&self, x: u32) -> Self::Wrapped<u32>;
+    }
+}
+
+#[test]
+fn lifetime_param() {
+    static VALUES: [u32; 3] = [1, 2, 3];
+    let mut mock = MockFoo::new();
+    mock.expect_iter()
+        .returning(|| VALUES.iter());
+    assert_eq!(6, mock.iter().sum::<u32>());
+}
+
+#[test]
+fn type_param() {
+    let mut mock = MockFoo::new();
+    mock.expect_wrap()
+        .returning(|x| vec![x; 2]);
+    assert_eq!(vec![7, 7], mock.wrap(7));
+}
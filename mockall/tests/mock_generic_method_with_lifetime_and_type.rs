@@ -0,0 +1,30 @@
+// vim: tw=80
+//! A method with both generic lifetime parameters and generic type parameters
+
+use mockall::*;
+
+pub trait Decode<'a>: Sized {
+    fn decode(buf: &'a [u8]) -> Self;
+}
+
+impl<'a> Decode<'a> for u32 {
+    fn decode(buf: &'a [u8]) -> Self {
+        u32::from(buf[0])
+    }
+}
+
+mock! {
+    Foo<Q: 'static> {
+        fn parse<'a, T: Decode<'a> + 'static>(&self, buf: &'a [u8], q: Q)
+            -> T;
+    }
+}
+
+#[test]
+fn returning() {
+    let mut mock = MockFoo::<u8>::new();
+    mock.expect_parse::<u32>()
+        .withf(|buf, q| buf.len() == 1 && *q == 1)
+        .returning(|buf, q| u32::from(buf[0] + q));
+    assert_eq!(5u32, mock.parse::<u32>(&[4u8][..], 1));
+}
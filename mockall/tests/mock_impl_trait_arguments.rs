@@ -0,0 +1,29 @@
+// vim: tw=80
+//! Arguments of type `impl Trait`
+
+use mockall::*;
+
+mock! {
+    pub Foo {
+        fn foo(&self, x: impl AsRef<str>, y: impl Into<u64>) -> u64;
+        fn bar(&mut self, f: impl FnMut(u32) -> u32 + 'static) -> u32;
+    }
+}
+
+#[test]
+fn returning() {
+    let mut mock = MockFoo::new();
+    mock.expect_foo()
+        .with(predicate::eq("abc"), predicate::eq(5))
+        .returning(|x, y| x.len() as u64 + y);
+    assert_eq!(8, mock.foo("abc", 5u8));
+}
+
+#[test]
+fn closure() {
+    let mut mock = MockFoo::new();
+    mock.expect_bar()
+        .returning(|mut f| f(1) + f(2));
+    let mut calls = 0;
+    assert_eq!(6, mock.bar(move |x| { calls += 1; x + calls }));
+}
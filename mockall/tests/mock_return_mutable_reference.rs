@@ -33,6 +33,18 @@ fn return_var() {
     assert_eq!(6, *mock.foo(0));
 }
 
+#[test]
+fn returning_from() {
+    let mut mock = MockFoo::new();
+    mock.expect_foo()
+        .returning_from(vec![0u32; 3], |v, i| &mut v[i as usize]);
+    *mock.foo(1) = 7;
+    *mock.foo(2) += 1;
+    assert_eq!(0, *mock.foo(0));
+    assert_eq!(7, *mock.foo(1));
+    assert_eq!(1, *mock.foo(2));
+}
+
 #[test]
 fn returning() {
     let mut mock = MockFoo::new();
@@ -2,11 +2,14 @@
 //! A struct with a method that returns an immutable reference
 
 use mockall::*;
+use std::collections::HashMap;
 
 mock! {
     Foo {
         fn foo(&self) -> &u32;
         fn bar(&self) -> &u32;
+        fn baz(&self, key: u32) -> &u32;
+        fn name(&self, key: &str) -> &String;
     }
 }
 
@@ -51,6 +54,31 @@ fn return_default() {
     assert_eq!(u32::default(), *r);
 }
 
+mod returning_from {
+    use super::*;
+
+    #[test]
+    fn ok() {
+        let mut mock = MockFoo::new();
+        mock.expect_baz()
+            .returning_from(vec![10u32, 20, 30], |v, key| &v[key as usize]);
+        assert_eq!(20, *mock.baz(1));
+        assert_eq!(30, *mock.baz(2));
+    }
+
+    #[test]
+    fn reference_argument() {
+        let mut names = HashMap::new();
+        names.insert("a", "Alice".to_string());
+        names.insert("b", "Bob".to_string());
+        let mut mock = MockFoo::new();
+        mock.expect_name()
+            .returning_from(names, |m, key| &m[key]);
+        assert_eq!("Bob", mock.name("b"));
+        assert_eq!("Alice", mock.name("a"));
+    }
+}
+
 mod sequence {
     use super::*;
 
@@ -0,0 +1,40 @@
+// vim: tw=80
+//! Methods with typed self receivers, like `self: Box<Self>`
+
+use mockall::*;
+use std::pin::Pin;
+use std::sync::Arc;
+
+pub trait Builder {
+    fn finish(self: Box<Self>) -> String;
+}
+
+mock! {
+    pub Foo {
+        fn shared(self: Arc<Self>, x: u32) -> u32;
+        fn pinned(self: Pin<&mut Self>, x: u32) -> u32;
+    }
+    trait Builder {
+        fn finish(self: Box<Self>) -> String;
+    }
+}
+
+#[test]
+fn inherent() {
+    let mut mock = MockFoo::new();
+    mock.expect_shared()
+        .returning(|x| x + 1);
+    mock.expect_pinned()
+        .returning(|x| x + 2);
+    assert_eq!(6, Pin::new(&mut mock).pinned(4));
+    assert_eq!(5, Arc::new(mock).shared(4));
+}
+
+#[test]
+fn trait_object() {
+    let mut mock = MockFoo::new();
+    mock.expect_finish()
+        .return_const("done".to_owned());
+    let b: Box<dyn Builder> = Box::new(mock);
+    assert_eq!("done", b.finish());
+}
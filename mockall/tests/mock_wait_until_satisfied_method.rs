@@ -0,0 +1,33 @@
+// vim: tw=80
+//! A mocked struct may have its own method named `wait_until_satisfied`
+
+use mockall::*;
+use std::time::Duration;
+
+mock! {
+    Foo {
+        fn wait_until_satisfied(&self, x: u32) -> u32;
+    }
+}
+
+#[test]
+fn inherent_method() {
+    let mut mock = MockFoo::new();
+    mock.expect_wait_until_satisfied()
+        .returning(|x| x + 1);
+    assert_eq!(5, mock.wait_until_satisfied(4));
+}
+
+#[test]
+fn trait_method() {
+    let mut mock = MockFoo::new();
+    mock.expect_wait_until_satisfied()
+        .times(1)
+        .returning(|x| x + 1);
+    assert_eq!(Err(Timeout),
+        WaitUntilSatisfied::wait_until_satisfied(&mock,
+                                                 Duration::from_millis(10)));
+    mock.wait_until_satisfied(0);
+    WaitUntilSatisfied::wait_until_satisfied(&mock, Duration::from_secs(60))
+        .unwrap();
+}
@@ -5,21 +5,65 @@ use std::{
     collections::HashMap,
     env
 };
-use syn::parse::{Parse, ParseStream};
+use syn::{
+    parse::{Parse, ParseStream},
+    visit_mut::{self, VisitMut}
+};
+
+mod kw {
+    syn::custom_keyword!(export);
+    syn::custom_keyword!(wrap);
+}
+
+/// How mock foreign functions should be exported to native code
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Export {
+    /// Export each mock function under the original function's name
+    NoMangle,
+    /// Export each mock function as `__wrap_<name>`, for the linker's
+    /// `--wrap=<name>` option
+    Wrap
+}
+
+impl Parse for Export {
+    fn parse(input: ParseStream) -> parse::Result<Self> {
+        input.parse::<kw::export>()?;
+        let export = if input.peek(token::Paren) {
+            let content;
+            parenthesized!(content in input);
+            content.parse::<kw::wrap>()?;
+            Export::Wrap
+        } else {
+            Export::NoMangle
+        };
+        input.parse::<Token![;]>()?;
+        Ok(export)
+    }
+}
 
 /// A single automock attribute
 // This enum is very short-lived, so it's fine not to box it.
 #[allow(clippy::large_enum_variant)]
 enum Attr {
+    Const(TraitItemConst),
+    Export(Export, Span),
     Mod(ItemMod),
+    Static(ItemStatic),
     Type(TraitItemType),
 }
 
 impl Parse for Attr {
     fn parse(input: ParseStream) -> parse::Result<Self> {
         let lookahead = input.lookahead1();
-        if lookahead.peek(Token![mod]) {
+        if lookahead.peek(Token![const]) {
+            input.parse().map(Attr::Const)
+        } else if lookahead.peek(kw::export) {
+            let span = input.span();
+            input.parse().map(|e| Attr::Export(e, span))
+        } else if lookahead.peek(Token![mod]) {
             input.parse().map(Attr::Mod)
+        } else if lookahead.peek(Token![static]) {
+            input.parse().map(Attr::Static)
         } else if lookahead.peek(Token![type]) {
             input.parse().map(Attr::Type)
         } else {
@@ -32,15 +76,102 @@ impl Parse for Attr {
 #[derive(Debug, Default)]
 struct Attrs {
     attrs: HashMap<Ident, Type>,
-    modname: Option<Ident>
+    /// Generic parameters of generic associated types
+    type_generics: HashMap<Ident, Generics>,
+    /// Values of associated constants, or of a mocked module's constants
+    consts: HashMap<Ident, Expr>,
+    /// Values of a mocked module's statics
+    statics: HashMap<Ident, Expr>,
+    /// Associated types that should become generic parameters of the mock
+    /// struct, with any extra bounds given in the attribute
+    generic_types: Vec<TraitItemType>,
+    modname: Option<Ident>,
+    /// How to export mock foreign functions to native code, if at all, and
+    /// where the attribute said so
+    export: Option<(Export, Span)>
 }
 
 impl Attrs {
+    /// Report any `const` attributes that don't name one of the mocked item's
+    /// constants.
+    fn check_consts(&self, consts: &[&Ident]) {
+        for ident in self.consts.keys() {
+            if !consts.contains(&ident) {
+                compile_error(ident.span(),
+                    "automock const attribute names no constant of this item");
+            }
+        }
+    }
+
+    /// Report any `static` attributes that don't name one of the mocked item's
+    /// statics.
+    fn check_statics(&self, statics: &[&Ident]) {
+        for ident in self.statics.keys() {
+            if !statics.contains(&ident) {
+                compile_error(ident.span(),
+                    "automock static attribute names no static of this item");
+            }
+        }
+    }
+
+    /// Generic parameters that the mock struct needs in addition to the
+    /// trait's own, one for each associated type that was left generic.
+    fn generics(&self, item: &ItemTrait) -> Generics {
+        let mut params = Punctuated::new();
+        for gt in self.generic_types.iter() {
+            let tity = item.items.iter().find_map(|ti| match ti {
+                TraitItem::Type(tity) if tity.ident == gt.ident => Some(tity),
+                _ => None
+            });
+            let mut bounds = Punctuated::<TypeParamBound, Token![+]>::new();
+            if let Some(tity) = tity {
+                bounds.extend(tity.bounds.iter().cloned());
+            } else {
+                compile_error(gt.span(),
+                    "automock type attribute does not name an associated type");
+            }
+            bounds.extend(gt.bounds.iter().cloned());
+            for bound in bounds.iter_mut() {
+                self.substitute_type_param_bound(bound);
+            }
+            let static_bound = Lifetime::new("'static", gt.ident.span());
+            bounds.push(TypeParamBound::Lifetime(static_bound));
+            params.push(GenericParam::Type(TypeParam {
+                attrs: Vec::new(),
+                ident: gt.ident.clone(),
+                colon_token: Some(Token![:](gt.ident.span())),
+                bounds,
+                eq_token: None,
+                default: None
+            }));
+        }
+        if params.is_empty() {
+            Generics::default()
+        } else {
+            Generics {
+                lt_token: Some(Token![<](item.ident.span())),
+                params,
+                gt_token: Some(Token![>](item.ident.span())),
+                where_clause: None
+            }
+        }
+    }
+
+    /// Look up the substitution for an associated type, instantiating any of
+    /// its generic parameters with the arguments from `seg`.
+    fn get_assoc_type(&self, seg: &PathSegment) -> Option<Type> {
+        let ty = self.attrs.get(&seg.ident)?;
+        if let Some(g) = self.type_generics.get(&seg.ident) {
+            Some(instantiate_gat(ty, g, &seg.arguments))
+        } else {
+            Some(ty.clone())
+        }
+    }
+
     fn get_path(&self, path: &Path) -> Option<Type> {
         if path.leading_colon.is_none() & (path.segments.len() == 2) {
             if path.segments.first().unwrap().ident == "Self" {
-                let ident = &path.segments.last().unwrap().ident;
-                self.attrs.get(ident).cloned()
+                self.get_assoc_type(path.segments.last().unwrap())
             } else {
                 None
             }
@@ -53,8 +184,13 @@ impl Attrs {
         match &mut seg.arguments {
             PathArguments::None => /* nothing to do */(),
             PathArguments::Parenthesized(p) => {
-                compile_error(p.span(),
-                    "Mockall does not support mocking Fn objects");
+                // The arguments of a closure type, like `impl Fn(u32)`
+                for ty in p.inputs.iter_mut() {
+                    self.substitute_type(ty)
+                }
+                if let ReturnType::Type(_, ty) = &mut p.output {
+                    self.substitute_type(ty.as_mut())
+                }
             },
             PathArguments::AngleBracketed(abga) => {
                 for arg in abga.args.iter_mut() {
@@ -121,12 +257,11 @@ impl Attrs {
                             "QSelf is a work in progress");
                     }
                     let last_seg = path.path.segments.pop().unwrap();
-                    let to_sub = &last_seg.value().ident;
                     let _ident = path.path.segments.pop().unwrap().value();
                     // TODO: check that the ident is the name of this type
-                    let new_type = self.attrs.get(to_sub)
+                    let new_type = self.get_assoc_type(last_seg.value())
                         .expect("Unknown type substitution for QSelf");
-                    *ty = new_type.clone();
+                    *ty = new_type;
                 } else if let Some(newty) = self.get_path(&path.path) {
                     *ty = newty;
                 } else {
@@ -197,6 +332,15 @@ impl Attrs {
                             "Default value not given for associated type");
                     }
                 },
+                TraitItem::Const(tic) => {
+                    self.substitute_type(&mut tic.ty);
+                    // A constant with no value is reported by
+                    // mock_trait_methods, for mock! as well as #[automock]
+                    if let Some(expr) = self.consts.get(&tic.ident) {
+                        let span = tic.span();
+                        tic.default = Some((Token![=](span), expr.clone()));
+                    }
+                },
                 TraitItem::Method(method) => {
                     let sig = &mut method.sig;
                     for fn_arg in sig.inputs.iter_mut() {
@@ -220,10 +364,27 @@ impl Attrs {
 impl Parse for Attrs {
     fn parse(input: ParseStream) -> parse::Result<Self> {
         let mut attrs = HashMap::new();
+        let mut type_generics = HashMap::new();
+        let mut consts = HashMap::new();
+        let mut statics = HashMap::new();
+        let mut generic_types = Vec::new();
         let mut modname = None;
+        let mut export = None;
         while !input.is_empty() {
             let attr: Attr = input.parse()?;
             match attr {
+                Attr::Const(trait_item_const) => {
+                    let ident = trait_item_const.ident.clone();
+                    if let Some((_, expr)) = trait_item_const.default {
+                        consts.insert(ident, expr);
+                    } else {
+                        compile_error(trait_item_const.span(),
+                          "automock const attributes must have a value");
+                    }
+                },
+                Attr::Export(e, span) => {
+                    export = Some((e, span));
+                },
                 Attr::Mod(item_mod) => {
                     if let Some((br, _)) = item_mod.content {
                         compile_error(br.span,
@@ -231,19 +392,157 @@ impl Parse for Attrs {
                     }
                     modname = Some(item_mod.ident.clone());
                 },
+                Attr::Static(item_static) => {
+                    statics.insert(item_static.ident, *item_static.expr);
+                },
                 Attr::Type(trait_item_type) => {
                     let ident = trait_item_type.ident.clone();
+                    if !trait_item_type.generics.params.is_empty() {
+                        type_generics.insert(ident.clone(),
+                            trait_item_type.generics.clone());
+                    }
                     if let Some((_, ty)) = trait_item_type.default {
                         attrs.insert(ident, ty.clone());
                     } else {
-                        compile_error(trait_item_type.span(),
-                          "automock type attributes must have a default value");
+                        // With no default value, the associated type becomes
+                        // a generic parameter of the mock struct.
+                        let ty = Type::Path(TypePath {
+                            qself: None,
+                            path: Path::from(ident.clone())
+                        });
+                        attrs.insert(ident, ty);
+                        generic_types.push(trait_item_type);
                     }
                 }
             }
         }
-        Ok(Attrs{attrs, modname})
+        Ok(Attrs{attrs, type_generics, consts, statics, generic_types,
+                 modname, export})
+    }
+}
+
+/// Replaces a generic associated type's parameters with their arguments
+#[derive(Default)]
+struct GatInstantiator {
+    lifetimes: HashMap<Lifetime, Lifetime>,
+    types: HashMap<Ident, Type>,
+    consts: HashMap<Ident, Expr>
+}
+
+impl GatInstantiator {
+    /// If `path` is just one of the parameters, return its identifier
+    fn param<'a>(path: &'a Path, qself: &Option<QSelf>) -> Option<&'a Ident> {
+        if qself.is_none() {
+            path.get_ident()
+        } else {
+            None
+        }
+    }
+}
+
+impl VisitMut for GatInstantiator {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        if let Expr::Path(ep) = expr {
+            if let Some(arg) = Self::param(&ep.path, &ep.qself)
+                .and_then(|i| self.consts.get(i))
+            {
+                *expr = arg.clone();
+                return;
+            }
+        }
+        visit_mut::visit_expr_mut(self, expr);
+    }
+
+    fn visit_generic_argument_mut(&mut self, ga: &mut GenericArgument) {
+        // syn can't tell a const argument from a type
+        if let GenericArgument::Type(Type::Path(tp)) = ga {
+            if let Some(arg) = Self::param(&tp.path, &tp.qself)
+                .and_then(|i| self.consts.get(i))
+            {
+                *ga = GenericArgument::Const(arg.clone());
+                return;
+            }
+        }
+        visit_mut::visit_generic_argument_mut(self, ga);
+    }
+
+    fn visit_lifetime_mut(&mut self, lt: &mut Lifetime) {
+        if let Some(arg) = self.lifetimes.get(lt) {
+            *lt = arg.clone();
+        }
+    }
+
+    fn visit_type_mut(&mut self, ty: &mut Type) {
+        if let Type::Path(tp) = ty {
+            if let Some(arg) = Self::param(&tp.path, &tp.qself)
+                .and_then(|i| self.types.get(i))
+            {
+                *ty = arg.clone();
+                return;
+            }
+        }
+        visit_mut::visit_type_mut(self, ty);
+    }
+}
+
+/// Replace every use of a generic associated type's parameters in `ty` with
+/// the corresponding arguments from `path_args`.
+/// e.g. instantiate_gat(Iter<'a, T>, <'a, T>, <'b, u32>) -> Iter<'b, u32>
+fn instantiate_gat(ty: &Type, g: &Generics, path_args: &PathArguments)
+    -> Type
+{
+    let empty = Punctuated::new();
+    let args = match path_args {
+        PathArguments::AngleBracketed(abga) => &abga.args,
+        _ => &empty
+    };
+    let mut lifetimes = args.iter().filter_map(|ga| match ga {
+        GenericArgument::Lifetime(lt) => Some(lt),
+        _ => None
+    });
+    let mut types = args.iter().filter(|ga|
+        !matches!(ga, GenericArgument::Lifetime(_))
+    );
+    let mut gi = GatInstantiator::default();
+    for param in g.params.iter() {
+        match param {
+            GenericParam::Lifetime(ld) => {
+                // Omitted lifetime arguments are elided
+                let arg = lifetimes.next()
+                    .cloned()
+                    .unwrap_or_else(|| Lifetime::new("'_", ld.span()));
+                gi.lifetimes.insert(ld.lifetime.clone(), arg);
+            },
+            GenericParam::Type(tp) => {
+                if let Some(GenericArgument::Type(arg)) = types.next() {
+                    gi.types.insert(tp.ident.clone(), arg.clone());
+                } else {
+                    compile_error(path_args.span(),
+                        "Missing type argument for generic associated type");
+                }
+            },
+            GenericParam::Const(cp) => {
+                let arg = match types.next() {
+                    Some(GenericArgument::Const(e)) => e.clone(),
+                    Some(GenericArgument::Type(Type::Path(tp))) =>
+                        Expr::Path(ExprPath {
+                            attrs: Vec::new(),
+                            qself: tp.qself.clone(),
+                            path: tp.path.clone()
+                        }),
+                    _ => {
+                        compile_error(path_args.span(),
+                        "Missing const argument for generic associated type");
+                        continue;
+                    }
+                };
+                gi.consts.insert(cp.ident.clone(), arg);
+            }
+        }
     }
+    let mut out = ty.clone();
+    gi.visit_type_mut(&mut out);
+    out
 }
 
 /// Filter a generics list, keeping only the elements specified by path_args
@@ -293,7 +592,19 @@ fn filter_generics(g: &Generics, path_args: &PathArguments)
                             params.push(param.clone())
                         }
                     },
-                    GenericParam::Const(_) => ()/* Ignore */,
+                    GenericParam::Const(cp) => {
+                        // syn can't tell a const argument from a type
+                        let matches = |ga: &GenericArgument| match ga {
+                            GenericArgument::Type(Type::Path(type_path)) =>
+                                type_path.path.is_ident(&cp.ident),
+                            GenericArgument::Const(Expr::Path(ep)) =>
+                                ep.path.is_ident(&cp.ident),
+                            _ => false
+                        };
+                        if args.iter().any(matches) {
+                            params.push(param.clone())
+                        }
+                    },
                 }
             }
         }
@@ -323,12 +634,28 @@ fn find_ident_from_path(path: &Path) -> (Ident, PathArguments) {
 fn mock_foreign(attrs: Attrs, foreign_mod: ItemForeignMod) -> TokenStream {
     let mut body = TokenStream::new();
     let mut cp_body = TokenStream::new();
-    let modname = attrs.modname.expect(concat!(
+    let modname = attrs.modname.clone().expect(concat!(
         "module name is required when mocking foreign functions,",
         " like `#[automock(mod mock_ffi)]`"
     ));
+    let export = attrs.export.map(|(e, _)| e);
+    // Mock functions called from native code use the foreign block's ABI, but
+    // let a failed expectation's panic unwind through them.
+    let abi = match foreign_mod.abi.name {
+        Some(ref name) if name.value() == "Rust" => name.value(),
+        Some(ref name) if name.value().ends_with("-unwind") => name.value(),
+        Some(ref name) => format!("{}-unwind", name.value()),
+        None => "C-unwind".to_owned()
+    };
 
-    for item in foreign_mod.items {
+    let statics = foreign_mod.items.iter()
+        .filter_map(|item| match item {
+            ForeignItem::Static(s) => Some(&s.ident),
+            _ => None
+        }).collect::<Vec<_>>();
+    attrs.check_consts(&[]);
+    attrs.check_statics(&statics);
+    for item in foreign_mod.items.iter().cloned() {
         match item {
             ForeignItem::Fn(f) => {
                 let mod_ident = format_ident!("__{}", &f.sig.ident);
@@ -338,11 +665,12 @@ fn mock_foreign(attrs: Attrs, foreign_mod: ItemForeignMod) -> TokenStream {
                         .checkpoint()
                         .collect::<Vec<_>>();
                 ).to_tokens(&mut cp_body);
-                mock_foreign_function(&modname, f).to_tokens(&mut body);
+                mock_foreign_function(&modname, f, &abi, export)
+                    .to_tokens(&mut body);
             },
             ForeignItem::Static(s) => {
-                // Copy verbatim so a mock method can mutate it
-                s.to_tokens(&mut body)
+                let init = attrs.statics.get(&s.ident);
+                mock_foreign_static(&s, init, export).to_tokens(&mut body)
             },
             ForeignItem::Type(ty) => {
                 // Copy verbatim
@@ -358,22 +686,101 @@ fn mock_foreign(attrs: Attrs, foreign_mod: ItemForeignMod) -> TokenStream {
         pub fn checkpoint() { #cp_body }).to_tokens(&mut body);
     quote!(
         #[allow(missing_docs)]
-        pub mod #modname { #body }
+        pub mod #modname {
+            // Import types from the calling environment
+            #[allow(unused_imports)]
+            use super::*;
+            #body
+        }
+    )
+}
+
+/// Mock a foreign static with a `static mut` that tests can set.  It's
+/// initialized with `init`, if given, or else zero-initialized like an
+/// uninitialized C global.
+fn mock_foreign_static(s: &ForeignItemStatic, init: Option<&Expr>,
+    export: Option<Export>) -> TokenStream
+{
+    let attrs = &s.attrs;
+    let ident = &s.ident;
+    let ty = &s.ty;
+    let vis = expectation_visibility(&s.vis, 1);
+    // Statics can't be wrapped, but they can be exported
+    let export_attr = if export == Some(Export::NoMangle) {
+        let name = ident.to_string();
+        quote!(#[export_name = #name])
+    } else {
+        TokenStream::new()
+    };
+    let init = match init {
+        Some(expr) => quote!(#expr),
+        None => quote!(unsafe { ::std::mem::zeroed() })
+    };
+    let mut out = quote!(
+        #(#attrs)*
+        #export_attr
+        #vis static mut #ident: #ty = #init;
+    );
+    static_context(&vis, ident, ty).to_tokens(&mut out);
+    out
+}
+
+/// Generate a function that returns a `StaticContext` for overriding the mock
+/// `static mut` named `ident` in a single test.
+fn static_context(vis: &Visibility, ident: &Ident, ty: &Type) -> TokenStream {
+    let context_ident = format_ident!("{}_context", ident);
+    let docstr = format!(
+        "Return a Context object used to override `{}` until it drops", ident);
+    // A static's references are implicitly 'static, but not the function's
+    let mut ty = ty.clone();
+    name_lifetimes(&mut ty, true,
+        &mut || Lifetime::new("'static", Span::call_site()));
+    quote!(
+        #[doc = #docstr]
+        #[allow(non_snake_case)]
+        #vis fn #context_ident() -> ::mockall::StaticContext<#ty> {
+            static MUTEX: ::std::sync::Mutex<()> =
+                ::std::sync::Mutex::new(());
+            unsafe {
+                ::mockall::StaticContext::new(
+                    ::std::ptr::addr_of_mut!(#ident), &MUTEX)
+            }
+        }
     )
 }
 
 /// Mock a foreign function the same way we mock static trait methods: with a
 /// global Expectations object
-fn mock_foreign_function(modname: &Ident, f: ForeignItemFn) -> TokenStream {
+///
+/// # Arguments
+/// * `abi` - ABI for the mock function, if it's variadic or exported
+fn mock_foreign_function(modname: &Ident, f: ForeignItemFn, abi: &str,
+    export: Option<Export>) -> TokenStream
+{
     // Foreign functions are always unsafe.  Mock foreign functions should be
     // unsafe too, to prevent "warning: unused unsafe" messages.
     let mut sig = f.sig.clone();
     sig.unsafety = Some(Token![unsafe](f.sig.span()));
-    mock_function(modname, &f.vis, &sig)
+    let export_name = export.map(|e| match e {
+        Export::NoMangle => sig.ident.to_string(),
+        Export::Wrap => format!("__wrap_{}", sig.ident)
+    });
+    // Only extern functions can be variadic or called from native code
+    let abi = if sig.variadic.is_some() || export_name.is_some() {
+        Some(abi)
+    } else {
+        None
+    };
+    mock_function(modname, &f.vis, &sig, abi, export_name.as_deref())
 }
 
-fn mock_function(modname: &Ident, vis: &Visibility, sig: &Signature)
-    -> TokenStream
+/// Mock a free function.
+///
+/// # Arguments
+/// * `abi` - ABI to declare the mock function with, if any
+/// * `export_name` - Symbol name to export the mock function under, if any
+fn mock_function(modname: &Ident, vis: &Visibility, sig: &Signature,
+    abi: Option<&str>, export_name: Option<&str>) -> TokenStream
 {
     let asyncness = &sig.asyncness;
     let constness = &sig.constness;
@@ -388,29 +795,28 @@ fn mock_function(modname: &Ident, vis: &Visibility, sig: &Signature)
             quote!(#decl_output)
         }
     };
-    let mut args = Vec::new();
-
-    if sig.variadic.is_some() {
+    if sig.variadic.is_some() && !cfg!(feature = "nightly_derive") {
         compile_error(sig.variadic.span(),
-            "Mockall does not support variadic extern functions");
+            "Mocking variadic functions requires the \"nightly\" feature");
         return TokenStream::new();
     }
 
     let mod_ident = format_ident!("__{}", &ident);
     let mut mock_sig = sig.clone();
     mock_sig.ident = mod_ident.clone();
-    let meth_types = method_types(&mock_sig, None);
+    if mock_sig.variadic.take().is_some() {
+        // The Expectation gets the variable arguments as one more fixed
+        // argument.
+        mock_sig.inputs.push(parse2(
+            quote!(__mockall_va: &mut ::mockall::VaArgs<'_>)
+        ).unwrap());
+    }
+    let meth_types = method_types(&mock_sig, None, false);
     let inputs = &meth_types.inputs;
-
-    for p in inputs.iter() {
-        match p {
-            FnArg::Typed(arg) => {
-                args.push(arg.pat.clone());
-            },
-            _ => compile_error(p.span(),
-                "Should be unreachable for normal Rust code")
-        }
+    if let Some(FnArg::Receiver(r)) = inputs.first() {
+        compile_error(r.span(), "Should be unreachable for normal Rust code");
     }
+    let call_exprs = &meth_types.call_exprs;
 
     let meth_vis = expectation_visibility(&vis, 1);
     let context_ident = format_ident!("{}_context", &ident);
@@ -423,8 +829,9 @@ fn mock_function(modname: &Ident, vis: &Visibility, sig: &Signature)
     g.params.push(GenericParam::Lifetime(ltd));
 
     let mut out = TokenStream::new();
-    Expectation::new(&TokenStream::new(), &inputs, &expect_obj, None, generics,
-        &ident, &mod_ident, None, &sig.output, &expect_vis, 1)
+    Expectation::new(&TokenStream::new(), &meth_types.expectation_inputs,
+        &expect_obj, None, &meth_types.expectation_generics, &ident,
+        &mod_ident, None, &sig.output, false, &expect_vis, 1)
         .to_tokens(&mut out);
     let no_match_msg = format!("{}::{}: No matching expectation found",
         modname, ident);
@@ -436,23 +843,42 @@ fn mock_function(modname: &Ident, vis: &Visibility, sig: &Signature)
         let inner_ds = format!("Return a Context object used to hold the expectations for `{}`", ident);
         quote!( #[doc = #inner_ds])
     };
+    let abi = abi.map(|abi| quote!(extern #abi));
+    let export_attr = export_name.map(|name| quote!(#[export_name = #name]));
+    // Record any violation by another thread, so the thread that holds the
+    // Context can report it again.
+    let call = quote!(
+        let __mockall_failures = #mod_ident::failures();
+        __mockall_failures.catch(move || {
+            #mod_ident::call(#call_exprs).unwrap_or_else(||
+                ::mockall::violation(#no_match_msg.to_owned()))
+        })
+    );
+    let mock_fn = if sig.variadic.is_some() {
+        let fixed_inputs = inputs.iter().take(inputs.len() - 1);
+        quote!(
+            #export_attr
+            #meth_vis #constness #unsafety #asyncness #abi
+            #fn_token #ident #generics (#(#fixed_inputs, )*
+                __mockall_va_list: ...) #output
+            {
+                let __mockall_va = &mut ::mockall::VaArgs::new(
+                    __mockall_va_list);
+                #call
+            }
+        )
+    } else {
+        quote!(
+            #export_attr
+            #meth_vis #constness #unsafety #asyncness #abi
+            #fn_token #ident #generics (#inputs) #output {
+                #call
+            }
+        )
+    };
     quote!(
         #fn_docstr
-        #meth_vis #constness #unsafety #asyncness
-        #fn_token #ident #generics (#inputs) #output {
-            {
-                let __mockall_guard = #mod_ident::EXPECTATIONS
-                    .lock().unwrap();
-                /*
-                 * TODO: catch panics, then gracefully release the mutex so it
-                 * won't be poisoned.  This requires bounding any generic
-                 * parameters with UnwindSafe
-                 */
-                /* std::panic::catch_unwind(|| */
-                __mockall_guard.call(#(#args),*)
-                /*)*/
-            }.expect(#no_match_msg)
-        }
+        #mock_fn
         #context_docstr
         #meth_vis fn #context_ident() -> #mod_ident::Context
         {
@@ -475,14 +901,14 @@ fn mock_impl(item_impl: ItemImpl) -> TokenStream {
             return TokenStream::new();
         }
     };
+    let mut consts = Vec::new();
     let mut methods = Vec::new();
     let mut titys = Vec::new();
     let mut attrs = Attrs::default();
     for item in item_impl.items.iter() {
         match item {
-            ImplItem::Const(_) => {
-                // const items can easily be added by the user in a separate
-                // impl block
+            ImplItem::Const(ic) => {
+                consts.push(ic.clone());
             },
             ImplItem::Method(meth) => {
                 methods.push(meth.clone());
@@ -499,6 +925,10 @@ fn mock_impl(item_impl: ItemImpl) -> TokenStream {
                     semi_token: ty.semi_token
                 };
                 attrs.attrs.insert(ty.ident.clone(), ty.ty.clone());
+                if !ty.generics.params.is_empty() {
+                    attrs.type_generics.insert(ty.ident.clone(),
+                        ty.generics.clone());
+                }
                 titys.push(tity);
             },
             _ => {
@@ -510,8 +940,21 @@ fn mock_impl(item_impl: ItemImpl) -> TokenStream {
     // automock makes everything public
     let pub_token = Token![pub](Span::call_site());
     let vis = Visibility::Public(VisPublic{pub_token});
-    let (methods, traits) = if let Some((_, path, _)) = item_impl.trait_ {
+    let (consts, methods, traits) = if let Some((_, path, _)) = item_impl.trait_
+    {
         let mut items = Vec::new();
+        for ic in consts.into_iter() {
+            let tic = TraitItemConst {
+                attrs: ic.attrs.clone(),
+                const_token: ic.const_token,
+                ident: ic.ident.clone(),
+                colon_token: ic.colon_token,
+                ty: ic.ty.clone(),
+                default: Some((ic.eq_token, ic.expr.clone())),
+                semi_token: ic.semi_token
+            };
+            items.push(TraitItem::Const(tic));
+        }
         for ty in titys.into_iter() {
             items.push(TraitItem::Type(ty));
         }
@@ -539,16 +982,17 @@ fn mock_impl(item_impl: ItemImpl) -> TokenStream {
             items
         };
         let concretized_trait = attrs.substitute_trait(&trait_);
-        (Vec::new(), vec![concretized_trait])
+        (Vec::new(), Vec::new(), vec![concretized_trait])
     } else {
         assert!(titys.is_empty());
-        (methods, Vec::new())
+        (consts, methods, Vec::new())
     };
     let mock = Mock {
         attrs: item_impl.attrs.clone(),
         vis,
         name,
         generics: item_impl.generics.clone(),
+        consts,
         methods,
         traits
     };
@@ -556,7 +1000,7 @@ fn mock_impl(item_impl: ItemImpl) -> TokenStream {
 }
 
 /// Generate mock functions for an entire module
-fn mock_module(mod_: ItemMod) -> TokenStream {
+fn mock_module(attrs: Attrs, mod_: ItemMod) -> TokenStream {
     let mut body = TokenStream::new();
     let mut cp_body = TokenStream::new();
     let modname = format_ident!("mock_{}", mod_.ident);
@@ -568,6 +1012,16 @@ fn mock_module(mod_: ItemMod) -> TokenStream {
         "automock can only mock inline modules, not modules from another file");
         Vec::new()
     };
+    let consts = items.iter().filter_map(|item| match item {
+        Item::Const(ic) => Some(&ic.ident),
+        _ => None
+    }).collect::<Vec<_>>();
+    let statics = items.iter().filter_map(|item| match item {
+        Item::Static(is) => Some(&is.ident),
+        _ => None
+    }).collect::<Vec<_>>();
+    attrs.check_consts(&consts);
+    attrs.check_statics(&statics);
     for item in items.iter() {
         match item {
             Item::ExternCrate(_) | Item::Impl(_) =>
@@ -575,9 +1029,23 @@ fn mock_module(mod_: ItemMod) -> TokenStream {
                 // Ignore
             },
             Item::Static(is) => {
-                is.to_tokens(&mut body)
+                let mut is = is.clone();
+                if let Some(expr) = attrs.statics.get(&is.ident) {
+                    *is.expr = expr.clone();
+                }
+                is.to_tokens(&mut body);
+                if is.mutability.is_some() {
+                    static_context(&is.vis, &is.ident, &is.ty)
+                        .to_tokens(&mut body);
+                }
+            },
+            Item::Const(ic) => {
+                let mut ic = ic.clone();
+                if let Some(expr) = attrs.consts.get(&ic.ident) {
+                    *ic.expr = expr.clone();
+                }
+                ic.to_tokens(&mut body)
             },
-            Item::Const(ic) => ic.to_tokens(&mut body),
             Item::Fn(f) => {
                 let mod_ident = format_ident!("__{}", &f.sig.ident);
                 quote!(
@@ -627,23 +1095,82 @@ fn mock_module(mod_: ItemMod) -> TokenStream {
 /// Mock a function the same way we mock static trait methods: with a
 /// global Expectations object
 fn mock_native_function(modname: &Ident, f: &ItemFn) -> TokenStream {
-    mock_function(modname, &f.vis, &f.sig)
+    mock_function(modname, &f.vis, &f.sig, None, None)
+}
+
+/// Substitute the associated types that a trait already defines, as in
+/// `mock!`, into the signatures of its methods.
+pub(crate) fn substitute_own_types(item: &ItemTrait) -> ItemTrait {
+    let mut attrs = Attrs::default();
+    for trait_item in item.items.iter() {
+        if let TraitItem::Type(tity) = trait_item {
+            if let Some((_, ty)) = &tity.default {
+                attrs.attrs.insert(tity.ident.clone(), ty.clone());
+                if !tity.generics.params.is_empty() {
+                    attrs.type_generics.insert(tity.ident.clone(),
+                        tity.generics.clone());
+                }
+            }
+        }
+    }
+    if attrs.attrs.is_empty() {
+        item.clone()
+    } else {
+        attrs.substitute_trait(item)
+    }
 }
 
 /// Generate a mock struct that implements a trait
 fn mock_trait(attrs: Attrs, item: ItemTrait) -> TokenStream {
+    for ident in attrs.statics.keys() {
+        compile_error(ident.span(),
+            "automock static attributes are only allowed on modules and foreign blocks");
+    }
+    let consts = item.items.iter().filter_map(|ti| match ti {
+        TraitItem::Const(tic) => Some(&tic.ident),
+        _ => None
+    }).collect::<Vec<_>>();
+    attrs.check_consts(&consts);
     let trait_ = attrs.substitute_trait(&item);
+    let generics = merge_generics(&item.generics, &attrs.generics(&item));
     let mock = Mock {
         attrs: item.attrs.clone(),
         vis: item.vis.clone(),
         name: item.ident.clone(),
-        generics: item.generics,
+        generics,
+        consts: Vec::new(),
         methods: Vec::new(),
         traits: vec![trait_]
     };
     mock.gen()
 }
 
+/// If `#[async_trait]` comes after `#[automock]`, swap them.  That way the
+/// trait or impl gets mocked after `#[async_trait]` has rewritten its `async fn`
+/// methods, just as if the attributes had been written in the other order.
+pub(crate)
+fn reorder_async_trait(attr_stream: &TokenStream, input: &TokenStream)
+    -> Option<TokenStream>
+{
+    let mut item: Item = parse2(input.clone()).ok()?;
+    let attrs = match &mut item {
+        Item::Impl(item_impl) => &mut item_impl.attrs,
+        Item::Trait(item_trait) => &mut item_trait.attrs,
+        _ => return None
+    };
+    let (async_trait, others): (Vec<_>, Vec<_>) = attrs.drain(..)
+        .partition(is_async_trait_attr);
+    if async_trait.is_empty() {
+        return None;
+    }
+    *attrs = others;
+    Some(quote!(
+        #(#async_trait)*
+        #[::mockall::automock(#attr_stream)]
+        #item
+    ))
+}
+
 pub(crate)
 fn do_automock(attr_stream: TokenStream, input: TokenStream) -> TokenStream
 {
@@ -659,10 +1186,22 @@ fn do_automock(attr_stream: TokenStream, input: TokenStream) -> TokenStream
             return err.to_compile_error();
         }
     };
+    if let Some((_, span)) = attrs.export {
+        if !matches!(item, Item::ForeignMod(_)) {
+            compile_error(span,
+                "export attributes are only allowed on foreign blocks");
+            return TokenStream::new();
+        }
+    }
     let ts = match item {
-        Item::Impl(item_impl) => mock_impl(item_impl),
+        Item::Impl(item_impl) => {
+            // An impl block's constants come from the impl itself
+            attrs.check_consts(&[]);
+            attrs.check_statics(&[]);
+            mock_impl(item_impl)
+        },
         Item::ForeignMod(foreign_mod) => mock_foreign(attrs, foreign_mod),
-        Item::Mod(item_mod) => mock_module(item_mod),
+        Item::Mod(item_mod) => mock_module(attrs, item_mod),
         Item::Trait(item_trait) => mock_trait(attrs, item_trait),
         _ => {
             compile_error(item.span(),
@@ -700,6 +1239,36 @@ mod t {
                               quote!(u32));
     }
 
+    #[test]
+    fn gat() {
+        check_substitute_type(quote!(type Iter<'a> = Iter<'a, u32>;),
+                              quote!(Self::Iter<'b>),
+                              quote!(Iter<'b, u32>));
+    }
+
+    /// Only the parameters themselves get substituted, not path segments or
+    /// other identifiers that share their names
+    #[test]
+    fn gat_path_segment() {
+        check_substitute_type(quote!(type W<T> = (T, other::T, <T as X>::T);),
+                              quote!(Self::W<u8>),
+                              quote!((u8, other::T, <u8 as X>::T)));
+    }
+
+    #[test]
+    fn gat_const() {
+        check_substitute_type(quote!(type A<const N: usize> = [u8; N];),
+                              quote!(Self::A<4>),
+                              quote!([u8; 4]));
+    }
+
+    #[test]
+    fn gat_qself() {
+        check_substitute_type(quote!(type W<T> = Vec<T>;),
+                              quote!(<Self as Foo>::W<u8>),
+                              quote!(Vec<u8>));
+    }
+
     #[test]
     fn method_visibility() {
         let code = r#"
@@ -725,6 +1294,20 @@ mod t {
         assert!(output.contains("pub ( in super :: x ) fn expect_bean"));
     }
 
+    /// A type parameter that may borrow through a lifetime parameter can't
+    /// have Expectations, but the error should say what to do about it.
+    #[test]
+    #[should_panic(expected = "Add a `+ 'static` bound")]
+    fn borrowing_type_parameter() {
+        let code = r#"
+        trait Parser {
+            fn parse<'a, T: Decode<'a>>(&self, buf: &'a [u8]) -> T;
+        }"#;
+        let ts = proc_macro2::TokenStream::from_str(code).unwrap();
+        let attrs_ts = proc_macro2::TokenStream::from_str("").unwrap();
+        do_automock(attrs_ts, ts).to_string();
+    }
+
     #[test]
     #[should_panic(expected = "can only mock inline modules")]
     fn external_module() {
@@ -42,6 +42,21 @@ fn destrify(ty: &mut Type) {
     }
 }
 
+/// Will a method's Expectation return its output by value, like
+/// [`StaticExpectation`] does?  Methods that return non-`'static` references,
+/// and non-static methods whose outputs have lifetime parameters, get other
+/// kinds of Expectation.
+pub(crate) fn returns_by_value(rt: &ReturnType, is_static: bool) -> bool {
+    match rt {
+        ReturnType::Default => true,
+        ReturnType::Type(_, ty) => match ty.as_ref() {
+            Type::Reference(tr) =>
+                matches!(&tr.lifetime, Some(lt) if lt.ident == "static"),
+            ty => is_static || !crate::borrowify(&mut ty.clone())
+        }
+    }
+}
+
 /// Stuff that's common between all Expectation types
 struct Common<'a> {
     /// Names of the method arguments
@@ -67,6 +82,10 @@ struct Common<'a> {
     rlifetimes: Generics,
     /// Is this for a static method or free function?
     is_static: bool,
+    /// Is this for an `async fn` whose mock calls the Expectation right away?
+    /// If so, the Expectation wraps its output in a future that can be
+    /// tracked.
+    is_async: bool,
     /// Expressions that create the predicate arguments from the call arguments
     predexprs: Vec<TokenStream>,
     /// Types used for Predicates.  Will be almost the same as args, but every
@@ -97,6 +116,42 @@ impl<'a> Common<'a> {
         let lg = &self.alifetimes;
         let hrtb = self.hrtb();
         quote!(
+            /// Report any call to this expectation that overlaps another call
+            /// to the same method, for example from another thread.
+            ///
+            /// Only calls to this one method of this one mock object are
+            /// considered.  Calls to the mock's other methods may still
+            /// overlap.
+            #v fn exclusive(&mut self) -> &mut Self {
+                self.common.exclusive();
+                self
+            }
+
+            /// Forbid this expectation from being called on the given thread.
+            #v fn not_on_thread(&mut self, __mockall_id: ::std::thread::ThreadId)
+                -> &mut Self
+            {
+                self.common.not_on_thread(__mockall_id);
+                self
+            }
+
+            /// Require this expectation to be called only on the given thread.
+            #v fn on_thread(&mut self, __mockall_id: ::std::thread::ThreadId)
+                -> &mut Self
+            {
+                self.common.on_thread(__mockall_id);
+                self
+            }
+
+            /// Make every matching call block until `latch` is released.
+            /// See [`Latch`](../../../mockall/struct.Latch.html).
+            #v fn blocking_on(&mut self, __mockall_latch: &::mockall::Latch)
+                -> &mut Self
+            {
+                self.common.blocking_on(__mockall_latch);
+                self
+            }
+
             /// Add this expectation to a
             /// [`Sequence`](../../../mockall/struct.Sequence.html).
             #v fn in_sequence(&mut self, __mockall_seq: &mut ::mockall::Sequence)
@@ -106,6 +161,12 @@ impl<'a> Common<'a> {
                 self
             }
 
+            /// Get a handle to this expectation that can be used to wait
+            /// for calls from other threads.
+            #v fn handle(&self) -> ::mockall::ExpectationHandle {
+                self.common.handle()
+            }
+
             fn is_done(&self) -> bool {
                 self.common.is_done()
             }
@@ -210,36 +271,101 @@ impl<'a> Common<'a> {
         let generics = merge_generics(&self.egenerics, &self.rlifetimes);
         let (ig, tg, wc) = generics.split_for_impl();
         let v = &self.vis;
+        // Static methods' Expectations live behind a global Mutex.  Each
+        // Expectation is reference counted so a call can release that Mutex
+        // before running the returning closure, which may call the method
+        // again.
+        let (elem, expectation_mut) = if self.is_static {
+            let elem = quote!(::std::sync::Arc<Expectation #tg>);
+            let expectation_mut = quote!(
+                /// Access an expectation for modification.
+                #v fn expectation_mut(&mut self, __mockall_i: usize)
+                    -> &mut Expectation #tg
+                {
+                    ::std::sync::Arc::get_mut(&mut self.0[__mockall_i])
+                        .expect(
+                            "Cannot modify an Expectation while it is being called")
+                }
+            );
+            (elem, expectation_mut)
+        } else {
+            (quote!(Expectation #tg), TokenStream::new())
+        };
+        let push = if self.is_static {
+            quote!(self.0.push(::std::sync::Arc::new(__mockall_e));)
+        } else {
+            quote!(self.0.push(__mockall_e);)
+        };
+        let last = if self.is_static {
+            quote!(self.expectation_mut(__mockall_l - 1))
+        } else {
+            quote!(&mut self.0[__mockall_l - 1])
+        };
+        let (check, reset_failures) = self.static_failures(quote!(self.1));
         quote!(
             /// A collection of [`Expectation`](struct.Expectations.html)
             /// objects.  Users will rarely if ever use this struct directly.
             #[doc(hidden)]
-            #v struct Expectations #ig ( Vec<Expectation #tg>) #wc;
+            #v struct Expectations #ig ( Vec<#elem>,
+                                         ::mockall::Failures,
+                                         ::std::sync::Arc<::mockall::InFlight>)
+                                         #wc;
 
             impl #ig Expectations #tg #wc {
                 /// Verify that all current expectations are satisfied and clear
                 /// them.
-                #v fn checkpoint(&mut self) -> std::vec::Drain<Expectation #tg>
+                #v fn checkpoint(&mut self) -> std::vec::Drain<#elem>
                 {
+                    #check
                     self.0.drain(..)
                 }
 
                 /// Create a new expectation for this method.
                 #v fn expect(&mut self) -> &mut Expectation #tg
                 {
-                    self.0.push(Expectation::default());
+                    let mut __mockall_e = Expectation::default();
+                    __mockall_e.common.concurrency.share(&self.2);
+                    #push
                     let __mockall_l = self.0.len();
-                    &mut self.0[__mockall_l - 1]
+                    #last
+                }
+
+                #expectation_mut
+
+                /// Failures recorded while calling this method
+                #v fn failures(&self) -> ::mockall::Failures {
+                    self.1.clone()
                 }
 
+                #reset_failures
+
                 #v fn new() -> Self {
                     Self::default()
                 }
+
+                /// Block until every expectation has been called its minimum
+                /// number of times.
+                #v fn wait_until_satisfied(&self,
+                    __mockall_deadline: ::std::time::Instant)
+                    -> ::std::result::Result<(), ::mockall::Timeout>
+                {
+                    for __mockall_e in self.0.iter() {
+                        __mockall_e.common
+                            .wait_until_satisfied(__mockall_deadline)?;
+                    }
+                    Ok(())
+                }
             }
             impl #ig Default for Expectations #tg #wc
             {
                 fn default() -> Self {
-                    Expectations(Vec::new())
+                    Expectations(Vec::new(), ::mockall::Failures::default(),
+                                 ::std::sync::Arc::default())
+                }
+            }
+            impl #ig Drop for Expectations #tg #wc {
+                fn drop(&mut self) {
+                    self.1.check();
                 }
             }
         )
@@ -251,6 +377,8 @@ impl<'a> Common<'a> {
         if !self.is_generic() {
             return TokenStream::new();
         }
+        let (check, reset_failures) =
+            self.static_failures(quote!(self.failures));
         quote!(
             /// A collection of [`Expectation`](struct.Expectations.html)
             /// objects for a generic method.  Users will rarely if ever use
@@ -258,6 +386,7 @@ impl<'a> Common<'a> {
             #[doc(hidden)]
             #[derive(Default)]
             #v struct GenericExpectations{
+                failures: ::mockall::Failures,
                 store: std::collections::hash_map::HashMap<::mockall::Key,
                                Box<dyn ::mockall::AnyExpectations>>
             }
@@ -268,16 +397,65 @@ impl<'a> Common<'a> {
                     std::collections::hash_map::Drain<::mockall::Key,
                                Box<dyn ::mockall::AnyExpectations>>
                 {
+                    #check
                     self.store.drain()
                 }
 
+                /// Failures recorded while calling this method, for any set
+                /// of generic parameters
+                #v fn failures(&self) -> ::mockall::Failures {
+                    self.failures.clone()
+                }
+
+                #reset_failures
+
                 #v fn new() -> Self {
                     Self::default()
                 }
+
+                /// Block until every expectation, for all sets of generic
+                /// parameters, has been called its minimum number of times.
+                #v fn wait_until_satisfied(&self,
+                    __mockall_deadline: ::std::time::Instant)
+                    -> ::std::result::Result<(), ::mockall::Timeout>
+                {
+                    for __mockall_e in self.store.values() {
+                        __mockall_e.wait_until_satisfied(__mockall_deadline)?;
+                    }
+                    Ok(())
+                }
+            }
+            impl Drop for GenericExpectations {
+                fn drop(&mut self) {
+                    self.failures.check();
+                }
             }
         )
     }
 
+    /// Statements for an Expectations object's `checkpoint` method to check
+    /// the `failures` recorded so far, and its `reset_failures` method, if
+    /// any.  A static method's failures get checked by its `Context` instead,
+    /// after releasing the lock on its Expectations, and they belong to
+    /// whichever thread last created a `Context`.
+    fn static_failures(&self, failures: TokenStream)
+        -> (TokenStream, TokenStream)
+    {
+        let v = &self.vis;
+        if self.is_static {
+            let reset = quote!(
+                /// Record failures on behalf of the calling thread from now
+                /// on, forgetting any earlier ones.
+                #v fn reset_failures(&mut self) {
+                    #failures = ::mockall::Failures::default();
+                }
+            );
+            (TokenStream::new(), reset)
+        } else {
+            (quote!(#failures.check();), TokenStream::new())
+        }
+    }
+
     fn hrtb(&self) -> TokenStream {
         if self.alifetimes.params.is_empty() {
             TokenStream::default()
@@ -287,6 +465,98 @@ impl<'a> Common<'a> {
         }
     }
 
+    /// Return type of the Expectation's `call` method
+    fn call_output(&self) -> TokenStream {
+        let output = &self.output;
+        if self.is_async {
+            quote!(::mockall::TrackedFuture<::std::future::Ready<#output>>)
+        } else {
+            quote!(#output)
+        }
+    }
+
+    /// Generics of `GenericExpectations::call`, which must also be generic
+    /// over the arguments' lifetimes.
+    fn call_generics(&self) -> Generics {
+        merge_generics(&self.alifetimes, &self.egenerics)
+    }
+
+    /// The Expectation's where clause, plus the `Send + Sync` bound on its
+    /// output that downcasting a stored return value requires.
+    fn sync_where_clause(&self) -> WhereClause {
+        let output = &self.output;
+        let mut generics = self.egenerics.clone();
+        generics.make_where_clause().predicates.push(
+            parse2(quote!(#output: Send + Sync)).unwrap());
+        generics.where_clause.unwrap()
+    }
+
+    /// Higher-ranked trait bound and argument types for the `Predicate`s that
+    /// match each argument.  A trait object argument with no lifetime bound,
+    /// like `&dyn Debug`, must be matched for any lifetime, not just
+    /// `'static`.
+    fn pred_types(&self) -> (TokenStream, Vec<Type>) {
+        let olt = Lifetime::new("'__mockall_o", Span::call_site());
+        let mut any_objects = false;
+        let predty = self.predty.iter().map(|t| {
+            let mut t = t.clone();
+            let mut inner = &mut t;
+            while let Type::Paren(p) = inner {
+                inner = p.elem.as_mut();
+            }
+            if let Type::TraitObject(tto) = inner {
+                if !tto.bounds.iter()
+                    .any(|b| matches!(b, TypeParamBound::Lifetime(_)))
+                {
+                    tto.bounds.push(TypeParamBound::Lifetime(olt.clone()));
+                    any_objects = true;
+                }
+            }
+            t
+        }).collect();
+        let hrtb = if any_objects {
+            let mut lts = self.alifetimes.clone();
+            lts.params.insert(0,
+                GenericParam::Lifetime(LifetimeDef::new(olt)));
+            lts.lt_token.get_or_insert_with(Default::default);
+            lts.gt_token.get_or_insert_with(Default::default);
+            quote!(for #lts)
+        } else {
+            self.hrtb()
+        };
+        (hrtb, predty)
+    }
+
+    /// Identifies one instantiation of a generic method's `Expectations`
+    /// within its `GenericExpectations`.
+    fn key(&self) -> TokenStream {
+        // PhantomData, because the parameters may be ?Sized
+        let fn_params = self.fn_params.iter();
+        let const_key = if self.egenerics.const_params().next().is_some() {
+            let idents = self.egenerics.const_params().map(|cp| &cp.ident);
+            quote!(ConstKey<#(#idents),*>)
+        } else {
+            TokenStream::new()
+        };
+        quote!(::mockall::Key::new::<
+            (#(::std::marker::PhantomData<#fn_params>, )* #const_key)
+        >())
+    }
+
+    /// A marker type that distinguishes the Expectations of a method's const
+    /// generic parameters by value.
+    fn const_key(&self) -> TokenStream {
+        if self.egenerics.const_params().next().is_none() {
+            return TokenStream::new();
+        }
+        let cps = self.egenerics.const_params().map(|cp| {
+            let ident = &cp.ident;
+            let ty = &cp.ty;
+            quote!(const #ident: #ty)
+        });
+        quote!(struct ConstKey<#(#cps),*>;)
+    }
+
     fn ident_str(&self) -> String {
         if let Some(pi) = self.parent_ident {
             format!("{}::{}", pi, self.meth_ident)
@@ -299,16 +569,13 @@ impl<'a> Common<'a> {
     /// parameters.
     fn is_generic(&self) -> bool {
         self.egenerics.params.iter().any(|p| {
-            if let GenericParam::Type(_) = p {
-                true
-            } else {
-                false
-            }
+            !matches!(p, GenericParam::Lifetime(_))
         }) || self.egenerics.where_clause.is_some()
     }
 }
 
 pub(crate) enum Expectation<'a> {
+    Borrow(BorrowExpectation<'a>),
     Ref(RefExpectation<'a>),
     RefMut(RefMutExpectation<'a>),
     Static(StaticExpectation<'a>)
@@ -317,6 +584,7 @@ pub(crate) enum Expectation<'a> {
 macro_rules! dispatch {
     ($self:ident, $func:ident $(,$args:ident)*) => {
         match $self {
+            Expectation::Borrow(e) => e.$func($($args, )*),
             Expectation::Ref(e) => e.$func($($args, )*),
             Expectation::RefMut(e) => e.$func($($args, )*),
             Expectation::Static(e) => e.$func($($args, )*),
@@ -351,10 +619,11 @@ impl<'a> Expectation<'a> {
         let rfunc_ts = self.rfunc();
         let (ig, tg, wc) = self.common().egenerics.split_for_impl();
         let hrtb = self.common().hrtb();
+        let (pred_hrtb, pred_predty) = self.common().pred_types();
         let lg = &self.common().alifetimes;
         let preds = TokenStream::from_iter(
-            self.common().predty.iter().map(|t|
-                quote!(Box<dyn #hrtb ::mockall::Predicate<#t> + Send>,)
+            pred_predty.iter().map(|t|
+                quote!(Box<dyn #pred_hrtb ::mockall::Predicate<#t> + Send>,)
             )
         );
         let pred_matches = TokenStream::from_iter(
@@ -371,9 +640,9 @@ impl<'a> Expectation<'a> {
             .map(|i| format_ident!("MockallMatcher{}", i))
             .collect::<Vec<_>>();
         let with_generics = TokenStream::from_iter(
-            with_generics_idents.iter().zip(self.common().predty.iter())
+            with_generics_idents.iter().zip(pred_predty.iter())
             .map(|(id, mt)|
-                quote!(#id: #hrtb ::mockall::Predicate<#mt> + Send + 'static, )
+                quote!(#id: #pred_hrtb ::mockall::Predicate<#mt> + Send + 'static, )
             )
         );
         let with_args = TokenStream::from_iter(
@@ -452,6 +721,8 @@ impl<'a> Expectation<'a> {
 
             /// Holds the stuff that is independent of the output type
             struct Common #ig #wc {
+                concurrency: ::mockall::Concurrency,
+                latch: Option<::mockall::Latch>,
                 matcher: Mutex<Matcher #tg>,
                 seq_handle: Option<::mockall::SeqHandle>,
                 times: ::mockall::Times
@@ -461,6 +732,8 @@ impl<'a> Expectation<'a> {
             {
                 fn default() -> Self {
                     Common {
+                        concurrency: ::mockall::Concurrency::default(),
+                        latch: None,
                         matcher: Mutex::new(Matcher::default()),
                         seq_handle: None,
                         times: ::mockall::Times::default()
@@ -469,18 +742,51 @@ impl<'a> Expectation<'a> {
             }
 
             impl #ig Common #tg #wc {
-                fn call(&self) {
+                fn blocking_on(&mut self, __mockall_latch: &::mockall::Latch) {
+                    self.latch = Some(__mockall_latch.clone());
+                }
+
+                fn call(&self) -> ::mockall::InFlightGuard<'_> {
+                    if let Some(__mockall_latch) = &self.latch {
+                        __mockall_latch.wait();
+                    }
+                    if let Some(__mockall_handle) = &self.seq_handle {
+                        __mockall_handle.wait()
+                    }
+                    let __mockall_flight = self.concurrency.enter()
+                        .unwrap_or_else(|m| {
+                            let desc = format!("{}",
+                                               self.matcher.lock().unwrap());
+                            ::mockall::violation(format!(
+                                "{}: Expectation({}) {}", #ident_str, desc, m));
+                        });
                     self.times.call()
                         .unwrap_or_else(|m| {
                             let desc = format!("{}",
                                                self.matcher.lock().unwrap());
-                            panic!("{}: Expectation({}) {}", #ident_str, desc,
-                                m);
+                            ::mockall::violation(format!(
+                                "{}: Expectation({}) {}", #ident_str, desc, m));
                         });
                     self.verify_sequence();
                     if self.times.is_satisfied() {
                         self.satisfy_sequence()
                     }
+                    __mockall_flight
+                }
+
+                /// Finish a call begun by `call`
+                fn exit(&self, __mockall_flight: ::mockall::InFlightGuard<'_>) {
+                    __mockall_flight.exit()
+                        .unwrap_or_else(|m| {
+                            let desc = format!("{}",
+                                               self.matcher.lock().unwrap());
+                            ::mockall::violation(format!(
+                                "{}: Expectation({}) {}", #ident_str, desc, m));
+                        });
+                }
+
+                fn exclusive(&mut self) {
+                    self.concurrency.exclusive();
                 }
 
                 fn in_sequence(&mut self, __mockall_seq: &mut ::mockall::Sequence)
@@ -492,6 +798,10 @@ impl<'a> Expectation<'a> {
                     self
                 }
 
+                fn handle(&self) -> ::mockall::ExpectationHandle {
+                    self.times.handle()
+                }
+
                 fn is_done(&self) -> bool {
                     self.times.is_done()
                 }
@@ -505,6 +815,15 @@ impl<'a> Expectation<'a> {
                     self.times.never();
                 }
 
+                fn not_on_thread(&mut self, __mockall_id: ::std::thread::ThreadId)
+                {
+                    self.concurrency.not_on_thread(__mockall_id);
+                }
+
+                fn on_thread(&mut self, __mockall_id: ::std::thread::ThreadId) {
+                    self.concurrency.on_thread(__mockall_id);
+                }
+
                 fn satisfy_sequence(&self) {
                     if let Some(__mockall_handle) = &self.seq_handle {
                         __mockall_handle.satisfy()
@@ -549,6 +868,13 @@ impl<'a> Expectation<'a> {
                         __mockall_handle.verify()
                     }
                 }
+
+                fn wait_until_satisfied(&self,
+                    __mockall_deadline: ::std::time::Instant)
+                    -> ::std::result::Result<(), ::mockall::Timeout>
+                {
+                    self.times.wait_satisfied(__mockall_deadline)
+                }
             }
 
             impl #ig Drop for Common #tg #wc {
@@ -572,6 +898,7 @@ impl<'a> Expectation<'a> {
         let gem_ts1 = self.common().generic_expectations_methods();
         let gem_ts2 = self.generic_expectations_methods();
         let sm_ts = self.static_method_methods(&with_generics, &with_args);
+        let const_key_ts = self.common().const_key();
         quote!(
             #attrs
             #[allow(missing_docs)]
@@ -584,6 +911,7 @@ impl<'a> Expectation<'a> {
                     ops::{DerefMut, Range},
                     sync::Mutex
                 };
+                #const_key_ts
                 #rfunc_ts
                 #matcher_ts
                 #expectation_ts
@@ -609,6 +937,7 @@ impl<'a> Expectation<'a> {
     /// * `meth_ident`      - Name of the original method
     /// * `parent_ident`    - Name of the parent struct, if any.
     /// * `return_type`     - Return type of the mock method
+    /// * `is_async`        - Whether the mock method is an `async fn`
     /// * `vis`             - Visibility of the expectation, *already supersuperfied*.
     /// * `levels`          - Depth of modules added by the caller
     #[allow(clippy::unused_unit)]
@@ -622,6 +951,7 @@ impl<'a> Expectation<'a> {
         mod_ident: &'a Ident,
         parent_ident: Option<&'a Ident>,
         rt: &ReturnType,
+        is_async: bool,
         vis: &Visibility,
         levels: i32) -> Self
     {
@@ -629,6 +959,7 @@ impl<'a> Expectation<'a> {
         let mut argnames = Vec::new();
         let mut argty = Vec::new();
         let mut is_static = true;
+        let mut mut_receiver = false;
         let mut predexprs = Vec::new();
         let mut predty = Vec::new();
         for fa in args.iter() {
@@ -645,6 +976,10 @@ impl<'a> Expectation<'a> {
                 argnames.push(argname);
                 argty.push(aty);
             } else {
+                if let FnArg::Receiver(r) = fa {
+                    mut_receiver = r.reference.is_some() &&
+                        r.mutability.is_some();
+                }
                 is_static = false;
                 ()    // Strip out the "&self" argument
             }
@@ -657,6 +992,7 @@ impl<'a> Expectation<'a> {
 
         let mut ref_expectation = false;
         let mut ref_mut_expectation = false;
+        let mut borrow_expectation = false;
         let output = supersuperfy(&match rt {
             ReturnType::Default => Type::Tuple(TypeTuple {
                 paren_token: token::Paren::default(),
@@ -683,6 +1019,9 @@ impl<'a> Expectation<'a> {
                         (*tr.elem).clone()
                     }
                 } else {
+                    if !is_static && crate::borrowify(&mut rt) {
+                        borrow_expectation = true;
+                    }
                     rt
                 }
             }
@@ -718,6 +1057,7 @@ impl<'a> Expectation<'a> {
             alifetimes,
             rlifetimes,
             is_static,
+            is_async,
             predexprs,
             predty,
             meth_ident,
@@ -726,7 +1066,9 @@ impl<'a> Expectation<'a> {
             parent_ident,
             vis: vis.clone()
         };
-        if ref_mut_expectation {
+        if borrow_expectation {
+            Expectation::Borrow(BorrowExpectation::new(common, mut_receiver))
+        } else if ref_mut_expectation {
             Expectation::RefMut(RefMutExpectation::new(common))
         } else if ref_expectation {
             Expectation::Ref(RefExpectation::new(common))
@@ -775,28 +1117,103 @@ impl<'a> StaticExpectation<'a> {
         let hrtb = self.common.hrtb();
         let lg = &self.common.alifetimes;
         let output = &self.common.output;
+        let call_output = self.common.call_output();
         let v = &self.common.vis;
+        let future_methods = self.future_methods();
+        let iterator_methods = self.iterator_methods();
+        let stream_methods = self.stream_methods();
+        // Extra fields for tracking the use of returned futures and streams
+        let mut tracker_fields = TokenStream::new();
+        let mut tracker_defaults = TokenStream::new();
+        let mut drop_checks = TokenStream::new();
+        let mut track = TokenStream::new();
+        let mut ret = quote!(__mockall_r);
+        if self.future_types().is_some() || self.common.is_async {
+            tracker_fields.extend(quote!(
+                poll_tracker: ::mockall::PollTracker,
+            ));
+            tracker_defaults.extend(quote!(
+                poll_tracker: ::mockall::PollTracker::default(),
+            ));
+            drop_checks.extend(quote!(
+                let __mockall_n = self.poll_tracker.unpolled();
+                if __mockall_n > 0 {
+                    let desc = format!("{}",
+                        self.common.matcher.lock().unwrap());
+                    panic!("{}: Expectation({}) returned {} future(s) that were never polled to completion",
+                           #ident_str, desc, __mockall_n);
+                }
+            ));
+            if self.common.is_async {
+                ret = quote!(self.poll_tracker.ready(__mockall_r));
+            } else {
+                track = quote!(
+                    if self.poll_tracker.is_enabled() {
+                        return Box::pin(self.poll_tracker.track(__mockall_r));
+                    }
+                );
+            }
+        }
+        if self.stream_types().is_some() {
+            tracker_fields.extend(quote!(
+                pull_tracker: ::mockall::PullTracker,
+            ));
+            tracker_defaults.extend(quote!(
+                pull_tracker: ::mockall::PullTracker::default(),
+            ));
+            drop_checks.extend(quote!(
+                if let Err(__mockall_m) = self.pull_tracker.verify() {
+                    let desc = format!("{}",
+                        self.common.matcher.lock().unwrap());
+                    panic!("{}: Expectation({}) {}", #ident_str, desc,
+                           __mockall_m);
+                }
+            ));
+        }
+        let tracker_drop = if drop_checks.is_empty() {
+            TokenStream::new()
+        } else {
+            quote!(
+                impl #ig Drop for Expectation #tg #wc {
+                    fn drop(&mut self) {
+                        if !::std::thread::panicking() {
+                            #drop_checks
+                        }
+                    }
+                }
+            )
+        };
         quote!(
             /// Expectation type for methods that return a `'static` type.
             /// This is the type returned by the `expect_*` methods.
             #v struct Expectation #ig #wc {
                 common: Common #common_tg,
+                reentrancy: ::mockall::Reentrancy,
                 rfunc: Mutex<Rfunc #tg>,
+                #tracker_fields
             }
 
             impl #ig Expectation #tg #wc {
                 /// Call this [`Expectation`] as if it were the real method.
                 #[doc(hidden)]
-                #v fn call #lg (&self, #(#argnames: #argty, )* ) -> #output
+                #v fn call #lg (&self, #(#argnames: #argty, )* )
+                    -> #call_output
                 {
-                    self.common.call();
-                    self.rfunc.lock().unwrap().call_mut(#(#argnames, )*)
-                        .unwrap_or_else(|message| {
+                    let __mockall_flight = self.common.call();
+                    let __mockall_r = match self.reentrancy.lock(&self.rfunc) {
+                        Ok(mut __mockall_rfunc) =>
+                            __mockall_rfunc.call_mut(#(#argnames, )*),
+                        Err(__mockall_m) => Err(__mockall_m)
+                    }.unwrap_or_else(|message| {
                             let desc = format!("{}",
                                 self.common.matcher.lock().unwrap());
-                            panic!("{}: Expectation({}) {}", #ident_str, desc,
-                                   message);
-                        })
+                            ::mockall::violation(format!(
+                                "{}: Expectation({}) {}", #ident_str, desc,
+                                message));
+                        });
+                    self.common.exit(__mockall_flight);
+                    #track
+                    #ret
                 }
 
                 /// Return a constant value from the `Expectation`
@@ -889,6 +1306,12 @@ impl<'a> StaticExpectation<'a> {
                     self
                 }
 
+                #future_methods
+
+                #iterator_methods
+
+                #stream_methods
+
                 #em_ts
             }
             impl #ig Default for Expectation #tg #wc
@@ -896,13 +1319,311 @@ impl<'a> StaticExpectation<'a> {
                 fn default() -> Self {
                     Expectation {
                         common: Common::default(),
-                        rfunc: Mutex::new(Rfunc::default())
+                        reentrancy: ::mockall::Reentrancy::default(),
+                        rfunc: Mutex::new(Rfunc::default()),
+                        #tracker_defaults
                     }
                 }
             }
+
+            #tracker_drop
+        )
+    }
+
+    /// Methods for Expectations whose methods return boxed futures, like
+    /// methods that return `impl Future`.  `async fn` methods only get
+    /// `must_be_polled`.  Returns an empty TokenStream for other methods.
+    fn future_methods(&self) -> TokenStream {
+        let v = &self.common.vis;
+        let must_be_polled = quote!(
+            /// Require every future returned by this `Expectation` to be
+            /// polled to completion before the `Expectation` is checkpointed
+            /// or dropped.  This catches futures that get dropped without
+            /// being `.await`ed.
+            #v fn must_be_polled(&mut self) -> &mut Self {
+                self.poll_tracker.enable();
+                self
+            }
+        );
+        let (fut_output, bounds) = match self.future_types() {
+            Some(x) => x,
+            None if self.common.is_async => return must_be_polled,
+            None => return TokenStream::new()
+        };
+        let argnames = &self.common.argnames;
+        let argty = &self.common.argty;
+        let hrtb = self.common.hrtb();
+        quote!(
+            #must_be_polled
+
+            /// Return a future that is immediately ready with a constant
+            /// value.  The value must be `Clone`.
+            #[allow(unused_variables)]
+            #v fn return_ready<MockallOutput>(&mut self,
+                __mockall_c: MockallOutput) -> &mut Self
+                where MockallOutput: Clone + Into<#fut_output> + Send
+                                     #bounds + 'static
+            {
+                self.return_after_pending(0, __mockall_c)
+            }
+
+            /// Return a future that returns `Poll::Pending` `n` times, waking
+            /// its task each time, before resolving to a constant value.
+            /// This is useful for testing code that polls futures itself.
+            #[allow(unused_variables)]
+            #v fn return_after_pending<MockallOutput>(&mut self, n: usize,
+                __mockall_c: MockallOutput) -> &mut Self
+                where MockallOutput: Clone + Into<#fut_output> + Send
+                                     #bounds + 'static
+            {
+                self.returning(move |#(#argnames, )*| Box::pin(
+                    ::mockall::PendingThenReady::<_, #fut_output>::new(n,
+                        __mockall_c.clone())
+                ))
+            }
+
+            /// Supply a closure that returns a future, such as an `async`
+            /// block, to provide the return value for this `Expectation`.
+            /// The method's arguments are passed to the closure by value.
+            #v fn returning_async<MockallF, MockallFut>(&mut self,
+                mut __mockall_f: MockallF) -> &mut Self
+                where MockallF: #hrtb FnMut(#(#argty, )*) -> MockallFut
+                                + Send + 'static,
+                      MockallFut: ::std::future::Future<Output=#fut_output>
+                                  #bounds + 'static
+            {
+                self.returning(move |#(#argnames, )*|
+                    Box::pin(__mockall_f(#(#argnames, )*))
+                )
+            }
+        )
+    }
+
+    /// Forward `future_methods` from an `ExpectationGuard`
+    ///
+    /// # Arguments
+    ///
+    /// * `expectation`:    Expression for the guard's `&mut Expectation`
+    /// * `tg`:             Type generics of the `Expectation`
+    fn future_guard_methods(&self, expectation: &TokenStream,
+                            tg: &TypeGenerics) -> TokenStream
+    {
+        let v = &self.common.vis;
+        let must_be_polled = quote!(
+            /// Just like
+            /// [`Expectation::must_be_polled`](struct.Expectation.html#method.must_be_polled)
+            #v fn must_be_polled(&mut self) -> &mut Expectation #tg {
+                #expectation.must_be_polled()
+            }
+        );
+        let (fut_output, bounds) = match self.future_types() {
+            Some(x) => x,
+            None if self.common.is_async => return must_be_polled,
+            None => return TokenStream::new()
+        };
+        let argty = &self.common.argty;
+        let hrtb = self.common.hrtb();
+        quote!(
+            #must_be_polled
+
+            /// Just like
+            /// [`Expectation::return_ready`](struct.Expectation.html#method.return_ready)
+            #v fn return_ready<MockallOutput>(&mut self,
+                __mockall_c: MockallOutput) -> &mut Expectation #tg
+                where MockallOutput: Clone + Into<#fut_output> + Send
+                                     #bounds + 'static
+            {
+                #expectation.return_ready(__mockall_c)
+            }
+
+            /// Just like
+            /// [`Expectation::return_after_pending`](struct.Expectation.html#method.return_after_pending)
+            #v fn return_after_pending<MockallOutput>(&mut self, n: usize,
+                __mockall_c: MockallOutput) -> &mut Expectation #tg
+                where MockallOutput: Clone + Into<#fut_output> + Send
+                                     #bounds + 'static
+            {
+                #expectation.return_after_pending(n, __mockall_c)
+            }
+
+            /// Just like
+            /// [`Expectation::returning_async`](struct.Expectation.html#method.returning_async)
+            #v fn returning_async<MockallF, MockallFut>(&mut self,
+                __mockall_f: MockallF) -> &mut Expectation #tg
+                where MockallF: #hrtb FnMut(#(#argty, )*) -> MockallFut
+                                + Send + 'static,
+                      MockallFut: ::std::future::Future<Output=#fut_output>
+                                  #bounds + 'static
+            {
+                #expectation.returning_async(__mockall_f)
+            }
+        )
+    }
+
+    /// Methods for Expectations whose methods return boxed iterators, like
+    /// methods that return `impl Iterator`.  Returns an empty TokenStream for
+    /// other methods.
+    fn iterator_methods(&self) -> TokenStream {
+        let (item, bounds) = match self.iterator_types() {
+            Some(x) => x,
+            None => return TokenStream::new()
+        };
+        let argnames = &self.common.argnames;
+        let v = &self.common.vis;
+        quote!(
+            /// Return an iterator over a clone of `items` each time the method
+            /// is called.  This is more convenient than building an iterator
+            /// in [`returning`](#method.returning).
+            #[allow(unused_variables)]
+            #v fn return_iter<MockallI>(&mut self, __mockall_items: MockallI)
+                -> &mut Self
+                where MockallI: IntoIterator<Item=#item> + Clone + Send
+                                + 'static,
+                      <MockallI as IntoIterator>::IntoIter: 'static #bounds
+            {
+                self.returning(move |#(#argnames, )*|
+                    Box::new(__mockall_items.clone().into_iter())
+                )
+            }
+        )
+    }
+
+    /// Methods for Expectations whose methods return boxed streams, like
+    /// methods that return `impl Stream`.  Returns an empty TokenStream for
+    /// other methods, or if the "stream" feature isn't enabled.
+    fn stream_methods(&self) -> TokenStream {
+        let (item, bounds) = match self.stream_types() {
+            Some(x) => x,
+            None => return TokenStream::new()
+        };
+        let argnames = &self.common.argnames;
+        let v = &self.common.vis;
+        quote!(
+            /// Return a stream that yields a clone of `items` each time the
+            /// method is called.
+            #[allow(unused_variables)]
+            #v fn return_stream<MockallI>(&mut self, __mockall_items: MockallI)
+                -> &mut Self
+                where MockallI: IntoIterator<Item=#item> + Clone + Send
+                                + 'static,
+                      <MockallI as IntoIterator>::IntoIter: 'static #bounds
+            {
+                let __mockall_counts = self.pull_tracker.counts();
+                self.returning(move |#(#argnames, )*|
+                    Box::pin(__mockall_counts.stream(__mockall_items.clone()))
+                )
+            }
+
+            /// Require the consumer to pull a certain number of items from
+            /// each stream created by
+            /// [`return_stream`](#method.return_stream).  It will be checked
+            /// when the `Expectation` is checkpointed or dropped.
+            #v fn items_pulled<MockallR>(&mut self, __mockall_r: MockallR)
+                -> &mut Self
+                where MockallR: Into<::mockall::TimesRange>
+            {
+                self.pull_tracker.expect(__mockall_r);
+                self
+            }
         )
     }
 
+    /// Forward all of the output-specific methods, like `return_ready`, from
+    /// an `ExpectationGuard`.
+    ///
+    /// # Arguments
+    ///
+    /// * `expectation`:    Expression for the guard's `&mut Expectation`
+    /// * `tg`:             Type generics of the `Expectation`
+    fn output_guard_methods(&self, expectation: &TokenStream,
+                            tg: &TypeGenerics) -> TokenStream
+    {
+        let mut ts = self.future_guard_methods(expectation, tg);
+        let v = &self.common.vis;
+        if let Some((item, bounds)) = self.iterator_types() {
+            quote!(
+                /// Just like
+                /// [`Expectation::return_iter`](struct.Expectation.html#method.return_iter)
+                #v fn return_iter<MockallI>(&mut self,
+                    __mockall_items: MockallI) -> &mut Expectation #tg
+                    where MockallI: IntoIterator<Item=#item> + Clone + Send
+                                    + 'static,
+                          <MockallI as IntoIterator>::IntoIter: 'static #bounds
+                {
+                    #expectation.return_iter(__mockall_items)
+                }
+            ).to_tokens(&mut ts);
+        }
+        if let Some((item, bounds)) = self.stream_types() {
+            quote!(
+                /// Just like
+                /// [`Expectation::return_stream`](struct.Expectation.html#method.return_stream)
+                #v fn return_stream<MockallI>(&mut self,
+                    __mockall_items: MockallI) -> &mut Expectation #tg
+                    where MockallI: IntoIterator<Item=#item> + Clone + Send
+                                    + 'static,
+                          <MockallI as IntoIterator>::IntoIter: 'static #bounds
+                {
+                    #expectation.return_stream(__mockall_items)
+                }
+
+                /// Just like
+                /// [`Expectation::items_pulled`](struct.Expectation.html#method.items_pulled)
+                #v fn items_pulled<MockallR>(&mut self, __mockall_r: MockallR)
+                    -> &mut Expectation #tg
+                    where MockallR: Into<::mockall::TimesRange>
+                {
+                    #expectation.items_pulled(__mockall_r)
+                }
+            ).to_tokens(&mut ts);
+        }
+        ts
+    }
+
+    /// If the method returns a boxed trait object like `Box<dyn Iterator<Item =
+    /// T> + Send>`, return the associated type `T` and any additional bounds of
+    /// the trait object, like `+ Send`.  If `pinned`, then the box must be
+    /// pinned, like `Pin<Box<dyn Future<Output = T>>>`.
+    fn boxed_types(&self, trait_: &str, assoc: &str, pinned: bool)
+        -> Option<(&Type, TokenStream)>
+    {
+        let output = &self.common.output;
+        let bounds = boxed_bounds(output, pinned)?;
+        let assoc_ty = assoc_binding(bounds, trait_, assoc)?;
+        let mut extra = TokenStream::new();
+        for b in bounds.iter() {
+            if let TypeParamBound::Trait(tb) = b {
+                if tb.path.segments.last().unwrap().ident != trait_ {
+                    quote!(+ #tb).to_tokens(&mut extra);
+                }
+            }
+        }
+        Some((assoc_ty, extra))
+    }
+
+    /// If the method returns a boxed future, return the future's output type
+    /// and any additional bounds of its trait object.
+    fn future_types(&self) -> Option<(&Type, TokenStream)> {
+        self.boxed_types("Future", "Output", true)
+    }
+
+    /// If the method returns a boxed iterator, return its item type and any
+    /// additional bounds of its trait object.
+    fn iterator_types(&self) -> Option<(&Type, TokenStream)> {
+        self.boxed_types("Iterator", "Item", false)
+    }
+
+    /// If the method returns a boxed stream, return its item type and any
+    /// additional bounds of its trait object.  Streams are only supported with
+    /// the "stream" feature.
+    fn stream_types(&self) -> Option<(&Type, TokenStream)> {
+        if cfg!(feature = "stream_derive") {
+            self.boxed_types("Stream", "Item", true)
+        } else {
+            None
+        }
+    }
+
     fn expectations_methods(&self) -> TokenStream {
         let argnames = &self.common.argnames;
         let argty = &self.common.argty;
@@ -910,16 +1631,35 @@ impl<'a> StaticExpectation<'a> {
                                       &self.common.rlifetimes);
         let (ig, tg, wc) = generics.split_for_impl();
         let lg = &self.common.alifetimes;
-        let output = &self.common.output;
+        let call_output = self.common.call_output();
         let predexprs = &self.common.predexprs;
+        let predty = &self.common.predty;
         let v = &self.common.vis;
+        if self.common.is_static {
+            return quote!(
+                impl #ig Expectations #tg #wc {
+                    /// Find the expectation that should handle a call.  Every
+                    /// current expectation will be checked in FIFO order and
+                    /// the first one with matching arguments will be used.
+                    #v fn find #lg (&self, #(#argnames: &#predty, )* )
+                        -> Option<::std::sync::Arc<Expectation #tg>>
+                    {
+                        self.0.iter()
+                            .find(|__mockall_e|
+                                  __mockall_e.matches(#(#argnames, )*) &&
+                                  (!__mockall_e.is_done() || self.0.len() == 1))
+                            .cloned()
+                    }
+                }
+            );
+        }
         quote!(
             impl #ig Expectations #tg #wc {
                 /// Simulate calling the real method.  Every current expectation
                 /// will be checked in FIFO order and the first one with
                 /// matching arguments will be used.
                 #v fn call #lg (&self, #(#argnames: #argty, )* )
-                    -> Option<#output>
+                    -> Option<#call_output>
                 {
                     self.0.iter()
                         .find(|__mockall_e|
@@ -945,33 +1685,66 @@ impl<'a> StaticExpectation<'a> {
     fn generic_expectations_methods(&self) -> TokenStream {
         let argnames = &self.common.argnames;
         let argty = &self.common.argty;
+        let key = self.common.key();
         let generics = merge_generics(&self.common.egenerics,
                                       &self.common.rlifetimes);
         let (ig, tg, wc) = generics.split_for_impl();
-        let output = &self.common.output;
+        // The call methods must also be generic over the arguments' lifetimes
+        let call_generics = merge_generics(&self.common.alifetimes, &generics);
+        let (call_ig, _, _) = call_generics.split_for_impl();
+        let call_output = self.common.call_output();
         let tbf = tg.as_turbofish();
         let v = &self.common.vis;
-        quote!(
-            impl #ig ::mockall::AnyExpectations for Expectations #tg #wc {}
-            impl GenericExpectations {
-                /// Simulating calling the real method.
-                #v fn call #ig (&self, #(#argnames: #argty, )* )
-                    -> Option<#output> #wc
+        let call = if self.common.is_static {
+            let predty = &self.common.predty;
+            quote!(
+                /// Find the expectation that should handle a call.
+                #v fn find #call_ig (&self, #(#argnames: &#predty, )* )
+                    -> Option<::std::sync::Arc<Expectation #tg>> #wc
                 {
-                    self.store.get(&::mockall::Key::new::<(#(#argty, )*)>())
-                        .map(|__mockall_e| {
+                    self.store.get(&#key)
+                        .and_then(|__mockall_e| {
                             __mockall_e.downcast_ref::<Expectations #tg>()
                             .unwrap()
-                            .call(#(#argnames, )*)
+                            .find(#(#argnames, )*)
+                        })
+                }
+            )
+        } else {
+            quote!(
+                /// Simulating calling the real method.
+                #v fn call #call_ig (&self, #(#argnames: #argty, )* )
+                    -> Option<#call_output> #wc
+                {
+                    self.store.get(&#key)
+                        .map(|__mockall_e| {
+                            __mockall_e.downcast_ref::<Expectations #tg>()
+                            .unwrap()
+                            .call(#(#argnames, )*)
                         // TODO: use Option::flatten once it stabilizes
                         // https://github.com/rust-lang/rust/issues/60258
                         }).and_then(std::convert::identity)
                 }
+            )
+        };
+        quote!(
+            impl #ig ::mockall::AnyExpectations for Expectations #tg #wc
+            {
+                fn wait_until_satisfied(&self,
+                    __mockall_deadline: ::std::time::Instant)
+                    -> ::std::result::Result<(), ::mockall::Timeout>
+                {
+                    Expectations::wait_until_satisfied(self, __mockall_deadline)
+                }
+            }
+            impl GenericExpectations {
+                #call
+
 
                 /// Create a new Expectation.
                 #v fn expect #ig (&mut self) -> &mut Expectation #tg #wc
                 {
-                    self.store.entry(::mockall::Key::new::<(#(#argty, )*)>())
+                    self.store.entry(#key)
                         .or_insert_with(|| Box::new(Expectations #tbf::new()))
                         .downcast_mut::<Expectations #tg>()
                         .unwrap()
@@ -1071,14 +1844,68 @@ impl<'a> StaticExpectation<'a> {
 
         let argnames = &self.common.argnames;
         let argty = &self.common.argty;
+        let key = self.common.key();
         let expect_obj = &self.common.expect_obj;
         let fn_params = &self.common.fn_params;
         let (_ig, tg, _wc) = self.common.egenerics.split_for_impl();
         let hrtb = self.common.hrtb();
         let output = &self.common.output;
+        let call_output = self.common.call_output();
         let predty = &self.common.predty;
         let tbf = tg.as_turbofish();
         let v = &self.common.vis;
+        let guard_expectation = quote!(self.guard.expectation_mut(self.i));
+        let future_guard_methods = self.output_guard_methods(
+            &guard_expectation, &tg);
+        let generic_guard_expectation = quote!(self.guard.store.get_mut(
+                &#key
+            ).unwrap()
+            .downcast_mut::<Expectations #tg>()
+            .unwrap()
+            .expectation_mut(self.i));
+        let generic_future_guard_methods = self.output_guard_methods(
+            &generic_guard_expectation, &tg);
+
+        // Release the lock on EXPECTATIONS before calling the expectation, so
+        // its returning closure may call this method again.
+        let call_ts = {
+            let generics = merge_generics(&self.common.alifetimes,
+                &merge_generics(&self.common.egenerics,
+                                &self.common.rlifetimes));
+            let (c_ig, _, c_wc) = generics.split_for_impl();
+            let predexprs = &self.common.predexprs;
+            let (cg, find_tbf) = if self.common.is_generic() {
+                let tps = self.common.egenerics.params.iter()
+                    .filter_map(|p| match p {
+                        GenericParam::Type(tp) => Some(&tp.ident),
+                        GenericParam::Const(cp) => Some(&cp.ident),
+                        GenericParam::Lifetime(_) => None
+                    });
+                (quote!(#c_ig), quote!(::<#(#tps),*>))
+            } else {
+                let lg = &self.common.alifetimes;
+                (quote!(#lg), TokenStream::new())
+            };
+            quote!(
+                /// Simulate calling the real method.
+                #[doc(hidden)]
+                #v fn call #cg (#(#argnames: #argty, )* )
+                    -> Option<#call_output> #c_wc
+                {
+                    let __mockall_e = EXPECTATIONS.lock().unwrap()
+                        .find #find_tbf (#(#predexprs, )*);
+                    __mockall_e.map(move |__mockall_e|
+                        __mockall_e.call(#(#argnames, )*)
+                    )
+                }
+
+                /// Failures recorded while calling this method
+                #[doc(hidden)]
+                #v fn failures() -> ::mockall::Failures {
+                    EXPECTATIONS.lock().unwrap().failures()
+                }
+            )
+        };
 
         let gd = Generics::default();
         let (s_ig, s_tg, s_wc) = self.common.struct_generics
@@ -1140,11 +1967,13 @@ impl<'a> StaticExpectation<'a> {
                 }
                 #[doc(hidden)]
                 #v fn do_checkpoint() {
+                    let __mockall_failures = failures();
                     let __mockall_timeses = EXPECTATIONS
                         .lock()
                         .unwrap()
                         .checkpoint()
                         .collect::<Vec<_>>();
+                    __mockall_failures.check();
                 }
 
                 /// Create a new expectation for this method.
@@ -1157,6 +1986,7 @@ impl<'a> StaticExpectation<'a> {
             }
             impl #s_ig Default for Context #s_tg #s_wc {
                 fn default() -> Self {
+                    EXPECTATIONS.lock().unwrap().reset_failures();
                     Context {_phantom: std::marker::PhantomData}
                 }
             }
@@ -1176,6 +2006,7 @@ impl<'a> StaticExpectation<'a> {
                         ::std::sync::Mutex<#expect_obj> =
                         ::std::sync::Mutex::new(Expectations::new());
                 }
+                #call_ts
                 /// Like an [`&Expectation`](struct.Expectation.html) but
                 /// protected by a Mutex guard.  Useful for mocking static
                 /// methods.  Forwards accesses to an `Expectation` object.
@@ -1202,13 +2033,52 @@ impl<'a> StaticExpectation<'a> {
                         __mockall_seq: &mut ::mockall::Sequence)
                         -> &mut Expectation #tg
                     {
-                        self.guard.0[self.i].in_sequence(__mockall_seq)
+                        self.guard.expectation_mut(self.i).in_sequence(__mockall_seq)
+                    }
+
+                    /// Just like
+                    /// [`Expectation::blocking_on`](struct.Expectation.html#method.blocking_on)
+                    #v fn blocking_on(&mut self,
+                        __mockall_latch: &::mockall::Latch)
+                        -> &mut Expectation #tg
+                    {
+                        self.guard.expectation_mut(self.i).blocking_on(__mockall_latch)
+                    }
+
+                    /// Just like
+                    /// [`Expectation::exclusive`](struct.Expectation.html#method.exclusive)
+                    #v fn exclusive(&mut self) -> &mut Expectation #tg {
+                        self.guard.expectation_mut(self.i).exclusive()
+                    }
+
+                    /// Just like
+                    /// [`Expectation::not_on_thread`](struct.Expectation.html#method.not_on_thread)
+                    #v fn not_on_thread(&mut self,
+                        __mockall_id: ::std::thread::ThreadId)
+                        -> &mut Expectation #tg
+                    {
+                        self.guard.expectation_mut(self.i).not_on_thread(__mockall_id)
+                    }
+
+                    /// Just like
+                    /// [`Expectation::on_thread`](struct.Expectation.html#method.on_thread)
+                    #v fn on_thread(&mut self,
+                        __mockall_id: ::std::thread::ThreadId)
+                        -> &mut Expectation #tg
+                    {
+                        self.guard.expectation_mut(self.i).on_thread(__mockall_id)
+                    }
+
+                    /// Just like
+                    /// [`Expectation::handle`](struct.Expectation.html#method.handle)
+                    #v fn handle(&self) -> ::mockall::ExpectationHandle {
+                        self.guard.0[self.i].handle()
                     }
 
                     /// Just like
                     /// [`Expectation::never`](struct.Expectation.html#method.never)
                     #v fn never(&mut self) -> &mut Expectation #tg {
-                        self.guard.0[self.i].never()
+                        self.guard.expectation_mut(self.i).never()
                     }
 
                     // Should only be called from the mockall_derive generated
@@ -1224,7 +2094,7 @@ impl<'a> StaticExpectation<'a> {
 
                     /// Just like [`Expectation::once`](struct.Expectation.html#method.once)
                     #v fn once(&mut self) -> &mut Expectation #tg {
-                        self.guard.0[self.i].once()
+                        self.guard.expectation_mut(self.i).once()
                     }
 
                     /// Just like
@@ -1234,7 +2104,7 @@ impl<'a> StaticExpectation<'a> {
                         -> &mut Expectation #tg
                         where MockallOutput: Clone + Into<#output> + Send + 'static
                     {
-                        self.guard.0[self.i].return_const(__mockall_c)
+                        self.guard.expectation_mut(self.i).return_const(__mockall_c)
                     }
 
                     /// Just like
@@ -1244,7 +2114,7 @@ impl<'a> StaticExpectation<'a> {
                         where MockallF: #hrtb FnMut(#(#argty, )*)
                             -> #output + Send + 'static
                     {
-                        self.guard.0[self.i].returning(__mockall_f)
+                        self.guard.expectation_mut(self.i).returning(__mockall_f)
                     }
 
                     /// Just like
@@ -1254,7 +2124,7 @@ impl<'a> StaticExpectation<'a> {
                         where MockallF: #hrtb FnOnce(#(#argty, )*)
                                         -> #output + Send + 'static
                     {
-                        self.guard.0[self.i].return_once(__mockall_f)
+                        self.guard.expectation_mut(self.i).return_once(__mockall_f)
                     }
 
                     /// Just like
@@ -1264,23 +2134,25 @@ impl<'a> StaticExpectation<'a> {
                         where MockallF: #hrtb FnMut(#(#argty, )*)
                                         -> #output + 'static
                     {
-                        self.guard.0[self.i].returning_st(__mockall_f)
+                        self.guard.expectation_mut(self.i).returning_st(__mockall_f)
                     }
 
+                    #future_guard_methods
+
                     /// Just like
                     /// [`Expectation::times`](struct.Expectation.html#method.times)
                     #v fn times<MockallR>(&mut self, __mockall_r: MockallR)
                         -> &mut Expectation #tg
                         where MockallR: Into<::mockall::TimesRange>
                     {
-                        self.guard.0[self.i].times(__mockall_r)
+                        self.guard.expectation_mut(self.i).times(__mockall_r)
                     }
 
                     /// Just like
                     /// [`Expectation::times_any`](struct.Expectation.html#method.times_any)
                     #[deprecated(since = "0.3.0", note = "Use times instead")]
                     #v fn times_any(&mut self) -> &mut Expectation #tg {
-                        self.guard.0[self.i].times(..)
+                        self.guard.expectation_mut(self.i).times(..)
                     }
 
                     /// Just like
@@ -1289,7 +2161,7 @@ impl<'a> StaticExpectation<'a> {
                     #v fn times_range(&mut self, __mockall_range: Range<usize>)
                         -> &mut Expectation #tg
                     {
-                        self.guard.0[self.i].times(__mockall_range)
+                        self.guard.expectation_mut(self.i).times(__mockall_range)
                     }
 
                     /// Just like
@@ -1297,7 +2169,7 @@ impl<'a> StaticExpectation<'a> {
                     #v fn with<#with_generics> (&mut self, #with_args)
                         -> &mut Expectation #tg
                     {
-                        self.guard.0[self.i].with(#(#argnames, )*)
+                        self.guard.expectation_mut(self.i).with(#(#argnames, )*)
                     }
 
                     /// Just like
@@ -1307,7 +2179,7 @@ impl<'a> StaticExpectation<'a> {
                         where MockallF: #hrtb Fn(#(&#predty, )*)
                                         -> bool + Send + 'static
                     {
-                        self.guard.0[self.i].withf(__mockall_f)
+                        self.guard.expectation_mut(self.i).withf(__mockall_f)
                     }
 
                     /// Just like
@@ -1317,7 +2189,7 @@ impl<'a> StaticExpectation<'a> {
                         where MockallF: #hrtb Fn(#(&#predty, )*)
                                         -> bool + 'static
                     {
-                        self.guard.0[self.i].withf_st(__mockall_f)
+                        self.guard.expectation_mut(self.i).withf_st(__mockall_f)
                     }
                 }
                 #context_ts
@@ -1329,6 +2201,7 @@ impl<'a> StaticExpectation<'a> {
                         ::std::sync::Mutex<GenericExpectations> =
                         ::std::sync::Mutex::new(GenericExpectations::new());
                 }
+                #call_ts
                 /// Like an
                 /// [`&Expectation`](struct.Expectation.html) but
                 /// protected by a Mutex guard.  Useful for mocking static
@@ -1348,23 +2221,92 @@ impl<'a> StaticExpectation<'a> {
                         -> &mut Expectation #tg
                     {
                         self.guard.store.get_mut(
-                                &::mockall::Key::new::<(#(#argty, )*)>()
+                                &#key
                             ).unwrap()
                             .downcast_mut::<Expectations #tg>()
                             .unwrap()
-                            .0[self.i]
+                            .expectation_mut(self.i)
                             .in_sequence(__mockall_seq)
                     }
 
+                    /// Just like
+                    /// [`Expectation::blocking_on`](struct.Expectation.html#method.blocking_on)
+                    #v fn blocking_on(&mut self,
+                        __mockall_latch: &::mockall::Latch)
+                        -> &mut Expectation #tg
+                    {
+                        self.guard.store.get_mut(
+                                &#key
+                            ).unwrap()
+                            .downcast_mut::<Expectations #tg>()
+                            .unwrap()
+                            .expectation_mut(self.i)
+                            .blocking_on(__mockall_latch)
+                    }
+
+                    /// Just like
+                    /// [`Expectation::exclusive`](struct.Expectation.html#method.exclusive)
+                    #v fn exclusive(&mut self) -> &mut Expectation #tg {
+                        self.guard.store.get_mut(
+                                &#key
+                            ).unwrap()
+                            .downcast_mut::<Expectations #tg>()
+                            .unwrap()
+                            .expectation_mut(self.i)
+                            .exclusive()
+                    }
+
+                    /// Just like
+                    /// [`Expectation::not_on_thread`](struct.Expectation.html#method.not_on_thread)
+                    #v fn not_on_thread(&mut self,
+                        __mockall_id: ::std::thread::ThreadId)
+                        -> &mut Expectation #tg
+                    {
+                        self.guard.store.get_mut(
+                                &#key
+                            ).unwrap()
+                            .downcast_mut::<Expectations #tg>()
+                            .unwrap()
+                            .expectation_mut(self.i)
+                            .not_on_thread(__mockall_id)
+                    }
+
+                    /// Just like
+                    /// [`Expectation::on_thread`](struct.Expectation.html#method.on_thread)
+                    #v fn on_thread(&mut self,
+                        __mockall_id: ::std::thread::ThreadId)
+                        -> &mut Expectation #tg
+                    {
+                        self.guard.store.get_mut(
+                                &#key
+                            ).unwrap()
+                            .downcast_mut::<Expectations #tg>()
+                            .unwrap()
+                            .expectation_mut(self.i)
+                            .on_thread(__mockall_id)
+                    }
+
+                    /// Just like
+                    /// [`Expectation::handle`](struct.Expectation.html#method.handle)
+                    #v fn handle(&self) -> ::mockall::ExpectationHandle {
+                        self.guard.store.get(
+                                &#key
+                            ).unwrap()
+                            .downcast_ref::<Expectations #tg>()
+                            .unwrap()
+                            .0[self.i]
+                            .handle()
+                    }
+
                     /// Just like
                     /// [`Expectation::never`](struct.Expectation.html#method.never)
                     #v fn never(&mut self) -> &mut Expectation #tg {
                             self.guard.store.get_mut(
-                                &::mockall::Key::new::<(#(#argty, )*)>()
+                                &#key
                             ).unwrap()
                             .downcast_mut::<Expectations #tg>()
                             .unwrap()
-                            .0[self.i]
+                            .expectation_mut(self.i)
                             .never()
                     }
 
@@ -1374,7 +2316,7 @@ impl<'a> StaticExpectation<'a> {
                     {
                         let __mockall_ee: &mut Expectations #tg =
                             guard.store.entry(
-                                ::mockall::Key::new::<(#(#argty, )*)>()
+                                #key
                             ).or_insert_with(||
                                 Box::new(Expectations #tbf ::new()))
                             .downcast_mut()
@@ -1389,11 +2331,11 @@ impl<'a> StaticExpectation<'a> {
                     /// [`Expectation::once`](struct.Expectation.html#method.once)
                     #v fn once(&mut self) -> &mut Expectation #tg {
                         self.guard.store.get_mut(
-                                &::mockall::Key::new::<(#(#argty, )*)>()
+                                &#key
                             ).unwrap()
                             .downcast_mut::<Expectations #tg>()
                             .unwrap()
-                            .0[self.i]
+                            .expectation_mut(self.i)
                             .once()
                     }
 
@@ -1401,15 +2343,15 @@ impl<'a> StaticExpectation<'a> {
                     /// [`Expectation::returning`](struct.Expectation.html#method.returning)
                     #v fn returning<MockallF>(&mut self, __mockall_f: MockallF)
                         -> &mut Expectation #tg
-                        where MockallF: FnMut(#(#argty, )*)
+                        where MockallF: #hrtb FnMut(#(#argty, )*)
                             -> #output + Send + 'static
                     {
                         self.guard.store.get_mut(
-                                &::mockall::Key::new::<(#(#argty, )*)>()
+                                &#key
                             ).unwrap()
                             .downcast_mut::<Expectations #tg>()
                             .unwrap()
-                            .0[self.i]
+                            .expectation_mut(self.i)
                             .returning(__mockall_f)
                     }
 
@@ -1417,15 +2359,15 @@ impl<'a> StaticExpectation<'a> {
                     /// [`Expectation::return_once`](struct.Expectation.html#method.return_once)
                     #v fn return_once<MockallF>(&mut self,
                         __mockall_f: MockallF) -> &mut Expectation #tg
-                        where MockallF: FnOnce(#(#argty, )*)
+                        where MockallF: #hrtb FnOnce(#(#argty, )*)
                             -> #output + Send + 'static
                     {
                         self.guard.store.get_mut(
-                                &::mockall::Key::new::<(#(#argty, )*)>()
+                                &#key
                             ).unwrap()
                             .downcast_mut::<Expectations #tg>()
                             .unwrap()
-                            .0[self.i]
+                            .expectation_mut(self.i)
                             .return_once(__mockall_f)
                     }
 
@@ -1433,17 +2375,19 @@ impl<'a> StaticExpectation<'a> {
                     /// [`Expectation::returning_st`](struct.Expectation.html#method.returning_st)
                     #v fn returning_st<MockallF>(&mut self,
                         __mockall_f: MockallF) -> &mut Expectation #tg
-                        where MockallF: FnMut(#(#argty, )*) -> #output + 'static
+                        where MockallF: #hrtb FnMut(#(#argty, )*) -> #output + 'static
                     {
                         self.guard.store.get_mut(
-                                &::mockall::Key::new::<(#(#argty, )*)>()
+                                &#key
                             ).unwrap()
                             .downcast_mut::<Expectations #tg>()
                             .unwrap()
-                            .0[self.i]
+                            .expectation_mut(self.i)
                             .returning_st(__mockall_f)
                     }
 
+                    #generic_future_guard_methods
+
                     /// Just like
                     /// [`Expectation::times`](struct.Expectation.html#method.times)
                     #v fn times<MockallR>(&mut self, __mockall_r: MockallR)
@@ -1451,11 +2395,11 @@ impl<'a> StaticExpectation<'a> {
                         where MockallR: Into<::mockall::TimesRange>
                     {
                         self.guard.store.get_mut(
-                                &::mockall::Key::new::<(#(#argty, )*)>()
+                                &#key
                             ).unwrap()
                             .downcast_mut::<Expectations #tg>()
                             .unwrap()
-                            .0[self.i]
+                            .expectation_mut(self.i)
                             .times(__mockall_r)
                     }
 
@@ -1464,11 +2408,11 @@ impl<'a> StaticExpectation<'a> {
                     #[deprecated(since = "0.3.0", note = "Use times instead")]
                     #v fn times_any(&mut self) -> &mut Expectation #tg {
                         self.guard.store.get_mut(
-                                &::mockall::Key::new::<(#(#argty, )*)>()
+                                &#key
                             ).unwrap()
                             .downcast_mut::<Expectations #tg>()
                             .unwrap()
-                            .0[self.i]
+                            .expectation_mut(self.i)
                             .times(..)
                     }
 
@@ -1479,11 +2423,11 @@ impl<'a> StaticExpectation<'a> {
                         -> &mut Expectation #tg
                     {
                         self.guard.store.get_mut(
-                                &::mockall::Key::new::<(#(#argty, )*)>()
+                                &#key
                             ).unwrap()
                             .downcast_mut::<Expectations #tg>()
                             .unwrap()
-                            .0[self.i]
+                            .expectation_mut(self.i)
                             .times(__mockall_range)
                     }
 
@@ -1493,11 +2437,11 @@ impl<'a> StaticExpectation<'a> {
                         -> &mut Expectation #tg
                     {
                         self.guard.store.get_mut(
-                                &::mockall::Key::new::<(#(#argty, )*)>()
+                                &#key
                             ).unwrap()
                             .downcast_mut::<Expectations #tg>()
                             .unwrap()
-                            .0[self.i]
+                            .expectation_mut(self.i)
                             .with(#(#argnames, )*)
                     }
 
@@ -1508,11 +2452,11 @@ impl<'a> StaticExpectation<'a> {
                                         -> bool + Send + 'static
                     {
                         self.guard.store.get_mut(
-                                &::mockall::Key::new::<(#(#argty, )*)>()
+                                &#key
                             ).unwrap()
                             .downcast_mut::<Expectations #tg>()
                             .unwrap()
-                            .0[self.i]
+                            .expectation_mut(self.i)
                             .withf(__mockall_f)
                     }
 
@@ -1523,11 +2467,11 @@ impl<'a> StaticExpectation<'a> {
                                         -> bool + 'static
                     {
                         self.guard.store.get_mut(
-                                &::mockall::Key::new::<(#(#argty, )*)>()
+                                &#key
                             ).unwrap()
                             .downcast_mut::<Expectations #tg>()
                             .unwrap()
-                            .0[self.i]
+                            .expectation_mut(self.i)
                             .withf_st(__mockall_f)
                     }
                 }
@@ -1545,9 +2489,12 @@ pub(crate) struct RefExpectation<'a> {
 impl<'a> RefExpectation<'a> {
     fn common(&self) -> &Common {&self.common}
     fn expectation(&self, em_ts: TokenStream) -> TokenStream {
+        let argnames = &self.common.argnames;
+        let argty = &self.common.argty;
         let ident_str = self.common().ident_str();
         let (ig, tg, _wc) = self.common.egenerics.split_for_impl();
         let lg = &self.common.alifetimes;
+        let lts = lg.lifetimes();
         let output = &self.common.output;
         let v = &self.common.vis;
         quote!(
@@ -1560,14 +2507,18 @@ impl<'a> RefExpectation<'a> {
             }
 
             impl #ig Expectation #tg {
-                #v fn call #lg (&self) -> &#output {
-                    self.common.call();
-                    self.rfunc.call().unwrap_or_else(|m| {
+                #v fn call #lg (&self, #(#argnames: #argty, )*) -> &#output {
+                    let __mockall_flight = self.common.call();
+                    let __mockall_r = self.rfunc.call(#(#argnames, )*)
+                        .unwrap_or_else(|m|
+                    {
                         let desc = format!("{}",
                                            self.common.matcher.lock().unwrap());
-                        panic!("{}: Expectation({}) {}", #ident_str, desc,
-                            m);
-                    })
+                        ::mockall::violation(format!(
+                            "{}: Expectation({}) {}", #ident_str, desc, m));
+                    });
+                    self.common.exit(__mockall_flight);
+                    __mockall_r
                 }
 
                 /// Return a reference to a constant value from the `Expectation`
@@ -1578,6 +2529,25 @@ impl<'a> RefExpectation<'a> {
                     self
                 }
 
+                /// Return a reference computed from the arguments.  The closure
+                /// receives `storage`, which the `Expectation` owns, so it may
+                /// return a reference into it, like an entry of a map.
+                #v fn returning_from<MockallS, MockallF>(&mut self,
+                    __mockall_s: MockallS, __mockall_f: MockallF) -> &mut Self
+                    where MockallS: Send + Sync + 'static,
+                          MockallF: for<'__mockall_s, #(#lts),*>
+                                    Fn(&'__mockall_s MockallS, #(#argty, )*)
+                                    -> &'__mockall_s #output
+                                    + Send + Sync + 'static
+                {
+                    self.rfunc = Rfunc::Func(Box::new(__mockall_s),
+                        Box::new(move |__mockall_s, #(#argnames, )*|
+                            __mockall_f(__mockall_s.downcast_ref().unwrap(),
+                                        #(#argnames, )*)
+                        ));
+                    self
+                }
+
                 #em_ts
             }
 
@@ -1614,7 +2584,7 @@ impl<'a> RefExpectation<'a> {
                               __mockall_e.matches(#(#predexprs, )*) &&
                               (!__mockall_e.is_done() || self.0.len() == 1))
                         .map(move |__mockall_e|
-                             __mockall_e.call()
+                             __mockall_e.call(#(#argnames, )*)
                         )
                 }
             }
@@ -1626,7 +2596,11 @@ impl<'a> RefExpectation<'a> {
     fn generic_expectations_methods(&self) -> TokenStream {
         let argnames = &self.common.argnames;
         let argty = &self.common.argty;
-        let (ig, tg, wc) = self.common.egenerics.split_for_impl();
+        let key = self.common.key();
+        let (ig, tg, _wc) = self.common.egenerics.split_for_impl();
+        let call_generics = self.common.call_generics();
+        let (call_ig, _, call_wc) = call_generics.split_for_impl();
+        let sync_wc = self.common.sync_where_clause();
         let output = &self.common.output;
         let tbf = tg.as_turbofish();
         let v = &self.common.vis;
@@ -1634,15 +2608,21 @@ impl<'a> RefExpectation<'a> {
         quote!(
             // The Senc + Sync are required for downcast, since Expectation
             // stores an Option<#output>
-            impl #ig ::mockall::AnyExpectations for Expectations #tg
-                    where #output: Send + Sync
-            {}
+            impl #ig ::mockall::AnyExpectations for Expectations #tg #sync_wc
+            {
+                fn wait_until_satisfied(&self,
+                    __mockall_deadline: ::std::time::Instant)
+                    -> ::std::result::Result<(), ::mockall::Timeout>
+                {
+                    Expectations::wait_until_satisfied(self, __mockall_deadline)
+                }
+            }
             impl GenericExpectations {
                 /// Simulating calling the real method.
-                #v fn call #ig (&self, #(#argnames: #argty,)*)
-                    -> Option<&#output>
+                #v fn call #call_ig (&self, #(#argnames: #argty,)*)
+                    -> Option<&#output> #call_wc
                 {
-                    self.store.get(&::mockall::Key::new::<(#(#argty, )*)>())
+                    self.store.get(&#key)
                         .map(|__mockall_e| {
                             __mockall_e.downcast_ref::<Expectations #tg>()
                             .unwrap()
@@ -1655,10 +2635,9 @@ impl<'a> RefExpectation<'a> {
                 /// Create a new Expectation.
                 #v fn expect #ig (&mut self)
                     -> &mut Expectation #tg
-                    #wc
-                    where #output: Send + Sync
+                    #sync_wc
                 {
-                    self.store.entry(::mockall::Key::new::<(#(#argty, )*)>())
+                    self.store.entry(#key)
                         .or_insert_with(||
                             Box::new(Expectations #tbf ::new())
                         ).downcast_mut::<Expectations #tg>()
@@ -1674,9 +2653,12 @@ impl<'a> RefExpectation<'a> {
     }
 
     fn rfunc(&self) -> TokenStream {
+        let argnames = &self.common.argnames;
+        let argty = &self.common.argty;
         let fn_params = &self.common.fn_params;
         let (ig, tg, wc) = self.common.egenerics.split_for_impl();
         let lg = &self.common.alifetimes;
+        let lts = lg.lifetimes();
         let output = &self.common.output;
 
         #[cfg(not(feature = "nightly_derive"))]
@@ -1690,6 +2672,13 @@ impl<'a> RefExpectation<'a> {
             enum Rfunc #ig #wc {
                 Default(Option<#output>),
                 Const(#output),
+                // Compute the return value from storage owned by the
+                // Expectation
+                Func(Box<dyn ::std::any::Any + Send + Sync>,
+                     Box<dyn for<'__mockall_s, #(#lts),*>
+                         Fn(&'__mockall_s (dyn ::std::any::Any + Send + Sync),
+                            #(#argty, )*)
+                         -> &'__mockall_s #output + Send + Sync>),
                 // Prevent "unused type parameter" errors Surprisingly,
                 // PhantomData<Fn(generics)> is Send even if generics are not,
                 // unlike PhantomData<generics>
@@ -1697,7 +2686,7 @@ impl<'a> RefExpectation<'a> {
             }
 
             impl #ig  Rfunc #tg #wc {
-                fn call #lg (&self)
+                fn call #lg (&self, #(#argnames: #argty, )*)
                     -> std::result::Result<&#output, &'static str>
                 {
                     match self {
@@ -1710,6 +2699,10 @@ impl<'a> RefExpectation<'a> {
                         Rfunc::Const(ref __mockall_o) => {
                             Ok(__mockall_o)
                         },
+                        Rfunc::Func(ref __mockall_s, ref __mockall_f) => {
+                            Ok(__mockall_f(__mockall_s.as_ref(),
+                                           #(#argnames, )*))
+                        },
                         Rfunc::_Phantom(_) => unreachable!()
                     }
                 }
@@ -1743,9 +2736,11 @@ impl<'a> RefMutExpectation<'a> {
     fn expectation(&self, em_ts: TokenStream) -> TokenStream {
         let argnames = &self.common.argnames;
         let argty = &self.common.argty;
+        let hrtb = self.common.hrtb();
         let ident_str = self.common().ident_str();
         let (ig, tg, _wc) = self.common.egenerics.split_for_impl();
         let lg = &self.common.alifetimes;
+        let lts = lg.lifetimes();
         let output = &self.common.output;
         let v = &self.common.vis;
         quote!(
@@ -1762,13 +2757,16 @@ impl<'a> RefMutExpectation<'a> {
                 #v fn call_mut #lg (&mut self, #(#argnames: #argty, )*)
                     -> &mut #output
                 {
-                    self.common.call();
+                    let __mockall_flight = self.common.call();
                     let desc = format!("{}",
                         self.common.matcher.lock().unwrap());
-                    self.rfunc.call_mut(#(#argnames, )*).unwrap_or_else(|m| {
-                            panic!("{}: Expectation({}) {}", #ident_str, desc,
-                                   m);
-                    })
+                    let __mockall_r = self.rfunc.call_mut(#(#argnames, )*)
+                        .unwrap_or_else(|m| {
+                            ::mockall::violation(format!(
+                                "{}: Expectation({}) {}", #ident_str, desc, m));
+                        });
+                    self.common.exit(__mockall_flight);
+                    __mockall_r
                 }
 
                 /// Convenience method that can be used to supply a return value
@@ -1785,7 +2783,7 @@ impl<'a> RefMutExpectation<'a> {
                 /// reference.
                 #v fn returning<MockallF>(&mut self, __mockall_f: MockallF)
                     -> &mut Self
-                    where MockallF: FnMut(#(#argty, )*) -> #output + Send + Sync + 'static
+                    where MockallF: #hrtb FnMut(#(#argty, )*) -> #output + Send + Sync + 'static
                 {
                     self.rfunc = Rfunc::Mut(Box::new(__mockall_f), None);
                     self
@@ -1795,13 +2793,34 @@ impl<'a> RefMutExpectation<'a> {
                 /// Can be used when the argument or return type isn't `Send`.
                 #v fn returning_st<MockallF>(&mut self, __mockall_f: MockallF)
                     -> &mut Self
-                    where MockallF: FnMut(#(#argty, )*) -> #output + 'static
+                    where MockallF: #hrtb FnMut(#(#argty, )*) -> #output + 'static
                 {
                     self.rfunc = Rfunc::MutST(
                         ::mockall::Fragile::new(Box::new(__mockall_f)), None);
                     self
                 }
 
+                /// Return a mutable reference computed from the arguments.  The
+                /// closure receives `storage`, which the `Expectation` owns, so
+                /// it may return a reference into it, like an entry of a map.
+                #v fn returning_from<MockallS, MockallF>(&mut self,
+                    __mockall_s: MockallS, mut __mockall_f: MockallF)
+                    -> &mut Self
+                    where MockallS: Send + Sync + 'static,
+                          MockallF: for<'__mockall_s, #(#lts),*>
+                                    FnMut(&'__mockall_s mut MockallS,
+                                          #(#argty, )*)
+                                    -> &'__mockall_s mut #output
+                                    + Send + Sync + 'static
+                {
+                    self.rfunc = Rfunc::Func(Box::new(__mockall_s),
+                        Box::new(move |__mockall_s, #(#argnames, )*|
+                            __mockall_f(__mockall_s.downcast_mut().unwrap(),
+                                        #(#argnames, )*)
+                        ));
+                    self
+                }
+
                 #em_ts
             }
             impl #ig Default for Expectation #tg
@@ -1847,7 +2866,14 @@ impl<'a> RefMutExpectation<'a> {
             impl #ig
                 ::mockall::AnyExpectations for Expectations #tg
                 where #output: Send + Sync
-            {}
+            {
+                fn wait_until_satisfied(&self,
+                    __mockall_deadline: ::std::time::Instant)
+                    -> ::std::result::Result<(), ::mockall::Timeout>
+                {
+                    Expectations::wait_until_satisfied(self, __mockall_deadline)
+                }
+            }
         )
     }
     fn extra_uses(&self) -> TokenStream { TokenStream::new() }
@@ -1855,18 +2881,22 @@ impl<'a> RefMutExpectation<'a> {
     fn generic_expectations_methods(&self) -> TokenStream {
         let argnames = &self.common.argnames;
         let argty = &self.common.argty;
-        let (ig, tg, wc) = self.common.egenerics.split_for_impl();
-        let output = &self.common.output;
+        let key = self.common.key();
+        let (ig, tg, _wc) = self.common.egenerics.split_for_impl();
+        let call_generics = self.common.call_generics();
+        let (call_ig, _, call_wc) = call_generics.split_for_impl();
+        let sync_wc = self.common.sync_where_clause();
         let tbf = tg.as_turbofish();
         let v = &self.common.vis;
+        let output = &self.common.output;
 
         quote!(
             impl GenericExpectations {
                 /// Simulating calling the real method.
-                #v fn call_mut #ig (&mut self, #(#argnames: #argty, )* )
-                    -> Option<&mut #output>
+                #v fn call_mut #call_ig (&mut self, #(#argnames: #argty, )* )
+                    -> Option<&mut #output> #call_wc
                 {
-                    self.store.get_mut(&::mockall::Key::new::<(#(#argty, )*)>())
+                    self.store.get_mut(&#key)
                         .map(|__mockall_e| {
                             __mockall_e.downcast_mut::<Expectations #tg>()
                             .unwrap()
@@ -1877,10 +2907,9 @@ impl<'a> RefMutExpectation<'a> {
                 }
 
                 /// Create a new Expectation.
-                #v fn expect #ig (&mut self) -> &mut Expectation #tg #wc
-                    where #output: Send + Sync
+                #v fn expect #ig (&mut self) -> &mut Expectation #tg #sync_wc
                 {
-                    self.store.entry(::mockall::Key::new::<(#(#argty, )*)>())
+                    self.store.entry(#key)
                         .or_insert_with(||
                             Box::new(Expectations #tbf ::new())
                         ).downcast_mut::<Expectations #tg>()
@@ -1900,7 +2929,9 @@ impl<'a> RefMutExpectation<'a> {
         let argty = &self.common.argty;
         let fn_params = &self.common.fn_params;
         let (ig, tg, wc) = self.common.egenerics.split_for_impl();
+        let hrtb = self.common.hrtb();
         let lg = &self.common.alifetimes;
+        let lts = lg.lifetimes();
         let output = &self.common.output;
 
         #[cfg(not(feature = "nightly_derive"))]
@@ -1913,14 +2944,22 @@ impl<'a> RefMutExpectation<'a> {
         quote!(
             enum Rfunc #ig #wc {
                 Default(Option<#output>),
-                Mut((Box<dyn FnMut(#(#argty, )*) -> #output + Send + Sync>),
+                Mut((Box<dyn #hrtb FnMut(#(#argty, )*) -> #output + Send + Sync>),
                     Option<#output>),
                 // Version of Rfunc::Mut for closures that aren't Send
                 MutST((::mockall::Fragile<
-                           Box<dyn FnMut(#(#argty, )*) -> #output >>
+                           Box<dyn #hrtb FnMut(#(#argty, )*) -> #output >>
                        ), Option<#output>
                 ),
                 Var(#output),
+                // Compute the return value from storage owned by the
+                // Expectation
+                Func(Box<dyn ::std::any::Any + Send + Sync>,
+                     Box<dyn for<'__mockall_s, #(#lts),*>
+                         FnMut(&'__mockall_s mut (dyn ::std::any::Any + Send
+                                                  + Sync),
+                               #(#argty, )*)
+                         -> &'__mockall_s mut #output + Send + Sync>),
                 // Prevent "unused type parameter" errors Surprisingly,
                 // PhantomData<Fn(generics)> is Send even if generics are not,
                 // unlike PhantomData<generics>
@@ -1961,6 +3000,11 @@ impl<'a> RefMutExpectation<'a> {
                         Rfunc::Var(ref mut __mockall_o) => {
                             Ok(__mockall_o)
                         },
+                        Rfunc::Func(ref mut __mockall_s, ref mut __mockall_f) =>
+                        {
+                            Ok(__mockall_f(__mockall_s.as_mut(),
+                                           #(#argnames, )*))
+                        },
                         Rfunc::_Phantom(_) => unreachable!()
                     }
                 }
@@ -1983,3 +3027,217 @@ impl<'a> RefMutExpectation<'a> {
         TokenStream::new()
     }
 }
+
+/// For methods that take `&self` or `&mut self` and return a value that
+/// borrows from it, like `Option<&T>`
+pub(crate) struct BorrowExpectation<'a> {
+    common: Common<'a>,
+    /// Does the method take `&mut self`?
+    is_mut: bool
+}
+
+impl<'a> BorrowExpectation<'a> {
+    fn common(&self) -> &Common<'_> {&self.common}
+
+    /// Name of the call method, its receiver, and the `Fn` trait of the
+    /// return closure
+    fn call_parts(&self) -> (Ident, TokenStream, TokenStream) {
+        if self.is_mut {
+            (format_ident!("call_mut"), quote!(&'__mockall_s mut),
+             quote!(FnMut))
+        } else {
+            (format_ident!("call"), quote!(&'__mockall_s), quote!(Fn))
+        }
+    }
+
+    fn expectation(&self, em_ts: TokenStream) -> TokenStream {
+        let argnames = &self.common.argnames;
+        let argty = &self.common.argty;
+        let ident_str = self.common().ident_str();
+        let (ig, tg, _wc) = self.common.egenerics.split_for_impl();
+        let lts = self.common.alifetimes.lifetimes().collect::<Vec<_>>();
+        let output = &self.common.output;
+        let v = &self.common.vis;
+        let (call, sref, fn_trait) = self.call_parts();
+        let downcast = if self.is_mut {
+            quote!(downcast_mut)
+        } else {
+            quote!(downcast_ref)
+        };
+        quote!(
+            /// Expectation type for methods whose return value borrows from
+            /// `self`.  This is the type returned by the `expect_*` methods.
+            #v struct Expectation #ig {
+                common: Common #tg,
+                rfunc: Rfunc #tg,
+            }
+
+            impl #ig Expectation #tg {
+                /// Simulating calling the real method for this expectation
+                #v fn #call<'__mockall_s, #(#lts),*>(#sref self,
+                    #(#argnames: #argty, )*) -> #output
+                {
+                    let __mockall_flight = self.common.call();
+                    // Borrow common separately, so the closure doesn't borrow
+                    // all of self while rfunc is borrowed.
+                    let __mockall_common = &self.common;
+                    let __mockall_r = self.rfunc.#call(#(#argnames, )*)
+                        .unwrap_or_else(|m| {
+                            let desc = format!("{}",
+                                __mockall_common.matcher.lock().unwrap());
+                            ::mockall::violation(format!(
+                                "{}: Expectation({}) {}", #ident_str, desc, m));
+                        });
+                    self.common.exit(__mockall_flight);
+                    __mockall_r
+                }
+
+                /// Compute the return value from the arguments.  The closure
+                /// receives `storage`, which the `Expectation` owns, so the
+                /// return value may borrow from it.
+                #v fn returning_from<MockallS, MockallF>(&mut self,
+                    __mockall_s: MockallS, mut __mockall_f: MockallF)
+                    -> &mut Self
+                    where MockallS: Send + Sync + 'static,
+                          MockallF: for<'__mockall_s, #(#lts),*>
+                                    #fn_trait(#sref MockallS, #(#argty, )*)
+                                    -> #output + Send + Sync + 'static
+                {
+                    self.rfunc = Rfunc::Func(Box::new(__mockall_s),
+                        Box::new(move |__mockall_s, #(#argnames, )*|
+                            __mockall_f(__mockall_s.#downcast().unwrap(),
+                                        #(#argnames, )*)
+                        ));
+                    self
+                }
+
+                #em_ts
+            }
+
+            impl #ig Default for Expectation #tg
+            {
+                fn default() -> Self {
+                    Expectation {
+                        common: Common::default(),
+                        rfunc: Rfunc::default()
+                    }
+                }
+            }
+        )
+    }
+
+    fn expectations_methods(&self) -> TokenStream {
+        let argnames = &self.common.argnames;
+        let argty = &self.common.argty;
+        let (ig, tg, _wc) = self.common.egenerics.split_for_impl();
+        let lts = self.common.alifetimes.lifetimes().collect::<Vec<_>>();
+        let output = &self.common.output;
+        let predexprs = &self.common.predexprs;
+        let v = &self.common.vis;
+        let (call, sref, _) = self.call_parts();
+        let iter = if self.is_mut {
+            quote!(iter_mut)
+        } else {
+            quote!(iter)
+        };
+        quote!(
+            impl #ig Expectations #tg {
+                /// Simulate calling the real method.  Every current expectation
+                /// will be checked in FIFO order and the first one with
+                /// matching arguments will be used.
+                #v fn #call<'__mockall_s, #(#lts),*>(#sref self,
+                    #(#argnames: #argty, )*) -> Option<#output>
+                {
+                    let __mockall_n = self.0.len();
+                    self.0.#iter()
+                        .find(|__mockall_e|
+                              __mockall_e.matches(#(#predexprs, )*) &&
+                              (!__mockall_e.is_done() || __mockall_n == 1))
+                        .map(move |__mockall_e|
+                             __mockall_e.#call(#(#argnames, )*)
+                        )
+                }
+            }
+        )
+    }
+
+    fn extra_uses(&self) -> TokenStream { TokenStream::new() }
+
+    fn generic_expectations_methods(&self) -> TokenStream {
+        compile_error(self.common.meth_ident.span(),
+            "Mockall does not yet support generic methods whose return values borrow from self");
+        TokenStream::new()
+    }
+
+    fn new(common: Common<'a>, is_mut: bool) -> Self {
+        BorrowExpectation { common, is_mut }
+    }
+
+    fn rfunc(&self) -> TokenStream {
+        let argnames = &self.common.argnames;
+        let argty = &self.common.argty;
+        let fn_params = &self.common.fn_params;
+        let (ig, tg, wc) = self.common.egenerics.split_for_impl();
+        let lts = self.common.alifetimes.lifetimes().collect::<Vec<_>>();
+        let output = &self.common.output;
+        let (call, sref, fn_trait) = self.call_parts();
+        let (storage, as_storage) = if self.is_mut {
+            (quote!(ref mut __mockall_s), quote!(as_mut))
+        } else {
+            (quote!(ref __mockall_s), quote!(as_ref))
+        };
+        let func = if self.is_mut {
+            quote!(ref mut __mockall_f)
+        } else {
+            quote!(ref __mockall_f)
+        };
+
+        quote!(
+            enum Rfunc #ig #wc {
+                Default,
+                // Compute the return value from storage owned by the
+                // Expectation
+                Func(Box<dyn ::std::any::Any + Send + Sync>,
+                     Box<dyn for<'__mockall_s, #(#lts),*>
+                         #fn_trait(#sref (dyn ::std::any::Any + Send + Sync),
+                                   #(#argty, )*)
+                         -> #output + Send + Sync>),
+                // Prevent "unused type parameter" errors Surprisingly,
+                // PhantomData<Fn(generics)> is Send even if generics are not,
+                // unlike PhantomData<generics>
+                _Phantom(Mutex<Box<dyn Fn(#fn_params) -> () + Send>>)
+            }
+
+            impl #ig  Rfunc #tg #wc {
+                fn #call<'__mockall_s, #(#lts),*>(#sref self,
+                    #(#argnames: #argty, )*)
+                    -> std::result::Result<#output, &'static str>
+                {
+                    match self {
+                        Rfunc::Default => {
+                            Err("No return value was set.  Use returning_from to supply one")
+                        },
+                        Rfunc::Func(#storage, #func) => {
+                            Ok(__mockall_f(__mockall_s.#as_storage(),
+                                           #(#argnames, )*))
+                        },
+                        Rfunc::_Phantom(_) => unreachable!()
+                    }
+                }
+            }
+
+            impl #ig std::default::Default for Rfunc #tg #wc
+            {
+                fn default() -> Self {
+                    Rfunc::Default
+                }
+            }
+        )
+    }
+
+    fn static_method_methods(&self, _with_generics: &TokenStream,
+        _with_args: &TokenStream) -> TokenStream
+    {
+        TokenStream::new()
+    }
+}
@@ -18,15 +18,16 @@ use std::{
 use syn::{
     *,
     punctuated::Punctuated,
-    spanned::Spanned
+    spanned::Spanned,
+    visit_mut::{self, VisitMut}
 };
 
 mod automock;
 mod expectation;
 mod mock;
-use crate::automock::do_automock;
+use crate::automock::{do_automock, reorder_async_trait, substitute_own_types};
 use crate::mock::{Mock, do_mock};
-use crate::expectation::Expectation;
+use crate::expectation::{Expectation, returns_by_value};
 
 #[derive(Debug)]
 struct MethodTypes {
@@ -74,6 +75,70 @@ cfg_if! {
     }
 }
 
+/// Choose a concrete type for the Expectation to use in place of an argument
+/// of type `impl Trait`, and the expression that converts the argument into
+/// it.
+///
+/// * `impl Into<T>` becomes `T`
+/// * `impl AsRef<T>` and `impl Borrow<T>` become `&T`
+/// * `impl IntoIterator<Item=T>` and `impl Iterator<Item=T>` become `Vec<T>`
+/// * `impl Fn(...)`, `impl FnMut(...)`, and `impl FnOnce(...)` are boxed
+/// * Any other `impl Trait` becomes `&dyn Trait`
+///
+/// The first of those traits among the bounds decides, so `impl Send +
+/// Into<T>` becomes `T` too.
+fn dearg_impl(tit: &TypeImplTrait, pat: &Pat) -> (Type, TokenStream) {
+    const CONVERTIBLE: &[&str] = &["Into", "AsRef", "Borrow", "IntoIterator",
+        "Iterator", "Fn", "FnMut", "FnOnce"];
+    let bounds = &tit.bounds;
+    let convertible = bounds.iter().find_map(|tpb| match tpb {
+        TypeParamBound::Trait(tb) => {
+            let seg = tb.path.segments.last().unwrap();
+            Some(seg).filter(|seg| CONVERTIBLE.iter().any(|t| seg.ident == t))
+        },
+        _ => None
+    });
+    let (trait_ident, trait_arg) = match convertible {
+        Some(seg) => {
+            let arg = match &seg.arguments {
+                PathArguments::AngleBracketed(abga) => abga.args.first(),
+                _ => None
+            };
+            (seg.ident.to_string(), arg)
+        },
+        None => (String::new(), None)
+    };
+    match (trait_ident.as_str(), trait_arg) {
+        ("Into", Some(GenericArgument::Type(ty))) => (
+            ty.clone(),
+            quote!(::std::convert::Into::<#ty>::into(#pat))
+        ),
+        ("AsRef", Some(GenericArgument::Type(ty))) => (
+            parse2(quote!(&#ty)).unwrap(),
+            quote!(::std::convert::AsRef::<#ty>::as_ref(&#pat))
+        ),
+        ("Borrow", Some(GenericArgument::Type(ty))) => (
+            parse2(quote!(&#ty)).unwrap(),
+            quote!(::std::borrow::Borrow::<#ty>::borrow(&#pat))
+        ),
+        ("IntoIterator", Some(GenericArgument::Binding(b))) |
+        ("Iterator", Some(GenericArgument::Binding(b))) if b.ident == "Item" =>
+        {
+            let ty = &b.ty;
+            (
+                parse2(quote!(Vec<#ty>)).unwrap(),
+                quote!(::std::iter::IntoIterator::into_iter(#pat)
+                       .collect::<Vec<#ty>>())
+            )
+        },
+        ("Fn", _) | ("FnMut", _) | ("FnOnce", _) => (
+            parse2(quote!(Box<dyn #bounds>)).unwrap(),
+            quote!(Box::new(#pat))
+        ),
+        _ => (parse2(quote!(&(dyn #bounds))).unwrap(), quote!(&#pat))
+    }
+}
+
 // If there are any closures in the argument list, turn them into boxed
 // functions
 fn declosurefy(gen: &Generics, args: &Punctuated<FnArg, Token![,]>) ->
@@ -156,7 +221,14 @@ fn declosurefy(gen: &Generics, args: &Punctuated<FnArg, Token![,]>) ->
         if let FnArg::Typed(pt) = arg {
             let mut immutable_pt = pt.clone();
             demutify_arg(&mut immutable_pt);
-            if let Some(newty) = hm.get(&pt.ty) {
+            if let Type::ImplTrait(tit) = pt.ty.as_ref() {
+                FnArg::Typed(PatType {
+                    attrs: Vec::default(),
+                    colon_token: pt.colon_token,
+                    ty: Box::new(dearg_impl(tit, &immutable_pt.pat).0),
+                    pat: immutable_pt.pat,
+                })
+            } else if let Some(newty) = hm.get(&pt.ty) {
                 FnArg::Typed(PatType {
                     attrs: Vec::default(),
                     pat: immutable_pt.pat,
@@ -176,7 +248,7 @@ fn declosurefy(gen: &Generics, args: &Punctuated<FnArg, Token![,]>) ->
         }
     }));
 
-    // Finally, Box any closure arguments
+    // Finally, Box any closure arguments and convert any impl Trait arguments
     // use filter_map to remove the &self argument
     let callargs = Punctuated::from_iter(args.iter().filter_map(|arg| {
         match arg {
@@ -184,7 +256,9 @@ fn declosurefy(gen: &Generics, args: &Punctuated<FnArg, Token![,]>) ->
                 let mut pt2 = pt.clone();
                 demutify_arg(&mut pt2);
                 let pat = &pt2.pat;
-                if hm.contains_key(&pt.ty) {
+                if let Type::ImplTrait(tit) = pt.ty.as_ref() {
+                    Some(dearg_impl(tit, pat).1)
+                } else if hm.contains_key(&pt.ty) {
                     Some(quote!(Box::new(#pat)))
                 } else {
                     Some(quote!(#pat))
@@ -196,14 +270,413 @@ fn declosurefy(gen: &Generics, args: &Punctuated<FnArg, Token![,]>) ->
     (outg, outargs, callargs)
 }
 
-/// Replace any "impl trait" types with "Box<dyn trait>" equivalents
-fn deimplify(rt: &mut ReturnType) {
+/// Replace any "impl trait" types with "Box<dyn trait>" equivalents.
+/// If `pin`, then `impl Future` and `impl Stream` become `Pin<Box<dyn ...>>`
+/// instead, because a boxed trait object can only be polled if it's pinned.
+/// Only trait methods get pinned; a mock struct's inherent method returns
+/// exactly the `Box<dyn ...>` that its expectation does.
+fn deimplify(rt: &mut ReturnType, pin: bool) {
     if let ReturnType::Type(_, ty) = rt {
         if let Type::ImplTrait(ref tit) = &**ty {
             let bounds = &tit.bounds;
-            *ty = parse2(quote!(Box<dyn #bounds>)).unwrap();
+            // Futures and Streams are recognized by their associated types so
+            // as not to confuse them with other traits of the same names.
+            let is_pollable =
+                assoc_binding(bounds, "Future", "Output").is_some() ||
+                assoc_binding(bounds, "Stream", "Item").is_some();
+            *ty = if pin && is_pollable {
+                parse2(quote!(::std::pin::Pin<Box<dyn #bounds>>))
+            } else {
+                parse2(quote!(Box<dyn #bounds>))
+            }.unwrap();
+        }
+    }
+}
+
+/// Find the type bound to associated type `assoc` of trait `trait_` in a list
+/// of bounds.  e.g. `T` in `Future<Output = T> + Send`.
+fn assoc_binding<'a>(bounds: &'a Punctuated<TypeParamBound, Token![+]>,
+                     trait_: &str, assoc: &str) -> Option<&'a Type>
+{
+    for bound in bounds.iter() {
+        if let TypeParamBound::Trait(tb) = bound {
+            let seg = tb.path.segments.last().unwrap();
+            if seg.ident != trait_ {
+                continue;
+            }
+            if let PathArguments::AngleBracketed(abga) = &seg.arguments {
+                for ga in abga.args.iter() {
+                    if let GenericArgument::Binding(b) = ga {
+                        if b.ident == assoc {
+                            return Some(&b.ty);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    None
+}
+
+/// If `ty` is a boxed trait object, like `Box<dyn Iterator<Item = T>>`, return
+/// the bounds of its trait object.  If `pinned`, then `ty` must be a pinned
+/// box instead, like `Pin<Box<dyn Future<Output = T> + Send>>`.
+fn boxed_bounds(ty: &Type, pinned: bool)
+    -> Option<&Punctuated<TypeParamBound, Token![+]>>
+{
+    fn first_type_arg<'a>(ty: &'a Type, name: &str) -> Option<&'a Type> {
+        if let Type::Path(tp) = ty {
+            let seg = tp.path.segments.last().unwrap();
+            if seg.ident != name {
+                return None;
+            }
+            if let PathArguments::AngleBracketed(abga) = &seg.arguments {
+                if let Some(GenericArgument::Type(ty)) = abga.args.first() {
+                    return Some(ty);
+                }
+            }
+        }
+        None
+    }
+
+    let boxed = if pinned {
+        first_type_arg(ty, "Pin")?
+    } else {
+        ty
+    };
+    if let Type::TraitObject(tto) = first_type_arg(boxed, "Box")? {
+        Some(&tto.bounds)
+    } else {
+        None
+    }
+}
+
+/// Find the output type of a boxed future, like the ones returned by
+/// `#[async_trait]` methods: `Pin<Box<dyn Future<Output = T> + Send +
+/// 'async_trait>>`
+fn future_output(ty: &Type) -> Option<&Type> {
+    assoc_binding(boxed_bounds(ty, true)?, "Future", "Output")
+}
+
+/// How a deep stub method returns its nested mock object
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum DeepKind {
+    /// Like `Box<dyn Trait>`
+    Boxed,
+    /// Like `&dyn Trait`
+    Ref,
+    /// Like `&mut dyn Trait`
+    RefMut
+}
+
+/// A method marked with `#[deep]`.  Instead of using Expectations, it returns
+/// a nested mock object that is stored in the parent mock.
+#[derive(Debug)]
+struct DeepStub {
+    kind: DeepKind,
+    /// Type of the nested mock object, like `MockSession`
+    mock: Type
+}
+
+impl DeepStub {
+    /// Type of the parent mock struct's field that holds the nested mock
+    fn field_ty(&self) -> TokenStream {
+        let mock = &self.mock;
+        if self.kind == DeepKind::Boxed {
+            quote!(::std::sync::Mutex<#mock>)
+        } else {
+            quote!(#mock)
+        }
+    }
+}
+
+/// Does this attribute mark a method as a deep stub?
+fn is_deep_attr(attr: &Attribute) -> bool {
+    attr.path.segments.last()
+        .map(|seg| seg.ident == "deep")
+        .unwrap_or(false)
+}
+
+/// If the method is marked with `#[deep]`, find the type of the nested mock
+/// object that it returns.
+fn deep_stub(attrs: &[Attribute], sig: &Signature) -> Option<DeepStub> {
+    attrs.iter().find(|a| is_deep_attr(a))?;
+    if !sig.generics.params.iter()
+        .all(|p| matches!(p, GenericParam::Lifetime(_)))
+    {
+        compile_error(sig.generics.span(),
+            "#[deep] does not support generic methods");
+        return None;
+    }
+    if !matches!(sig.inputs.first(), Some(FnArg::Receiver(_))) {
+        compile_error(sig.span(), "#[deep] methods must have a self receiver");
+        return None;
+    }
+    let ty = match &sig.output {
+        ReturnType::Type(_, ty) => ty.as_ref(),
+        ReturnType::Default => {
+            compile_error(sig.span(),
+                "#[deep] methods must return a trait object");
+            return None;
+        }
+    };
+    let (kind, bounds) = if let Type::Reference(tr) = ty {
+        let kind = if tr.mutability.is_some() {
+            DeepKind::RefMut
+        } else {
+            DeepKind::Ref
+        };
+        match tr.elem.as_ref() {
+            Type::TraitObject(tto) => (kind, &tto.bounds),
+            _ => {
+                compile_error(ty.span(),
+                    "#[deep] methods must return a trait object");
+                return None;
+            }
+        }
+    } else if let Some(bounds) = boxed_bounds(ty, false) {
+        (DeepKind::Boxed, bounds)
+    } else {
+        compile_error(ty.span(),
+            "#[deep] methods must return Box<dyn Trait>, &dyn Trait, or &mut dyn Trait");
+        return None;
+    };
+    let mut path = bounds.iter().find_map(|b| match b {
+        TypeParamBound::Trait(tb) => Some(tb.path.clone()),
+        _ => None
+    })?;
+    let seg = path.segments.last_mut().unwrap();
+    seg.ident = gen_mock_ident(&seg.ident);
+    let mock = Type::Path(TypePath{qself: None, path});
+    Some(DeepStub{kind, mock})
+}
+
+/// Does this attribute invoke `#[async_trait]` or `#[async_trait(?Send)]`?
+fn is_async_trait_attr(attr: &Attribute) -> bool {
+    attr.path.segments.last()
+        .map(|seg| seg.ident == "async_trait")
+        .unwrap_or(false)
+}
+
+/// Has this method signature already been rewritten by `#[async_trait]`?
+fn is_async_trait(sig: &Signature) -> bool {
+    sig.generics.lifetimes().any(|ltd| ltd.lifetime.ident == "async_trait")
+}
+
+/// Collects every lifetime used by a syntax tree
+#[derive(Default)]
+struct UsedLifetimes(Vec<Lifetime>);
+
+impl VisitMut for UsedLifetimes {
+    fn visit_lifetime_mut(&mut self, lt: &mut Lifetime) {
+        self.0.push(lt.clone());
+    }
+}
+
+/// Replaces some lifetimes with elided ones
+struct ElideLifetimes<'a>(&'a [Lifetime]);
+
+impl VisitMut for ElideLifetimes<'_> {
+    fn visit_receiver_mut(&mut self, receiver: &mut Receiver) {
+        if let Some((_, lt)) = &mut receiver.reference {
+            if matches!(lt, Some(l) if self.0.contains(l)) {
+                *lt = None;
+            }
+        }
+        visit_mut::visit_receiver_mut(self, receiver);
+    }
+
+    fn visit_type_reference_mut(&mut self, tr: &mut TypeReference) {
+        if matches!(&tr.lifetime, Some(l) if self.0.contains(l)) {
+            tr.lifetime = None;
+        }
+        visit_mut::visit_type_reference_mut(self, tr);
+    }
+
+    fn visit_type_impl_trait_mut(&mut self, tit: &mut TypeImplTrait) {
+        tit.bounds = tit.bounds.iter()
+            .filter(|b| !matches!(b,
+                TypeParamBound::Lifetime(l) if self.0.contains(l)))
+            .cloned()
+            .collect();
+        visit_mut::visit_type_impl_trait_mut(self, tit);
+    }
+
+    fn visit_lifetime_mut(&mut self, lt: &mut Lifetime) {
+        if self.0.contains(lt) {
+            *lt = Lifetime::new("'_", lt.span());
+        }
+    }
+}
+
+/// Find the lifetime parameters that `#[async_trait]` added to a method that
+/// returns a future of `output`.
+///
+/// `#[async_trait]` names each elided lifetime in the method's arguments
+/// `'life0`, `'life1`, etc, and adds them after the method's own lifetime
+/// parameters, followed by `'async_trait`.  Each is used exactly once in the
+/// arguments and nowhere else, except for bounds like `'life0: 'async_trait`.
+/// A lifetime of the user's own that's merely named like them is used in other
+/// places too.
+fn async_trait_lifetimes(sig: &Signature, output: &Type) -> Vec<Lifetime> {
+    let mut lifetimes = sig.generics.lifetimes()
+        .map(|ltd| ltd.lifetime.clone())
+        .collect::<Vec<_>>();
+    match lifetimes.pop() {
+        Some(lt) if lt.ident == "async_trait" => (),
+        _ => return Vec::new()
+    }
+    let n = lifetimes.last()
+        .and_then(|lt| lt.ident.to_string()
+            .strip_prefix("life")?
+            .parse::<usize>()
+            .ok())
+        .map_or(0, |i| i + 1);
+    if n > lifetimes.len() {
+        return Vec::new();
+    }
+    let candidates = lifetimes.split_off(lifetimes.len() - n);
+    if candidates.iter().enumerate()
+        .any(|(i, lt)| lt.ident != format!("life{}", i))
+    {
+        return Vec::new();
+    }
+
+    let mut in_inputs = UsedLifetimes::default();
+    for fa in sig.inputs.iter() {
+        in_inputs.visit_fn_arg_mut(&mut fa.clone());
+    }
+    let mut elsewhere = UsedLifetimes::default();
+    elsewhere.visit_type_mut(&mut output.clone());
+    for gp in sig.generics.params.iter() {
+        match gp {
+            GenericParam::Lifetime(ltd) => elsewhere.0.extend(
+                ltd.bounds.iter().cloned()),
+            gp => elsewhere.visit_generic_param_mut(&mut gp.clone())
+        }
+    }
+    if let Some(wc) = &sig.generics.where_clause {
+        for wp in wc.predicates.iter() {
+            match wp {
+                WherePredicate::Lifetime(plt)
+                    if candidates.contains(&plt.lifetime) &&
+                        plt.bounds.iter().all(|b| b.ident == "async_trait")
+                    => (),
+                wp => elsewhere.visit_where_predicate_mut(&mut wp.clone())
+            }
+        }
+    }
+    candidates.into_iter()
+        .filter(|lt| {
+            in_inputs.0.iter().filter(|l| *l == lt).count() == 1 &&
+                !elsewhere.0.contains(lt)
+        }).collect()
+}
+
+/// Undo `#[async_trait]`'s rewriting of a method signature.
+///
+/// `#[async_trait]` turns `async fn foo(&self) -> u32` into something like
+/// `fn foo<'life0, 'async_trait>(&'life0 self) -> Pin<Box<dyn Future<Output =
+/// u32> + Send + 'async_trait>> where 'life0: 'async_trait, Self:
+/// 'async_trait`.  Return the original `async fn` signature, if `sig` looks like
+/// that.  The bounds of the method's type parameters stay in the where clause,
+/// where `#[async_trait]` moved them.
+fn resugar_async_trait(sig: &Signature) -> Option<Signature> {
+    if !is_async_trait(sig) {
+        return None;
+    }
+    let output = if let ReturnType::Type(_, ty) = &sig.output {
+        future_output(ty)?
+    } else {
+        return None;
+    };
+    let mut generated = async_trait_lifetimes(sig, output);
+    generated.push(Lifetime::new("'async_trait", Span::call_site()));
+    let is_generated = |lt: &Lifetime| generated.contains(lt);
+    let is_generated_bound = |b: &TypeParamBound| matches!(b,
+        TypeParamBound::Lifetime(lt) if is_generated(lt));
+
+    let mut resugared = sig.clone();
+    resugared.asyncness = Some(Token![async](sig.fn_token.span()));
+    resugared.generics.params = sig.generics.params.iter()
+        .filter_map(|gp| match gp {
+            GenericParam::Lifetime(ltd) if is_generated(&ltd.lifetime) => None,
+            GenericParam::Lifetime(ltd) => {
+                let mut ltd = ltd.clone();
+                ltd.bounds = ltd.bounds.iter()
+                    .filter(|lt| !is_generated(lt))
+                    .cloned()
+                    .collect();
+                Some(GenericParam::Lifetime(ltd))
+            },
+            GenericParam::Type(tp) => {
+                let mut tp = tp.clone();
+                tp.bounds = tp.bounds.iter()
+                    .filter(|b| !is_generated_bound(b))
+                    .cloned()
+                    .collect();
+                Some(GenericParam::Type(tp))
+            },
+            gp => Some(gp.clone())
+        }).collect();
+    if resugared.generics.params.is_empty() {
+        resugared.generics.lt_token = None;
+        resugared.generics.gt_token = None;
+    }
+    if let Some(wc) = resugared.generics.where_clause.as_mut() {
+        wc.predicates = wc.predicates.iter()
+            .filter_map(|wp| match wp {
+                WherePredicate::Lifetime(plt) => {
+                    if is_generated(&plt.lifetime) {
+                        return None;
+                    }
+                    let mut plt = plt.clone();
+                    plt.bounds = plt.bounds.iter()
+                        .filter(|lt| !is_generated(lt))
+                        .cloned()
+                        .collect();
+                    if plt.bounds.is_empty() {
+                        None
+                    } else {
+                        Some(WherePredicate::Lifetime(plt))
+                    }
+                },
+                WherePredicate::Type(pt) => {
+                    if !pt.bounds.iter().any(is_generated_bound) {
+                        return Some(wp.clone());
+                    }
+                    // #[async_trait] inferred all of Self's bounds
+                    if let Type::Path(tp) = &pt.bounded_ty {
+                        if tp.qself.is_none() && tp.path.is_ident("Self") {
+                            return None;
+                        }
+                    }
+                    let mut pt = pt.clone();
+                    pt.bounds = pt.bounds.iter()
+                        .filter(|b| !is_generated_bound(b))
+                        .cloned()
+                        .collect();
+                    if pt.bounds.is_empty() {
+                        None
+                    } else {
+                        Some(WherePredicate::Type(pt))
+                    }
+                },
+                wp => Some(wp.clone())
+            }).collect();
+        if wc.predicates.is_empty() {
+            resugared.generics.where_clause = None;
         }
     }
+    let mut elide = ElideLifetimes(&generated);
+    for fa in resugared.inputs.iter_mut() {
+        elide.visit_fn_arg_mut(fa);
+    }
+    let mut output = output.clone();
+    elide.visit_type_mut(&mut output);
+    resugared.output = ReturnType::Type(<Token![->]>::default(),
+                                        Box::new(output));
+    Some(resugared)
 }
 
 /// Remove any mutability qualifiers from a method's argument list
@@ -222,6 +695,50 @@ fn demutify(inputs: &Punctuated<FnArg, token::Comma>)
     output
 }
 
+/// Replace a typed `self` argument, like `self: Box<Self>`, with the plain
+/// receiver that the Expectation should treat it as.  `&Self` and `Pin<&Self>`
+/// become `&self`, `&mut Self` and `Pin<&mut Self>` become `&mut self`, and
+/// owning pointers like `Box<Self>`, `Rc<Self>`, and `Arc<Self>` become `self`.
+fn untype_self(sig: &Signature) -> Signature {
+    let mut out = sig.clone();
+    let pt = match out.inputs.first() {
+        Some(FnArg::Typed(pt)) => pt,
+        _ => return out
+    };
+    match pt.pat.as_ref() {
+        Pat::Ident(pi) if pi.ident == "self" => (),
+        _ => return out
+    }
+    let mut ty = pt.ty.as_ref();
+    if let Type::Path(tp) = ty {
+        let last = tp.path.segments.last().unwrap();
+        if last.ident == "Pin" {
+            if let PathArguments::AngleBracketed(abga) = &last.arguments {
+                if let Some(GenericArgument::Type(inner)) = abga.args.first() {
+                    ty = inner;
+                }
+            }
+        }
+    }
+    let receiver = if let Type::Reference(tr) = ty {
+        Receiver {
+            attrs: pt.attrs.clone(),
+            reference: Some((tr.and_token, tr.lifetime.clone())),
+            mutability: tr.mutability,
+            self_token: <Token![self]>::default()
+        }
+    } else {
+        Receiver {
+            attrs: pt.attrs.clone(),
+            reference: None,
+            mutability: None,
+            self_token: <Token![self]>::default()
+        }
+    };
+    *out.inputs.first_mut().unwrap() = FnArg::Receiver(receiver);
+    out
+}
+
 /// Remove any "mut" from a method argument's binding.
 fn demutify_arg(arg: &mut PatType) {
     match *arg.pat {
@@ -473,8 +990,9 @@ fn strip_generics_lifetimes(generics: &Generics) -> Generics {
         lt_token: generics.lt_token,
         gt_token: generics.gt_token,
         where_clause: generics.where_clause.clone(),
-        params: generics.type_params()
-            .map(|generics| GenericParam::Type(generics.clone()))
+        params: generics.params.iter()
+            .filter(|p| !matches!(p, GenericParam::Lifetime(_)))
+            .cloned()
             .collect::<Punctuated<GenericParam, Token![,]>>()
     }
 }
@@ -562,6 +1080,122 @@ fn supersuperfy(original: &Type, levels: i32) -> Type {
     output
 }
 
+/// Replace every anonymous lifetime in a type, like `'_`, with one supplied by
+/// `name`.  If `elided` is true, then replace the elided lifetimes of
+/// references, too.  Returns true if any lifetime was replaced.
+fn name_lifetimes(ty: &mut Type, elided: bool,
+                  name: &mut dyn FnMut() -> Lifetime) -> bool
+{
+    fn is_anonymous(lt: &Lifetime) -> bool {
+        lt.ident == "_"
+    }
+
+    fn name_path(path: &mut Path, elided: bool,
+                 name: &mut dyn FnMut() -> Lifetime) -> bool
+    {
+        let mut named = false;
+        for seg in path.segments.iter_mut() {
+            if let PathArguments::AngleBracketed(abga) = &mut seg.arguments {
+                for ga in abga.args.iter_mut() {
+                    match ga {
+                        GenericArgument::Lifetime(lt) if is_anonymous(lt) => {
+                            *lt = name();
+                            named = true;
+                        },
+                        GenericArgument::Type(ty) => {
+                            named |= name_lifetimes(ty, elided, name);
+                        },
+                        GenericArgument::Binding(b) => {
+                            named |= name_lifetimes(&mut b.ty, elided, name);
+                        },
+                        _ => ()
+                    }
+                }
+            }
+        }
+        named
+    }
+
+    match ty {
+        Type::Reference(r) => {
+            let replace = match &r.lifetime {
+                None => elided,
+                Some(lt) => is_anonymous(lt)
+            };
+            if replace {
+                r.lifetime = Some(name());
+            }
+            name_lifetimes(r.elem.as_mut(), elided, name) | replace
+        },
+        Type::Path(tp) => {
+            let mut named = false;
+            if let Some(qself) = &mut tp.qself {
+                named |= name_lifetimes(qself.ty.as_mut(), elided, name);
+            }
+            name_path(&mut tp.path, elided, name) | named
+        },
+        Type::Tuple(tuple) => {
+            tuple.elems.iter_mut()
+                .fold(false, |acc, t| name_lifetimes(t, elided, name) | acc)
+        },
+        Type::Slice(s) => name_lifetimes(s.elem.as_mut(), elided, name),
+        Type::Array(a) => name_lifetimes(a.elem.as_mut(), elided, name),
+        Type::Paren(p) => name_lifetimes(p.elem.as_mut(), elided, name),
+        Type::Group(g) => name_lifetimes(g.elem.as_mut(), elided, name),
+        Type::TraitObject(tto) => {
+            let mut named = false;
+            for bound in tto.bounds.iter_mut() {
+                match bound {
+                    TypeParamBound::Lifetime(lt) if is_anonymous(lt) => {
+                        *lt = name();
+                        named = true;
+                    },
+                    TypeParamBound::Trait(tb) => {
+                        named |= name_path(&mut tb.path, elided, name);
+                    },
+                    _ => ()
+                }
+            }
+            named
+        },
+        _ => false
+    }
+}
+
+/// Replace every elided or anonymous lifetime in a method's return type with
+/// `'__mockall_s`, the lifetime of storage owned by the Expectation.  Returns
+/// true if any lifetime was replaced, which means that the return value
+/// borrows from `self`.
+fn borrowify(ty: &mut Type) -> bool {
+    name_lifetimes(ty, true,
+        &mut || Lifetime::new("'__mockall_s", Span::call_site()))
+}
+
+/// Give each anonymous lifetime in a method's arguments, like the one in
+/// `cx: &mut Context<'_>`, a name, and add it to the method's generics.
+fn name_arg_lifetimes(sig: &mut Signature) {
+    let mut lifetimes = Vec::new();
+    for arg in sig.inputs.iter_mut() {
+        if let FnArg::Typed(pt) = arg {
+            name_lifetimes(pt.ty.as_mut(), false, &mut || {
+                let lt = Lifetime::new(
+                    &format!("'__mockall_a{}", lifetimes.len()),
+                    Span::call_site());
+                lifetimes.push(lt.clone());
+                lt
+            });
+        }
+    }
+    for lt in lifetimes.into_iter().rev() {
+        sig.generics.params.insert(0,
+            GenericParam::Lifetime(LifetimeDef::new(lt)));
+    }
+    if !sig.generics.params.is_empty() {
+        sig.generics.lt_token.get_or_insert_with(Default::default);
+        sig.generics.gt_token.get_or_insert_with(Default::default);
+    }
+}
+
 /// Generate a mock identifier from the regular one: eg "Foo" => "MockFoo"
 fn gen_mock_ident(ident: &Ident) -> Ident {
     format_ident!("Mock{}", ident)
@@ -624,8 +1258,11 @@ fn merge_generics(x: &Generics, y: &Generics) -> Generics {
                         if ot.default.is_none() {
                             ot.default = yt.default.clone();
                         }
-                        // XXX this might result in duplicate bounds
-                        ot.bounds.extend(yt.bounds.iter().cloned());
+                        for b in yt.bounds.iter() {
+                            if !ot.bounds.iter().any(|ob| ob == b) {
+                                ot.bounds.push(b.clone());
+                            }
+                        }
                     }
                     continue 'outer_param;
                 }
@@ -655,8 +1292,12 @@ fn merge_generics(x: &Generics, y: &Generics) -> Generics {
                                     obl.lifetimes.extend(
                                         ybl.lifetimes.iter().cloned()),
                             };
-                            // XXX: might result in duplicate bounds
-                            ot.bounds.extend(yt.bounds.iter().cloned())
+                            // Duplicate ?Sized bounds are an error
+                            for b in yt.bounds.iter() {
+                                if !ot.bounds.iter().any(|ob| ob == b) {
+                                    ot.bounds.push(b.clone());
+                                }
+                            }
                         }
                         continue 'outer_wc;
                     }
@@ -737,6 +1378,49 @@ fn split_lifetimes(
         }
     }
 
+    // The argument lifetimes become higher-ranked, so the type parameters
+    // can't be bounded by them.  Drop any such bounds.
+    let alset = alv.iter()
+        .filter_map(|p| match p {
+            GenericParam::Lifetime(ltd) => Some(ltd.lifetime.clone()),
+            _ => None
+        }).collect::<HashSet<_>>();
+    let free = |tpb: &TypeParamBound|
+        find_lifetimes_in_tpb(tpb).is_disjoint(&alset);
+    for p in tv.iter_mut() {
+        if let GenericParam::Type(tp) = p {
+            tp.bounds = Punctuated::from_iter(
+                tp.bounds.iter().filter(|b| free(b)).cloned());
+            if tp.bounds.is_empty() {
+                tp.colon_token = None;
+            }
+        }
+    }
+    let where_clause = generics.where_clause.map(|mut wc| {
+        wc.predicates = Punctuated::from_iter(
+            wc.predicates.into_iter().filter_map(|mut wp| match &mut wp {
+                WherePredicate::Type(pt) => {
+                    if !find_lifetimes(&pt.bounded_ty).is_disjoint(&alset) {
+                        return None;
+                    }
+                    pt.bounds = Punctuated::from_iter(
+                        pt.bounds.iter().filter(|b| free(b)).cloned());
+                    if pt.bounds.is_empty() { None } else { Some(wp) }
+                },
+                WherePredicate::Lifetime(pl) => {
+                    if alset.contains(&pl.lifetime) ||
+                        pl.bounds.iter().any(|b| alset.contains(b))
+                    {
+                        None
+                    } else {
+                        Some(wp)
+                    }
+                },
+                _ => Some(wp)
+            }));
+        wc
+    });
+
     let alg = lifetimes_to_generics(alv);
     let rlg = lifetimes_to_generics(rlv);
 
@@ -747,9 +1431,7 @@ fn split_lifetimes(
             lt_token: generics.lt_token,
             gt_token: generics.gt_token,
             params: Punctuated::from_iter(tv.into_iter()),
-            // XXX this assumes that none of the lifetimes are referenced by the
-            // where clause
-            where_clause: generics.where_clause
+            where_clause
         }
     };
 
@@ -803,6 +1485,43 @@ fn expectation_visibility(vis: &Visibility, levels: u32)
     }
 }
 
+/// Report any of a method's type parameters that are bounded by one of its
+/// lifetime parameters, like `T: Decode<'a>`, but not by `'static`.  The
+/// Expectations of a generic method are keyed by type, so they can only be
+/// stored for `'static` types.
+fn check_borrowing_type_params(generics: &Generics) {
+    let lifetimes = generics.lifetimes()
+        .map(|ltd| ltd.lifetime.clone())
+        .collect::<HashSet<_>>();
+    if lifetimes.is_empty() {
+        return;
+    }
+    for tp in generics.type_params() {
+        let mut bounds = tp.bounds.iter().collect::<Vec<_>>();
+        if let Some(wc) = &generics.where_clause {
+            for wp in wc.predicates.iter() {
+                if let WherePredicate::Type(pt) = wp {
+                    if let Type::Path(tpath) = &pt.bounded_ty {
+                        if tpath.qself.is_none() &&
+                            tpath.path.is_ident(&tp.ident)
+                        {
+                            bounds.extend(pt.bounds.iter());
+                        }
+                    }
+                }
+            }
+        }
+        let is_static = bounds.iter().any(|b| matches!(b,
+            TypeParamBound::Lifetime(lt) if lt.ident == "static"));
+        let borrows = bounds.iter()
+            .any(|b| !find_lifetimes_in_tpb(b).is_disjoint(&lifetimes));
+        if borrows && !is_static {
+            compile_error(tp.span(),
+                "Mockall can only mock type parameters that are 'static, even if they are bounded by the method's lifetime parameters.  Add a `+ 'static` bound");
+        }
+    }
+}
+
 /// Extract useful data about a method
 ///
 /// # Arguments
@@ -810,9 +1529,22 @@ fn expectation_visibility(vis: &Visibility, levels: u32)
 /// * `sig`:            Signature of the original method
 /// * `generics`:       Generics of the method's parent trait or structure,
 ///                     _not_ the method itself.
-fn method_types(sig: &Signature, generics: Option<&Generics>) -> MethodTypes {
+/// * `in_trait`:       Whether the method belongs to a trait
+fn method_types(sig: &Signature, generics: Option<&Generics>, in_trait: bool)
+    -> MethodTypes
+{
     let mut is_static = true;
     let ident = &sig.ident;
+    let inputs = demutify(&sig.inputs);
+    // An #[async_trait] method's Expectation deals in the future's output
+    let resugared = resugar_async_trait(sig);
+    let sig = resugared.as_ref().unwrap_or(sig);
+    check_borrowing_type_params(&sig.generics);
+    // A typed receiver, like `self: Pin<&mut Self>`, is just a receiver to the
+    // Expectation
+    let mut untyped = untype_self(sig);
+    name_arg_lifetimes(&mut untyped);
+    let sig = &untyped;
     let (expectation_generics, expectation_inputs, call_exprs) =
         declosurefy(&sig.generics, &sig.inputs);
     let merged_generics = if let Some(g) = generics {
@@ -820,7 +1552,6 @@ fn method_types(sig: &Signature, generics: Option<&Generics>) -> MethodTypes {
     } else {
         sig.generics.clone()
     };
-    let inputs = demutify(&sig.inputs);
     let (no_lt_g, _, rlg) = split_lifetimes(merged_generics,
                                             &sig.inputs, &sig.output);
     let with_ret_lt_g = merge_generics(&no_lt_g, &rlg);
@@ -851,19 +1582,26 @@ fn method_types(sig: &Signature, generics: Option<&Generics>) -> MethodTypes {
                         format_ident!("call")
                     }
                 },
-                _ => format_ident!("call")
+                _ => {
+                    // A return value that borrows from `&mut self` must be
+                    // created from mutable storage.
+                    let mut_receiver = match sig.inputs.first() {
+                        Some(FnArg::Receiver(r)) =>
+                            r.reference.is_some() && r.mutability.is_some(),
+                        _ => false
+                    };
+                    if mut_receiver && borrowify(&mut ty.as_ref().clone()) {
+                        format_ident!("call_mut")
+                    } else {
+                        format_ident!("call")
+                    }
+                }
             }
         }
     };
 
     let is_expectation_generic = expectation_generics.params.iter()
-        .any(|p| {
-            if let GenericParam::Type(_) = p {
-                true
-            } else {
-                false
-            }
-        }) ||
+        .any(|p| !matches!(p, GenericParam::Lifetime(_))) ||
         expectation_generics.where_clause.is_some();
 
     let expectation_ident = format_ident!("Expectation");
@@ -889,7 +1627,7 @@ fn method_types(sig: &Signature, generics: Option<&Generics>) -> MethodTypes {
     }.unwrap();
     let expectation: Type = parse2(expect_ts).unwrap();
     let mut output = sig.output.clone();
-    deimplify(&mut output);
+    deimplify(&mut output, in_trait);
 
     MethodTypes{is_static, is_expectation_generic, expectation,
                 expectation_generics, expectation_inputs, expectations, call,
@@ -909,13 +1647,26 @@ pub fn mock(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
     do_mock(item.into()).into()
 }
 
+/// Mark a method as a deep stub.  It has no effect by itself, but `#[automock]`
+/// and `mock!` will make the method return a nested mock object.
+#[proc_macro_attribute]
+pub fn deep(_attrs: proc_macro::TokenStream, input: proc_macro::TokenStream)
+    -> proc_macro::TokenStream
+{
+    input
+}
+
 #[proc_macro_attribute]
 pub fn automock(attrs: proc_macro::TokenStream, input: proc_macro::TokenStream)
     -> proc_macro::TokenStream
 {
+    let attrs: proc_macro2::TokenStream = attrs.into();
     let input: proc_macro2::TokenStream = input.into();
+    if let Some(reordered) = reorder_async_trait(&attrs, &input) {
+        return reordered.into();
+    }
     let mut output = input.clone();
-    output.extend(do_automock(attrs.into(), input));
+    output.extend(do_automock(attrs, input));
     output.into()
 }
 
@@ -947,6 +1698,59 @@ mod t {
                    quote!(#gm #gm_wc).to_string());
     }
 
+    #[test]
+    fn resugar_async_trait() {
+        let desugared: Signature = parse2(quote!(
+            fn foo<'life0, 'life1, 'life2, 'async_trait, T>(&'life0 self,
+                x: &'life1 T, y: Option<&'life2 str>)
+                -> ::core::pin::Pin<Box<dyn ::core::future::Future<Output=u32>
+                    + ::core::marker::Send + 'async_trait>>
+                where T: 'async_trait + Debug,
+                      T: Send,
+                      'life0: 'async_trait,
+                      'life1: 'async_trait,
+                      'life2: 'async_trait,
+                      Self: Sync + 'async_trait
+        )).unwrap();
+        let expected: Signature = parse2(quote!(
+            async fn foo<T>(&self, x: &T, y: Option<&str>) -> u32
+                where T: Debug,
+                      T: Send
+        )).unwrap();
+        let resugared = super::resugar_async_trait(&desugared).unwrap();
+        assert_eq!(quote!(#expected).to_string(),
+                   quote!(#resugared).to_string());
+    }
+
+    /// The user's own lifetimes stay, even if they look like #[async_trait]'s
+    #[test]
+    fn resugar_async_trait_user_lifetimes() {
+        let desugared: Signature = parse2(quote!(
+            fn foo<'a, 'life0, 'async_trait, T>(&'life0 self, x: &'a T)
+                -> ::core::pin::Pin<Box<dyn ::core::future::Future<
+                    Output=&'life0 u32> + ::core::marker::Send + 'async_trait>>
+                where T: 'async_trait + Foo<'a>,
+                      'a: 'async_trait,
+                      'life0: 'async_trait,
+                      Self: Sync + 'async_trait
+        )).unwrap();
+        let expected: Signature = parse2(quote!(
+            async fn foo<'a, 'life0, T>(&'life0 self, x: &'a T) -> &'life0 u32
+                where T: Foo<'a>
+        )).unwrap();
+        let resugared = super::resugar_async_trait(&desugared).unwrap();
+        assert_eq!(quote!(#expected).to_string(),
+                   quote!(#resugared).to_string());
+    }
+
+    #[test]
+    fn resugar_not_async_trait() {
+        let sig: Signature = parse2(quote!(
+            fn foo<'a>(&'a self) -> Pin<Box<dyn Future<Output=u32> + 'a>>
+        )).unwrap();
+        assert!(super::resugar_async_trait(&sig).is_none());
+    }
+
 // Tests for the method_types function.  But there are no assertions for the
 // call_exprs field, because TokenStream doesn't implement Eq or anything close
 // to it.
@@ -957,7 +1761,7 @@ mod method_types{
     #[test]
     fn base(){
         let tim: TraitItemMethod = parse2(quote!(fn foo(&self);)).unwrap();
-        let mt = method_types(&tim.sig, None);
+        let mt = method_types(&tim.sig, None, true);
         assert!(!mt.is_static);
         assert!(!mt.is_expectation_generic);
         assert_eq!(mt.expectation, parse2(quote!(foo::Expectation)).unwrap());
@@ -978,7 +1782,7 @@ mod method_types{
         let tim: TraitItemMethod = parse2(quote!(
             fn foo<F: Fn(u32) -> u32 + 'static>(&self, f: F) -> u32;
         )).unwrap();
-        let mt = method_types(&tim.sig, None);
+        let mt = method_types(&tim.sig, None, true);
         assert!(!mt.is_static);
         assert!(!mt.is_expectation_generic);
         assert_eq!(mt.expectation,
@@ -1009,7 +1813,7 @@ mod method_types{
         let tim: TraitItemMethod = parse2(quote!(
             fn foo<I: 'static, O: 'static>(&self, i:I) -> O;
         )).unwrap();
-        let mt = method_types(&tim.sig, None);
+        let mt = method_types(&tim.sig, None, true);
         assert!(!mt.is_static);
         assert!(mt.is_expectation_generic);
         assert_eq!(mt.expectation,
@@ -1036,7 +1840,7 @@ mod method_types{
         let tim: TraitItemMethod = parse2(quote!(
             fn foo<'a>(&self, x: &'a X<'a>) -> u32;
         )).unwrap();
-        let mt = method_types(&tim.sig, None);
+        let mt = method_types(&tim.sig, None, true);
         assert!(!mt.is_static);
         assert!(!mt.is_expectation_generic);
         assert_eq!(mt.expectation,
@@ -1063,7 +1867,7 @@ mod method_types{
         let tim: TraitItemMethod = parse2(quote!(
             fn foo<'a>(&self) -> X<'a>;
         )).unwrap();
-        let mt = method_types(&tim.sig, None);
+        let mt = method_types(&tim.sig, None, true);
         assert!(!mt.is_static);
         assert!(!mt.is_expectation_generic);
         assert_eq!(mt.expectation,
@@ -1084,7 +1888,7 @@ mod method_types{
         let tim: TraitItemMethod = parse2(quote!(
             fn foo(&self, x: T) -> T;
         )).unwrap();
-        let mt = method_types(&tim.sig, Some(&struct_generics));
+        let mt = method_types(&tim.sig, Some(&struct_generics), true);
         assert!(!mt.is_static);
         assert!(!mt.is_expectation_generic);
         assert_eq!(mt.expectation,
@@ -1114,7 +1918,7 @@ mod method_types{
         let tim: TraitItemMethod = parse2(quote!(
             fn foo(&self, x: T) -> T;
         )).unwrap();
-        let mt = method_types(&tim.sig, Some(&struct_generics));
+        let mt = method_types(&tim.sig, Some(&struct_generics), true);
         assert!(!mt.is_static);
         assert!(!mt.is_expectation_generic);
         assert_eq!(mt.expectation,
@@ -1143,7 +1947,7 @@ mod method_types{
         let tim: TraitItemMethod = parse2(quote!(
             fn foo<Q: 'static>(&self, q: Q) -> T;
         )).unwrap();
-        let mt = method_types(&tim.sig, Some(&struct_generics));
+        let mt = method_types(&tim.sig, Some(&struct_generics), true);
         assert!(!mt.is_static);
         assert!(mt.is_expectation_generic);
         assert_eq!(mt.expectation,
@@ -1170,19 +1974,42 @@ mod method_types{
         let tim: TraitItemMethod = parse2(quote!(
             fn foo(&self) -> impl Debug + Send;
         )).unwrap();
-        let mt = method_types(&tim.sig, None);
+        let mt = method_types(&tim.sig, None, true);
         assert!(!mt.is_static);
         assert!(!mt.is_expectation_generic);
         assert_eq!(mt.output,
                    parse2(quote!(-> Box<dyn Debug + Send>)).unwrap());
     }
 
+    #[test]
+    fn impl_future() {
+        let tim: TraitItemMethod = parse2(quote!(
+            fn foo(&self) -> impl Future<Output=u32> + Send;
+        )).unwrap();
+        let mt = method_types(&tim.sig, None, true);
+        assert_eq!(mt.output, parse2(quote!(
+            -> ::std::pin::Pin<Box<dyn Future<Output=u32> + Send>>
+        )).unwrap());
+    }
+
+    // Only trait methods pin their futures
+    #[test]
+    fn impl_future_inherent() {
+        let iim: ImplItemMethod = parse2(quote!(
+            fn foo(&self) -> impl Future<Output=u32> + Send { unimplemented!() }
+        )).unwrap();
+        let mt = method_types(&iim.sig, None, false);
+        assert_eq!(mt.output, parse2(quote!(
+            -> Box<dyn Future<Output=u32> + Send>
+        )).unwrap());
+    }
+
     // Methods with mutable arguments must be demutified
     #[test]
     fn mutable_args() {
         let tim: TraitItemMethod = parse2(quote!(
             fn foo(&mut self, mut x: u32);)).unwrap();
-        let mt = method_types(&tim.sig, None);
+        let mt = method_types(&tim.sig, None, true);
         assert!(!mt.is_static);
         assert!(!mt.is_expectation_generic);
         let inputs_vec: Vec<FnArg> = vec![
@@ -1204,7 +2031,7 @@ mod method_types{
         let tim: TraitItemMethod = parse2(quote!(
             fn foo(&self) -> &str;
         )).unwrap();
-        let mt = method_types(&tim.sig, None);
+        let mt = method_types(&tim.sig, None, true);
         assert!(!mt.is_expectation_generic);
         assert_eq!(mt.output,
                    parse2(quote!(-> &str)).unwrap());
@@ -1215,7 +2042,7 @@ mod method_types{
         let tim: TraitItemMethod = parse2(quote!(
             fn foo(&mut self) -> &mut u32;
         )).unwrap();
-        let mt = method_types(&tim.sig, None);
+        let mt = method_types(&tim.sig, None, true);
         assert!(!mt.is_static);
         assert!(!mt.is_expectation_generic);
         assert_eq!(mt.call, "call_mut");
@@ -1227,7 +2054,7 @@ mod method_types{
         let tim: TraitItemMethod = parse2(quote!(
             fn foo() -> u32;
         )).unwrap();
-        let mt = method_types(&tim.sig, None);
+        let mt = method_types(&tim.sig, None, true);
         assert!(mt.is_static);
         assert!(!mt.is_expectation_generic);
         assert_eq!(mt.expectation,
@@ -12,16 +12,46 @@ pub(crate) struct Mock {
     pub(crate) vis: syn::Visibility,
     pub(crate) name: syn::Ident,
     pub(crate) generics: syn::Generics,
+    // The Mock struct's inherent associated constants
+    pub(crate) consts: Vec<syn::ImplItemConst>,
     // The Mock struct's inherent methods.  The blocks will all be empty.
     pub(crate) methods: Vec<syn::ImplItemMethod>,
     pub(crate) traits: Vec<syn::ItemTrait>
 }
 
 impl Mock {
+    /// Check that no `#[deep]` method's `<method>_mock` accessor has the same
+    /// name as one of the mock's other methods.
+    fn check_deep_accessors(&self) {
+        let methods = self.methods.iter()
+            .map(|m| (&m.attrs[..], &m.sig))
+            .chain(self.traits.iter()
+                .flat_map(|trait_| trait_.items.iter())
+                .filter_map(|item| match item {
+                    syn::TraitItem::Method(m) => Some((&m.attrs[..], &m.sig)),
+                    _ => None
+                })
+            ).collect::<Vec<_>>();
+        for (attrs, sig) in methods.iter() {
+            if !attrs.iter().any(is_deep_attr) {
+                continue;
+            }
+            let accessor = format_ident!("{}_mock", sig.ident);
+            if let Some((_, other)) = methods.iter()
+                .find(|(_, other)| other.ident == accessor)
+            {
+                compile_error(other.ident.span(),
+                    "This method's name collides with the <method>_mock accessor of a #[deep] method.  Rename one of them");
+            }
+        }
+    }
+
     pub(crate) fn gen(&self) -> TokenStream {
+        self.check_deep_accessors();
         let mut output = TokenStream::new();
         let mut mock_body = TokenStream::new();
         let mut cp_body = TokenStream::new();
+        let mut wait_body = TokenStream::new();
         let mut has_new = false;
         let mock_struct_name = gen_mock_ident(&self.name);
         let mock_mod_ident = gen_mod_ident(&self.name, None);
@@ -30,11 +60,12 @@ impl Mock {
         }).collect::<Vec<_>>();
         // generate the mock structure
         gen_struct(&self.attrs[..], &mock_struct_name, &self.vis, &self.name,
-                   &self.generics, &subs, &self.methods)
+                   &self.generics, &subs, &self.methods, false, false)
             .to_tokens(&mut output);
         // generate sub structures
         for trait_ in self.traits.iter() {
             let mut sub_cp_body = TokenStream::new();
+            let mut sub_wait_body = TokenStream::new();
             let sub_mock = format_ident!("{}_{}", &self.name, &trait_.ident);
             let sub_struct = format_ident!("{}_expectations", &trait_.ident);
             let mod_ident = gen_mod_ident(&self.name, Some(&trait_.ident));
@@ -46,20 +77,22 @@ impl Mock {
                 }
             }).collect::<Vec<_>>();
             let vis = syn::Visibility::Inherited;
+            let eager_async = !trait_.attrs.iter().any(is_async_trait_attr);
             gen_struct(&[], &mock_struct_name, &vis, &sub_mock,
-                       &self.generics, &[], &methods)
+                       &self.generics, &[], &methods, true, eager_async)
                 .to_tokens(&mut output);
             let mock_sub_name = gen_mock_ident(&sub_mock);
             for meth in methods {
                 has_new |= meth.borrow().sig.ident == "new";
                 let generics = merge_generics(&self.generics, &trait_.generics);
-                let (_, _, cp) = gen_mock_method(&mock_struct_name,
+                let (_, _, cp, wait) = gen_mock_method(&mock_struct_name,
                                                  Some(&mod_ident),
                                                  &meth.attrs[..],
                                                  &meth.vis, &meth.vis,
                                                  &meth.borrow().sig, None,
-                                                 &generics);
+                                                 &generics, eager_async);
                 cp.to_tokens(&mut sub_cp_body);
+                wait.to_tokens(&mut sub_wait_body);
             }
             let (ig, tg, wc) = self.generics.split_for_impl();
             quote!(impl #ig #mock_sub_name #tg #wc {
@@ -68,23 +101,41 @@ impl Mock {
                 fn checkpoint(&mut self) {
                     #sub_cp_body
                 }
+
+                /// Block until all current expectations for all methods have
+                /// been satisfied.
+                fn wait_until_satisfied(&self,
+                    __mockall_deadline: ::std::time::Instant)
+                    -> ::std::result::Result<(), ::mockall::Timeout>
+                {
+                    #sub_wait_body
+                    Ok(())
+                }
             }).to_tokens(&mut output);
             quote!(self.#sub_struct.checkpoint();).to_tokens(&mut cp_body);
+            quote!(
+                self.#sub_struct.wait_until_satisfied(__mockall_deadline)?;
+            ).to_tokens(&mut wait_body);
+        }
+        // generate associated constants on the mock structure itself
+        for ic in self.consts.iter() {
+            ic.to_tokens(&mut mock_body);
         }
         // generate methods on the mock structure itself
         for meth in self.methods.iter() {
             has_new |= meth.sig.ident == "new";
-            let (mm, em, cp) = gen_mock_method(&mock_struct_name,
+            let (mm, em, cp, wait) = gen_mock_method(&mock_struct_name,
                                                Some(&mock_mod_ident),
                                                &meth.attrs[..],
                                                &meth.vis, &meth.vis,
                                                &meth.sig, None,
-                                               &self.generics);
+                                               &self.generics, false);
             // For inherent methods, use the same visibility for the mock and
             // expectation method as for the original.
             mm.to_tokens(&mut mock_body);
             em.to_tokens(&mut mock_body);
             cp.to_tokens(&mut cp_body);
+            wait.to_tokens(&mut wait_body);
         }
         // generate the mock struct's inherent methods
         quote!(
@@ -114,6 +165,20 @@ impl Mock {
         let (ig, tg, wc) = self.generics.split_for_impl();
         quote!(impl #ig #mock_struct_name #tg #wc {#mock_body})
             .to_tokens(&mut output);
+        quote!(
+            impl #ig ::mockall::WaitUntilSatisfied for #mock_struct_name #tg #wc
+            {
+                fn wait_until_satisfied(&self,
+                    __mockall_timeout: ::std::time::Duration)
+                    -> ::std::result::Result<(), ::mockall::Timeout>
+                {
+                    let __mockall_deadline =
+                        ::std::time::Instant::now() + __mockall_timeout;
+                    #wait_body
+                    Ok(())
+                }
+            }
+        ).to_tokens(&mut output);
         for trait_ in self.traits.iter() {
             mock_trait_methods(&self.name, &self.generics, &trait_, &self.vis)
                 .to_tokens(&mut output);
@@ -133,10 +198,14 @@ impl Parse for Mock {
 
         let impl_content;
         let _brace_token = braced!(impl_content in input);
+        let mut consts = Vec::new();
         let mut methods = Vec::new();
         while !impl_content.is_empty() {
             let method: syn::TraitItem = impl_content.parse()?;
             match &method {
+                syn::TraitItem::Const(tic) => {
+                    consts.push(tic2iic(tic, &vis)?)
+                },
                 syn::TraitItem::Method(meth) => {
                     methods.push(tim2iim(meth, &vis))
                 },
@@ -149,16 +218,19 @@ impl Parse for Mock {
         let mut traits = Vec::new();
         while !input.is_empty() {
             let trait_: syn::ItemTrait = input.parse()?;
-            traits.push(trait_);
+            traits.push(substitute_own_types(&trait_));
         }
 
-        Ok(Mock{attrs, vis, name, generics, methods, traits})
+        Ok(Mock{attrs, vis, name, generics, consts, methods, traits})
     }
 }
 
 fn format_attrs(attrs: &[syn::Attribute], include_docs: bool) -> TokenStream {
     let mut out = TokenStream::new();
     for attr in attrs {
+        if is_deep_attr(attr) {
+            continue;
+        }
         let is_doc = attr.path.get_ident().map(|i| i == "doc").unwrap_or(false);
         if !is_doc || include_docs {
             attr.to_tokens(&mut out);
@@ -167,6 +239,18 @@ fn format_attrs(attrs: &[syn::Attribute], include_docs: bool) -> TokenStream {
     out
 }
 
+/// Should this method's mock call its Expectation as soon as it's called, and
+/// return the output in a future that `must_be_polled` can track?  A trait's
+/// `async fn` does, unless `#[async_trait]` will rewrite its impl, and so does
+/// a method that `#[async_trait]` has already rewritten.  But only if the
+/// Expectation returns the output by value.
+fn tracks_async(sig: &syn::Signature, meth_types: &MethodTypes,
+                eager_async: bool) -> bool
+{
+    ((sig.asyncness.is_some() && eager_async) || is_async_trait(sig)) &&
+        returns_by_value(&meth_types.output, meth_types.is_static)
+}
+
 /// Generate a mock method and its expectation method
 ///
 /// # Arguments
@@ -183,6 +267,8 @@ fn format_attrs(attrs: &[syn::Attribute], include_docs: bool) -> TokenStream {
 ///                     object, if any.
 /// * `generics`:       Generics of the method's parent trait or structure,
 ///                     _not_ the method itself.
+/// * `eager_async`:    Whether an `async fn` may call its Expectation eagerly
+#[allow(clippy::too_many_arguments)]
 fn gen_mock_method(mock_struct_name: &syn::Ident,
                    mod_ident: Option<&syn::Ident>,
                    meth_attrs: &[syn::Attribute],
@@ -190,34 +276,54 @@ fn gen_mock_method(mock_struct_name: &syn::Ident,
                    expect_vis: &syn::Visibility,
                    sig: &syn::Signature,
                    sub: Option<&syn::Ident>,
-                   generics: &syn::Generics)
-    -> (TokenStream, TokenStream, TokenStream)
+                   generics: &syn::Generics,
+                   eager_async: bool)
+    -> (TokenStream, TokenStream, TokenStream, TokenStream)
 {
     assert!(sig.variadic.is_none(),
         "MockAll does not yet support variadic functions");
     let mut mock_output = TokenStream::new();
     let mut expect_output = TokenStream::new();
     let mut cp_output = TokenStream::new();
+    let mut wait_output = TokenStream::new();
     let constness = sig.constness;
     let unsafety = sig.unsafety;
     let asyncness = sig.asyncness;
     let abi = &sig.abi;
     let fn_token = &sig.fn_token;
     let ident = &sig.ident;
-    let meth_types = method_types(sig, Some(generics));
+    let meth_types = method_types(sig, Some(generics), sub.is_some());
     let merged_g = merge_generics(&generics, &meth_types.expectation_generics);
     let inputs = &meth_types.inputs;
-    let output = &meth_types.output;
+    // A trait's mock method must keep any `impl Trait` return type from the
+    // trait's signature, even though its expectation returns a boxed trait
+    // object.
+    let output = if sub.is_some() {
+        &sig.output
+    } else {
+        &meth_types.output
+    };
     let attrs_with_docs = format_attrs(meth_attrs, true);
     let attrs_nodocs = format_attrs(meth_attrs, false);
 
-    // First the mock method
-    {
+    // First the mock method's signature.  An eager `async fn` returns the
+    // Expectation's output in a future that can be tracked.
+    let tracked = tracks_async(sig, &meth_types, eager_async);
+    let mock_sig = {
         let (ig, _, wc) = sig.generics.split_for_impl();
-        quote!(#attrs_with_docs #meth_vis #constness #unsafety #asyncness #abi
-               #fn_token #ident #ig (#inputs) #output #wc)
-            .to_tokens(&mut mock_output);
-    }
+        if asyncness.is_some() && tracked {
+            let fut_output = match output {
+                syn::ReturnType::Default => quote!(()),
+                syn::ReturnType::Type(_, ty) => quote!(#ty)
+            };
+            quote!(#attrs_with_docs #meth_vis #constness #unsafety #abi
+                   #fn_token #ident #ig (#inputs)
+                   -> impl ::std::future::Future<Output = #fut_output> #wc)
+        } else {
+            quote!(#attrs_with_docs #meth_vis #constness #unsafety #asyncness
+                   #abi #fn_token #ident #ig (#inputs) #output #wc)
+        }
+    };
 
     let expectation = &meth_types.expectation;
     let call = &meth_types.call;
@@ -229,6 +335,14 @@ fn gen_mock_method(mock_struct_name: &syn::Ident,
     } else {
         quote!(self.#ident)
     };
+
+    if let Some(deep) = deep_stub(meth_attrs, sig) {
+        let (body, expect_output, cp_output, wait_output) = gen_deep_stub(
+            &deep, &expect_obj_name, ident, &attrs_nodocs, expect_vis);
+        mock_sig.to_tokens(&mut mock_output);
+        body.to_tokens(&mut mock_output);
+        return (mock_output, expect_output, cp_output, wait_output);
+    }
     for p in inputs.iter() {
         match p {
             syn::FnArg::Receiver(_) => {
@@ -253,27 +367,38 @@ fn gen_mock_method(mock_struct_name: &syn::Ident,
     let call_turbofish = tg.as_turbofish();
     let no_match_msg = format!("{}::{}: No matching expectation found",
         mock_struct_name, ident);
-    if meth_types.is_static {
+    let no_match = quote!(
+        unwrap_or_else(|| ::mockall::violation(#no_match_msg.to_owned()))
+    );
+    // Record any violation by another thread, so it can be reported again
+    // by the owning thread even if the calling thread's panic gets swallowed.
+    let body = if meth_types.is_static {
         quote!({
-            {
-                let __mockall_guard = #mod_ident::#ident::EXPECTATIONS
-                    .lock().unwrap();
-                /*
-                 * TODO: catch panics, then gracefully release the mutex so it
-                 * won't be poisoned.  This requires bounding any generic
-                 * parameters with UnwindSafe
-                 */
-                /* std::panic::catch_unwind(|| */
-                __mockall_guard.#call#call_turbofish(#call_exprs)
-                /*)*/
-            }.expect(#no_match_msg)
-            /*}.unwrap()*/
+            let __mockall_failures = #mod_ident::#ident::failures();
+            __mockall_failures.catch(move || {
+                #mod_ident::#ident::#call#call_turbofish(#call_exprs)
+                .#no_match
+            })
         })
     } else {
         quote!({
-            #expect_obj_name.#call#call_turbofish(#call_exprs)
-            .expect(#no_match_msg)
+            let __mockall_failures = #expect_obj_name.failures();
+            __mockall_failures.catch(move || {
+                #expect_obj_name.#call#call_turbofish(#call_exprs)
+                .#no_match
+            })
         })
+    };
+    if is_async_trait(sig) {
+        // The trait was already rewritten by #[async_trait], so we must box
+        // the future ourselves.
+        if tracked {
+            quote!(#mock_sig { Box::pin(#body) })
+        } else {
+            quote!(#mock_sig { Box::pin(async move #body) })
+        }
+    } else {
+        quote!(#mock_sig #body)
     }.to_tokens(&mut mock_output);
 
     // Then the expectation method
@@ -315,7 +440,8 @@ fn gen_mock_method(mock_struct_name: &syn::Ident,
         )
     }.to_tokens(&mut expect_output);
 
-    // Finally this method's contribution to the checkpoint method
+    // Finally this method's contribution to the checkpoint and
+    // wait_until_satisfied methods
     if meth_types.is_static {
         // Don't checkpoint static methods.  They get checkpointed by their
         // context objects instead.
@@ -323,17 +449,72 @@ fn gen_mock_method(mock_struct_name: &syn::Ident,
     } else {
         quote!(#attrs_nodocs { #expect_obj_name.checkpoint(); })
     }.to_tokens(&mut cp_output);
+    if !meth_types.is_static {
+        quote!(#attrs_nodocs {
+            #expect_obj_name.wait_until_satisfied(__mockall_deadline)?;
+        }).to_tokens(&mut wait_output);
+    }
 
-    (mock_output, expect_output, cp_output)
+    (mock_output, expect_output, cp_output, wait_output)
 }
 
+/// Generate the body, accessor, checkpoint, and wait_until_satisfied code for
+/// a deep stub method.  `field` is the parent mock's field that holds the
+/// nested mock object.
+fn gen_deep_stub(deep: &DeepStub,
+                 field: &TokenStream,
+                 ident: &syn::Ident,
+                 attrs_nodocs: &TokenStream,
+                 expect_vis: &syn::Visibility)
+    -> (TokenStream, TokenStream, TokenStream, TokenStream)
+{
+    let mock = &deep.mock;
+    let remaining = quote!(
+        __mockall_deadline.saturating_duration_since(::std::time::Instant::now())
+    );
+    let (body, nested, accessor) = match deep.kind {
+        DeepKind::Boxed => (
+            // Hand out the nested mock, leaving a fresh one to be configured
+            // for the next call.
+            quote!({
+                Box::new(::std::mem::take(&mut *#field.lock().unwrap()))
+            }),
+            quote!(#field.lock().unwrap()),
+            quote!(#field.get_mut().unwrap())
+        ),
+        DeepKind::Ref => (quote!({ &#field }), field.clone(),
+                          quote!(&mut #field)),
+        DeepKind::RefMut => (quote!({ &mut #field }), field.clone(),
+                             quote!(&mut #field))
+    };
+    let mock_ident = format_ident!("{}_mock", ident);
+    let doc = format!("Access the nested mock object that the `{}` method \
+                      will return.", ident);
+    let expect_output = quote!(
+        #attrs_nodocs
+        #[doc = #doc]
+        #expect_vis fn #mock_ident(&mut self) -> &mut #mock {
+            #accessor
+        }
+    );
+    let cp_output = quote!(#attrs_nodocs { #nested.checkpoint(); });
+    let wait_output = quote!(#attrs_nodocs {
+        <#mock as ::mockall::WaitUntilSatisfied>::wait_until_satisfied(
+            &#nested, #remaining)?;
+    });
+    (body, expect_output, cp_output, wait_output)
+}
+
+#[allow(clippy::too_many_arguments)]
 fn gen_struct<T>(attrs: &[syn::Attribute],
                  mock_ident: &syn::Ident,
                  vis: &syn::Visibility,
                  ident: &syn::Ident,
                  generics: &syn::Generics,
                  subs: &[(String, syn::Generics)],
-                 methods: &[T]) -> TokenStream
+                 methods: &[T],
+                 in_trait: bool,
+                 eager_async: bool) -> TokenStream
     where T: Borrow<syn::ImplItemMethod>
 {
     let mut output = TokenStream::new();
@@ -360,11 +541,23 @@ fn gen_struct<T>(attrs: &[syn::Attribute],
     for meth in methods.iter() {
         let attrs = format_attrs(&meth.borrow().attrs, false);
         let method_ident = &meth.borrow().sig.ident;
-        let meth_types = method_types(&meth.borrow().sig, Some(generics));
+        if let Some(deep) = deep_stub(&meth.borrow().attrs, &meth.borrow().sig)
+        {
+            // Deep stubs store a nested mock object instead of Expectations
+            let field_ty = deep.field_ty();
+            quote!(#attrs #method_ident: #field_ty,).to_tokens(&mut body);
+            quote!(#attrs #method_ident: ::std::default::Default::default(),)
+                .to_tokens(&mut default_body);
+            continue;
+        }
+        let meth_types = method_types(&meth.borrow().sig, Some(generics),
+                                      in_trait);
         let expect_obj = &meth_types.expect_obj;
         let expectations = &meth_types.expectations;
         let meth_ident = &meth.borrow().sig.ident;
         let output = &meth_types.output;
+        let is_async = tracks_async(&meth.borrow().sig, &meth_types,
+                                    eager_async);
 
         let expect_vis = expectation_visibility(&meth.borrow().vis, 2);
         let mut macro_g = TokenStream::new();
@@ -380,7 +573,7 @@ fn gen_struct<T>(attrs: &[syn::Attribute],
                          &meth_types.expect_obj,
                          Some(&generics), &meth_types.expectation_generics,
                          meth_ident, meth_ident, Some(&mock_ident), output,
-                         &expect_vis, 2).to_tokens(&mut mod_body);
+                         is_async, &expect_vis, 2).to_tokens(&mut mod_body);
 
         if !meth_types.is_static {
             quote!(#attrs #method_ident: #mod_ident::#expect_obj,)
@@ -408,10 +601,8 @@ fn gen_struct<T>(attrs: &[syn::Attribute],
                 quote!(#phident: ::std::marker::PhantomData<#ty>,)
                     .to_tokens(&mut body);
             },
-            syn::GenericParam::Const(_) => {
-                compile_error(param.span(),
-                    "#automock does not yet support generic constants");
-            }
+            // Unused const parameters are allowed
+            syn::GenericParam::Const(_) => continue
         }
         quote!(#phident: ::std::marker::PhantomData,)
             .to_tokens(&mut default_body);
@@ -462,16 +653,26 @@ fn mock_trait_methods(struct_ident: &syn::Ident,
     let mut mock_body = TokenStream::new();
     let mut expect_body = TokenStream::new();
     let mock_ident = gen_mock_ident(&struct_ident);
+    let eager_async = !item.attrs.iter().any(is_async_trait_attr);
 
     for trait_item in item.items.iter() {
         match trait_item {
-            syn::TraitItem::Const(_) => {
-                // Nothing to implement
+            syn::TraitItem::Const(tic) => {
+                if let Some((eq_token, expr)) = &tic.default {
+                    let attrs = format_attrs(&tic.attrs, true);
+                    let ident = &tic.ident;
+                    let ty = &tic.ty;
+                    quote!(#attrs const #ident: #ty #eq_token #expr;)
+                        .to_tokens(&mut mock_body);
+                } else {
+                    compile_error(tic.span(),
+                        "Associated constants must be given a value for mocking.");
+                }
             },
             syn::TraitItem::Method(meth) => {
                 let mod_ident = gen_mod_ident(&struct_ident, Some(&item.ident));
                 let generics = merge_generics(&struct_generics, &item.generics);
-                let (mock_meth, expect_meth, _cp) = gen_mock_method(
+                let (mock_meth, expect_meth, _cp, _wait) = gen_mock_method(
                     &mock_ident,
                     Some(&mod_ident),
                     &meth.attrs[..],
@@ -479,7 +680,8 @@ fn mock_trait_methods(struct_ident: &syn::Ident,
                     vis,
                     &meth.sig,
                     Some(&item.ident),
-                    &generics
+                    &generics,
+                    eager_async
                 );
                 // trait methods must have inherited visibility.  Expectation
                 // methods should have public, for lack of any clearer option.
@@ -487,10 +689,6 @@ fn mock_trait_methods(struct_ident: &syn::Ident,
                 expect_meth.to_tokens(&mut expect_body);
             },
             syn::TraitItem::Type(ty) => {
-                if !ty.generics.params.is_empty() {
-                    compile_error(ty.generics.span(),
-                        "Mockall does not yet support generic associated types");
-                }
                 if ty.default.is_some() {
                     // Trait normally can't get here (unless the
                     // associated_type_defaults feature is enabled), but we can
@@ -513,7 +711,11 @@ fn mock_trait_methods(struct_ident: &syn::Ident,
         }
     }
 
-    // Put all mock methods in one impl block
+    // Put all mock methods in one impl block.  If the trait is to be
+    // rewritten by #[async_trait], then the impl block must be too.
+    for attr in item.attrs.iter().filter(|a| is_async_trait_attr(a)) {
+        attr.to_tokens(&mut output);
+    }
     item.unsafety.to_tokens(&mut output);
     let ident = &item.ident;
     let (s_ig, s_sg, s_wc) = struct_generics.split_for_impl();
@@ -532,6 +734,28 @@ fn mock_trait_methods(struct_ident: &syn::Ident,
     output
 }
 
+fn tic2iic(c: &syn::TraitItemConst, vis: &syn::Visibility)
+    -> syn::parse::Result<syn::ImplItemConst>
+{
+    if let Some((eq_token, expr)) = &c.default {
+        Ok(syn::ImplItemConst{
+            attrs: c.attrs.clone(),
+            vis: vis.clone(),
+            defaultness: None,
+            const_token: c.const_token,
+            ident: c.ident.clone(),
+            colon_token: c.colon_token,
+            ty: c.ty.clone(),
+            eq_token: *eq_token,
+            expr: expr.clone(),
+            semi_token: c.semi_token
+        })
+    } else {
+        Err(syn::Error::new(c.span(),
+            "Associated constants must be given a value for mocking."))
+    }
+}
+
 fn tim2iim(m: &syn::TraitItemMethod, vis: &syn::Visibility)
     -> syn::ImplItemMethod
 {
@@ -601,4 +825,21 @@ mod t {
         assert!(output.contains(r#"#[doc="Traitmethoddocs"]fnbar"#));
     }
 
+    /// A #[deep] method's accessor may not collide with another method
+    #[test]
+    #[should_panic(expected = "collides with the <method>_mock accessor")]
+    fn deep_accessor_collision() {
+        let code = r#"
+            Foo {
+                fn session_mock(&self) -> u32;
+            }
+            trait Client {
+                #[deep]
+                fn session(&self) -> Box<dyn Session>;
+            }
+        "#;
+        let ts = proc_macro2::TokenStream::from_str(code).unwrap();
+        do_mock(ts);
+    }
+
 }